@@ -6,7 +6,7 @@ use bevy_egui::{egui, EguiContexts, EguiPlugin};
 
 use bevy_extrude_mesh::extrude;
 use bevy_extrude_mesh::extrude::ExtrudeShape;
-use bevy_extrude_mesh::bezier::{BezierCurve};
+use bevy_extrude_mesh::bezier::{BezierCurve, Falloff};
 
 fn main() {
     App::new()
@@ -51,6 +51,9 @@ struct MeshExtrusionParameters {
     control_points: Vec<Vec3>,
     old_control_points: Vec<Vec3>,
     subdivisions: u32,
+    /// Dragging a control point drags the others within this distance along, see [`BezierCurve::move_point_soft`].
+    soft_radius: f32,
+    falloff: Falloff,
     is_dirty: bool,
 }
 
@@ -66,6 +69,8 @@ impl Default for MeshExtrusionParameters {
             old_control_points: control_points.clone(),
             control_points,
             subdivisions: 15,
+            soft_radius: 0.,
+            falloff: Falloff::default(),
             is_dirty: false,
         }
     }
@@ -106,8 +111,7 @@ fn spawn_extruded_mesh(
 ) {
     if let Some(gltf) = gltf_assets.get(&assets.track) {
         let track_gltf_mesh = gltf_mesh_assets.get(&gltf.named_meshes["TrackCrossSection"]).unwrap();
-        let track_mesh = meshes.get(&track_gltf_mesh.primitives[0].mesh).unwrap();
-        let extrude_shape = ExtrudeShape::from_mesh(track_mesh);
+        let extrude_shape = ExtrudeShape::from_gltf_mesh(track_gltf_mesh, &meshes);
 
         let bezier = BezierCurve::new(params.control_points.clone(), None);
         let path = bezier.generate_path(params.subdivisions);
@@ -153,7 +157,7 @@ fn spawn_control_point_spheres(
 fn update_extruded_mesh(
     mut meshes: ResMut<Assets<Mesh>>,
     mut params: ResMut<MeshExtrusionParameters>,
-    gizmo_spheres_query: Query<(&Transform, &BezierControlGizmoSphereMarker)>,
+    mut gizmo_spheres_query: Query<(&mut Transform, &BezierControlGizmoSphereMarker)>,
     extruded_mesh_handle: Option<Res<ExtrudedMeshHandle>>,
 
     assets: Option<Res<TrackAssets>>,
@@ -166,15 +170,25 @@ fn update_extruded_mesh(
 
     if let Some(gltf) = gltf_assets.get(&assets.unwrap().track) {
         let track_gltf_mesh = gltf_mesh_assets.get(&gltf.named_meshes["TrackCrossSection"]).unwrap();
-        let track_mesh = meshes.get(&track_gltf_mesh.primitives[0].mesh).unwrap();
 
         let mut new_sphere_positions = vec![Vec3::ZERO; 4];
         for (transform, marker) in gizmo_spheres_query.iter() {
             new_sphere_positions[marker.0] = transform.translation;
         }
 
+        // Soft selection: when a single sphere was dragged, the others follow it by their falloff
+        let moved: Vec<usize> = (0..new_sphere_positions.len()).filter(|&i| new_sphere_positions[i] != params.old_control_points[i]).collect();
+        if let ([index], true) = (&moved[..], params.soft_radius > 0.) {
+            let mut bezier = BezierCurve::new(params.old_control_points.clone(), None);
+            bezier.move_point_soft(*index, new_sphere_positions[*index] - params.old_control_points[*index], params.soft_radius, params.falloff);
+            new_sphere_positions = bezier.points().to_vec();
+            for (mut transform, marker) in gizmo_spheres_query.iter_mut() {
+                transform.translation = new_sphere_positions[marker.0];
+            }
+        }
+
         if new_sphere_positions != params.old_control_points || params.is_dirty {
-            let extrude_shape = ExtrudeShape::from_mesh(track_mesh);
+            let extrude_shape = ExtrudeShape::from_gltf_mesh(track_gltf_mesh, &meshes);
             let mut mesh = meshes.get_mut(&extruded_mesh_handle.unwrap().0).unwrap();
 
             let bezier = BezierCurve::new(new_sphere_positions.clone(), None);
@@ -199,6 +213,16 @@ fn properties_ui(
         if old_subdivisions != params.subdivisions {
             params.is_dirty = true; // mark for mesh update
         }
+
+        // Soft selection
+        ui.add(egui::Slider::new(&mut params.soft_radius, 0f32..=20f32).text("Soft selection radius"));
+        egui::ComboBox::from_label("Falloff")
+            .selected_text(format!("{:?}", params.falloff))
+            .show_ui(ui, |ui| {
+                for falloff in [Falloff::Linear, Falloff::Smooth, Falloff::Gaussian] {
+                    ui.selectable_value(&mut params.falloff, falloff, format!("{falloff:?}"));
+                }
+            });
     });
 }
 
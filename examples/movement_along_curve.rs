@@ -103,8 +103,7 @@ fn spawn_extruded_mesh(
 ) {
     if let Some(gltf) = gltf_assets.get(&assets.track) {
         let track_gltf_mesh = gltf_mesh_assets.get(&gltf.named_meshes["TestShape"]).unwrap();
-        let track_mesh = meshes.get(&track_gltf_mesh.primitives[0].mesh).unwrap();
-        let extrude_shape = ExtrudeShape::from_mesh(track_mesh);
+        let extrude_shape = ExtrudeShape::from_gltf_mesh(track_gltf_mesh, &meshes);
 
         let control_points = vec![
             Vec3::new(0., 0., 0.,),
@@ -0,0 +1,57 @@
+use bevy::render::mesh::VertexAttributeValues;
+
+macro_rules! map_values {
+    ($values:expr, $f:expr, $($variant:ident),*) => {
+        match $values {
+            $(VertexAttributeValues::$variant(values) => VertexAttributeValues::$variant($f(values)),)*
+        }
+    };
+}
+
+/// Builds a new attribute from the values at `indices`, in that order. Used to replicate cross-section
+/// attributes per edge loop, and to reorder or merge vertices.
+pub(crate) fn select_values(values: &VertexAttributeValues, indices: impl Iterator<Item = usize> + Clone) -> VertexAttributeValues {
+    fn select<T: Copy>(values: &[T], indices: impl Iterator<Item = usize>) -> Vec<T> {
+        indices.map(|i| values[i]).collect()
+    }
+
+    map_values!(values, |values: &Vec<_>| select(values, indices.clone()),
+        Float32, Sint32, Uint32, Float32x2, Sint32x2, Uint32x2, Float32x3, Sint32x3, Uint32x3, Float32x4, Sint32x4, Uint32x4,
+        Sint16x2, Snorm16x2, Uint16x2, Unorm16x2, Sint16x4, Snorm16x4, Uint16x4, Unorm16x4,
+        Sint8x2, Snorm8x2, Uint8x2, Unorm8x2, Sint8x4, Snorm8x4, Uint8x4, Unorm8x4)
+}
+
+/// The values of `a` followed by those of `b`, if both have the same format.
+pub(crate) fn concat_values(a: &VertexAttributeValues, b: &VertexAttributeValues) -> Option<VertexAttributeValues> {
+    macro_rules! concat_variants {
+        ($($variant:ident),*) => {
+            match (a, b) {
+                $((VertexAttributeValues::$variant(a), VertexAttributeValues::$variant(b)) => Some(VertexAttributeValues::$variant([a.as_slice(), b.as_slice()].concat())),)*
+                _ => None,
+            }
+        };
+    }
+
+    concat_variants!(
+        Float32, Sint32, Uint32, Float32x2, Sint32x2, Uint32x2, Float32x3, Sint32x3, Uint32x3, Float32x4, Sint32x4, Uint32x4,
+        Sint16x2, Snorm16x2, Uint16x2, Unorm16x2, Sint16x4, Snorm16x4, Uint16x4, Unorm16x4,
+        Sint8x2, Snorm8x2, Uint8x2, Unorm8x2, Sint8x4, Snorm8x4, Uint8x4, Unorm8x4)
+}
+
+/// Appends the values of `b` to `a`, returning false and leaving `a` as is if the formats differ.
+pub(crate) fn extend_values(a: &mut VertexAttributeValues, b: &VertexAttributeValues) -> bool {
+    macro_rules! extend_variants {
+        ($($variant:ident),*) => {
+            match (a, b) {
+                $((VertexAttributeValues::$variant(a), VertexAttributeValues::$variant(b)) => a.extend_from_slice(b),)*
+                _ => return false,
+            }
+        };
+    }
+
+    extend_variants!(
+        Float32, Sint32, Uint32, Float32x2, Sint32x2, Uint32x2, Float32x3, Sint32x3, Uint32x3, Float32x4, Sint32x4, Uint32x4,
+        Sint16x2, Snorm16x2, Uint16x2, Unorm16x2, Sint16x4, Snorm16x4, Uint16x4, Unorm16x4,
+        Sint8x2, Snorm8x2, Uint8x2, Unorm8x2, Sint8x4, Snorm8x4, Uint8x4, Unorm8x4);
+    true
+}
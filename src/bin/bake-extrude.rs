@@ -0,0 +1,163 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::process::ExitCode;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy_extrude_mesh::composite::CompositeCurve;
+use bevy_extrude_mesh::extrude::{self, ExtrudeShape};
+use bevy_extrude_mesh::{export, interchange};
+
+const USAGE: &str = "usage: bake-extrude <curve.json|curve.csv> <shape.glb> <output.glb|output.obj> [--subdivisions N] [--waypoints]
+
+Extrudes the cross-section in shape.glb (the meshes in its scene, placed by their nodes and merged)
+along the curve and writes the result. The curve file lists control points (3n + 1 of them,
+consecutive segments sharing their joints), or with --waypoints points for a smooth curve to pass
+through.";
+
+const DEFAULT_SUBDIVISIONS: u32 = 20;
+
+struct Arguments {
+    curve: String,
+    shape: String,
+    output: String,
+    subdivisions: u32,
+    waypoints: bool,
+}
+
+fn main() -> ExitCode {
+    let arguments = match parse_arguments(std::env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(message) => {
+            eprintln!("{message}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match bake(&arguments) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("bake-extrude: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<Arguments, String> {
+    let mut files = Vec::new();
+    let mut subdivisions = DEFAULT_SUBDIVISIONS;
+    let mut waypoints = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--subdivisions" => {
+                subdivisions = args.next().and_then(|value| value.parse().ok()).ok_or("--subdivisions needs a number")?;
+            }
+            "--waypoints" => waypoints = true,
+            _ => files.push(arg),
+        }
+    }
+
+    let [curve, shape, output]: [String; 3] = files.try_into().map_err(|_| "expected a curve, a shape and an output file")?;
+    Ok(Arguments {
+        curve,
+        shape,
+        output,
+        subdivisions,
+        waypoints,
+    })
+}
+
+fn bake(arguments: &Arguments) -> Result<(), String> {
+    let text = std::fs::read_to_string(&arguments.curve).map_err(|e| format!("can't read {}: {e}", arguments.curve))?;
+    let points = match extension(&arguments.curve) {
+        "json" => interchange::control_points_from_json(&text),
+        "csv" => interchange::control_points_from_csv(&text),
+        _ => return Err(format!("{}: expected a .json or .csv curve", arguments.curve)),
+    }.map_err(|e| format!("{}: {e}", arguments.curve))?;
+
+    let curve = if arguments.waypoints {
+        CompositeCurve::from_waypoints(&points, 0., false)
+    } else {
+        CompositeCurve::from_control_points(&points)
+            .ok_or_else(|| format!("{}: {} control points don't form a chain of cubic segments (3n + 1 needed)", arguments.curve, points.len()))?
+    };
+    if curve.segments().is_empty() {
+        return Err(format!("{}: at least two points are needed", arguments.curve));
+    }
+
+    let shape = ExtrudeShape::from_mesh(&load_glb_mesh(&arguments.shape)?);
+    let path = curve.generate_path(arguments.subdivisions);
+    let mesh = extrude::extrude(&shape, &path);
+
+    let mut writer = BufWriter::new(File::create(&arguments.output).map_err(|e| format!("can't create {}: {e}", arguments.output))?);
+    match extension(&arguments.output) {
+        "glb" => export::write_glb(&mesh, &mut writer),
+        "obj" => export::write_obj(&mesh, &mut writer),
+        _ => return Err(format!("{}: expected a .glb or .obj output", arguments.output)),
+    }.map_err(|e| format!("{}: {e}", arguments.output))
+}
+
+fn extension(path: &str) -> &str {
+    Path::new(path).extension().and_then(|extension| extension.to_str()).unwrap_or_default()
+}
+
+/// Every triangle primitive placed in the file's scene, moved by its node's world transform and
+/// merged into one mesh, without going through Bevy's asset server.
+fn load_glb_mesh(path: &str) -> Result<Mesh, String> {
+    let (document, buffers, _) = gltf::import(path).map_err(|e| format!("can't load {path}: {e}"))?;
+
+    // Nodes with a mesh and their world matrix, from the default scene or else the first one
+    let mut instances: Vec<(gltf::Mesh, Mat4)> = Vec::new();
+    let mut stack: Vec<(gltf::Node, Mat4)> = document.default_scene().or_else(|| document.scenes().next())
+        .map(|scene| scene.nodes().map(|node| (node, Mat4::IDENTITY)).collect())
+        .unwrap_or_default();
+    while let Some((node, parent)) = stack.pop() {
+        let world = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            instances.push((mesh, world));
+        }
+        stack.extend(node.children().map(|child| (child, world)));
+    }
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for (mesh, world) in instances {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(primitive_positions) = reader.read_positions() else {
+                continue;
+            };
+
+            let offset = positions.len() as u32;
+            positions.extend(primitive_positions.map(|position| world.transform_point3(Vec3::from_array(position)).to_array()));
+            let first_index = indices.len();
+            match reader.read_indices() {
+                Some(primitive_indices) => indices.extend(primitive_indices.into_u32().map(|i| i + offset)),
+                None => indices.extend(offset..positions.len() as u32),
+            }
+            // Mirroring turns the triangles inside out
+            if world.determinant() < 0. {
+                indices[first_index..].chunks_exact_mut(3).for_each(|triangle| triangle.swap(1, 2));
+            }
+            match reader.read_tex_coords(0) {
+                Some(primitive_uvs) => uvs.extend(primitive_uvs.into_f32()),
+                None => uvs.resize(positions.len(), [0., 0.]),
+            }
+        }
+    }
+    if positions.is_empty() {
+        return Err(format!("{path}: no triangle meshes in the scene"));
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_indices(Indices::U32(indices));
+
+    Ok(mesh)
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use crate::bezier::{BezierCurve, OrientedPoint};
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+use crate::path::ExtrudePath;
+
+/// The edge loops of a sweep between two guide rails, with their X scale stretching the cross-section
+/// from rail to rail. Both rails are sampled at the same fraction of their arc length, and the loops
+/// tilt with the line between the rails, so differing rail heights give superelevation.
+pub fn birail_path(shape: &ExtrudeShape, left: &BezierCurve, right: &BezierCurve, subdivisions: u32) -> ExtrudePath {
+    let (outline, _) = shape.outline();
+    let min_x = outline.iter().map(|p| p.x).reduce(f32::min).unwrap_or(0.);
+    let max_x = outline.iter().map(|p| p.x).reduce(f32::max).unwrap_or(0.);
+    let shape_width = (max_x - min_x).max(f32::EPSILON);
+
+    let subdivisions = subdivisions.max(1);
+    let mut path = Vec::with_capacity(subdivisions as usize + 1);
+    let mut v = 0.;
+    let mut previous_center: Option<Vec3> = None;
+    for i in 0..=subdivisions {
+        let f = i as f32 / subdivisions as f32;
+        let left_t = left.t_at_distance(f * left.length());
+        let right_t = right.t_at_distance(f * right.length());
+        let (left_point, right_point) = (left.get_oriented_point(left_t).position, right.get_oriented_point(right_t).position);
+
+        let across = right_point - left_point;
+        let width = across.length();
+        let right_direction = across.normalize_or(Vec3::X);
+        let tangent = left.derivative(left_t).normalize_or_zero() + right.derivative(right_t).normalize_or_zero();
+        let forward = (tangent - right_direction * tangent.dot(right_direction)).normalize_or(Vec3::NEG_Z);
+        let up = right_direction.cross(forward);
+        let rotation = Quat::from_mat3(&Mat3::from_cols(right_direction, up, -forward));
+
+        let scale = width / shape_width;
+        let center = (left_point + right_point) * 0.5;
+        if let Some(previous) = previous_center {
+            v += center.distance(previous);
+        }
+        previous_center = Some(center);
+
+        // Put the cross-section's leftmost point on the left rail
+        path.push(OrientedPoint::new(left_point - right_direction * min_x * scale, rotation, v).with_scale(Vec3::new(scale, 1., 1.)));
+    }
+
+    path.into()
+}
+
+/// Sweeps `shape` between a left and a right guide rail, stretching it across per edge loop, for
+/// roads and rivers of varying width and banking.
+pub fn birail_sweep(shape: &ExtrudeShape, left: &BezierCurve, right: &BezierCurve, subdivisions: u32, options: &ExtrudeOptions) -> Mesh {
+    let path = birail_path(shape, left, right, subdivisions);
+
+    extrude::extrude_with_options(shape, &path, options)
+}
@@ -0,0 +1,79 @@
+use bevy::math::Vec3A;
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+use bevy::render::primitives::{Aabb, Sphere};
+use crate::bezier::OrientedPoint;
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+
+/// A box and a sphere around some vertices, the sphere centered on the box.
+#[derive(Debug, Clone, Default)]
+pub struct BoundingVolume {
+    pub aabb: Aabb,
+    pub sphere: Sphere,
+}
+
+impl BoundingVolume {
+    /// `None` without any points.
+    pub fn from_points(points: impl Iterator<Item = Vec3> + Clone) -> Option<Self> {
+        let (min, max) = points.clone().fold(None, |bounds: Option<(Vec3, Vec3)>, point| match bounds {
+            Some((min, max)) => Some((min.min(point), max.max(point))),
+            None => Some((point, point)),
+        })?;
+        let aabb = Aabb::from_min_max(min, max);
+        let radius = points.map(|point| Vec3A::from(point).distance_squared(aabb.center)).fold(0., f32::max).sqrt();
+
+        Some(Self {
+            sphere: Sphere { center: aabb.center, radius },
+            aabb,
+        })
+    }
+
+    /// The bounds of a mesh's positions.
+    pub fn from_mesh(mesh: &Mesh) -> Option<Self> {
+        match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) => Self::from_points(positions.iter().map(|p| Vec3::from_array(*p))),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        let offset = (Vec3A::from(point) - self.aabb.center).abs();
+        // The box is stored as a center and half size, allow for the rounding so its own corners are inside
+        let tolerance = (self.aabb.center.abs() + self.aabb.half_extents) * (4. * f32::EPSILON);
+        offset.cmple(self.aabb.half_extents + tolerance).all()
+    }
+}
+
+/// The bounds of an extrusion as a whole and of each segment between two edge loops, for custom
+/// culling, broad-phase physics and spawn queries without reading the mesh back.
+#[derive(Debug, Clone, Default)]
+pub struct ExtrusionBounds {
+    pub mesh: BoundingVolume,
+    /// One per segment of the path as extruded, i.e. after [`ExtrudeOptions::min_segment_length`] and
+    /// [`ExtrudeOptions::fold_handling`] dropped any edge loops.
+    pub segments: Vec<BoundingVolume>,
+}
+
+impl ExtrusionBounds {
+    /// The segments whose bounding box holds `point`.
+    pub fn segments_containing(&self, point: Vec3) -> impl Iterator<Item = usize> + '_ {
+        self.segments.iter()
+            .enumerate()
+            .filter(move |(_, segment)| segment.contains(point))
+            .map(|(segment, _)| segment)
+    }
+}
+
+/// The bounds of extruding `shape` along `path`, computed from the edge loops alone without building
+/// the mesh. `None` if the shape or path is empty. For chunked extrusions see [`extrude::MeshChunk`].
+pub fn extrusion_bounds(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) -> Option<ExtrusionBounds> {
+    let path = extrude::extruded_path(shape, path, options);
+    let loops: Vec<Vec<Vec3>> = (0..path.len()).map(|i| extrude::edge_loop(shape, &path, i, options).collect()).collect();
+
+    Some(ExtrusionBounds {
+        mesh: BoundingVolume::from_points(loops.iter().flatten().copied())?,
+        segments: loops.windows(2)
+            .filter_map(|pair| BoundingVolume::from_points(pair[0].iter().chain(&pair[1]).copied()))
+            .collect(),
+    })
+}
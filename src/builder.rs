@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::render_asset::RenderAssetUsages;
+use crate::bezier::OrientedPoint;
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape, ExtrudedGeometry, IndexFormat};
+use crate::simplify;
+
+/// Extrudes `shape` along `path` into an [`ExtrudedMeshBuilder`] for post-processing. Extra attributes
+/// requested in `options` (vertex colors, lightmap UVs and the like) are not generated, as the
+/// post-processing steps can't carry them along; use [`extrude::extrude_with_options`] for those.
+pub fn extrude_builder(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) -> ExtrudedMeshBuilder {
+    ExtrudedMeshBuilder::new(extrude::extrude_raw_with_options(shape, path, options))
+        .with_index_format(options.index_format)
+        .with_asset_usage(options.asset_usage)
+}
+
+/// Extrusion output on its way to becoming a [`Mesh`]. Each step is applied right away, in the order it
+/// is called, and [`ExtrudedMeshBuilder::build`] produces the mesh.
+#[derive(Debug, Clone)]
+pub struct ExtrudedMeshBuilder {
+    geometry: ExtrudedGeometry,
+    /// One per vertex once [`ExtrudedMeshBuilder::generate_tangents`] was called, empty before.
+    tangents: Vec<[f32; 4]>,
+    index_format: IndexFormat,
+    asset_usage: RenderAssetUsages,
+}
+
+impl From<ExtrudedGeometry> for ExtrudedMeshBuilder {
+    fn from(geometry: ExtrudedGeometry) -> Self {
+        Self::new(geometry)
+    }
+}
+
+impl ExtrudedMeshBuilder {
+    pub fn new(geometry: ExtrudedGeometry) -> Self {
+        Self {
+            geometry,
+            tangents: Vec::new(),
+            index_format: IndexFormat::default(),
+            asset_usage: RenderAssetUsages::default(),
+        }
+    }
+
+    pub fn with_index_format(mut self, index_format: IndexFormat) -> Self {
+        self.index_format = index_format;
+        self
+    }
+
+    pub fn with_asset_usage(mut self, asset_usage: RenderAssetUsages) -> Self {
+        self.asset_usage = asset_usage;
+        self
+    }
+
+    pub fn geometry(&self) -> &ExtrudedGeometry {
+        &self.geometry
+    }
+
+    /// Merges vertices whose positions, normals and UVs all lie within `epsilon` of each other, such as
+    /// the ones left behind by coincident path points. Hard edges and UV seams are kept. See
+    /// [`ExtrudedGeometry::weld_matching`].
+    pub fn weld(mut self, epsilon: f32) -> Self {
+        let kept = self.geometry.weld_matching(epsilon);
+        self.keep_tangents(&kept);
+        self
+    }
+
+    /// Merges all vertices within `epsilon` of each other into one, averaging their normals, so the
+    /// surface becomes watertight for physics cooking or CSG. See [`ExtrudedGeometry::weld`].
+    pub fn weld_watertight(mut self, epsilon: f32) -> Self {
+        let kept = self.geometry.weld(epsilon);
+        self.keep_tangents(&kept);
+        self
+    }
+
+    /// Replaces the normals with the area-weighted average of the faces around each vertex. Vertices
+    /// that are split (at hard edges or UV seams) stay split, so their normals stay faceted.
+    pub fn recompute_normals(mut self) -> Self {
+        self.geometry.recompute_normals();
+        self
+    }
+
+    /// Decimates the surface, see [`simplify::simplify`]. This welds the vertices, and drops any
+    /// tangents generated so far; generate them afterwards.
+    pub fn simplify(mut self, target_ratio: f32, max_error: f32) -> Self {
+        self.geometry = simplify::simplify(&self.geometry, target_ratio, max_error);
+        self.tangents.clear();
+        self
+    }
+
+    /// Reorders the triangles and vertices for faster rendering, see [`ExtrudedGeometry::optimize`].
+    /// Best called last, after any step that changes the triangles.
+    #[cfg(feature = "meshopt")]
+    pub fn optimize(mut self) -> Self {
+        let sources = self.geometry.optimize();
+        self.keep_tangents(&sources);
+        self
+    }
+
+    /// Generates per-vertex tangents from the UVs, needed for normal mapping. Does nothing without UVs.
+    pub fn generate_tangents(mut self) -> Self {
+        let geometry = &self.geometry;
+        if geometry.uvs.is_empty() {
+            return self;
+        }
+
+        let mut tangents = vec![Vec3::ZERO; geometry.positions.len()];
+        let mut bitangents = vec![Vec3::ZERO; geometry.positions.len()];
+        for triangle in geometry.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| triangle[k] as usize);
+            let [pa, pb, pc] = [a, b, c].map(|i| Vec3::from_array(geometry.positions[i]));
+            let [ta, tb, tc] = [a, b, c].map(|i| Vec2::from_array(geometry.uvs[i]));
+            let (e1, e2) = (pb - pa, pc - pa);
+            let (d1, d2) = (tb - ta, tc - ta);
+            let det = d1.x * d2.y - d2.x * d1.y;
+            if det.abs() <= f32::EPSILON {
+                continue;
+            }
+            let tangent = (e1 * d2.y - e2 * d1.y) / det;
+            let bitangent = (e2 * d1.x - e1 * d2.x) / det;
+            for i in [a, b, c] {
+                tangents[i] += tangent;
+                bitangents[i] += bitangent;
+            }
+        }
+
+        self.tangents = tangents.into_iter().zip(bitangents).enumerate()
+            .map(|(i, (tangent, bitangent))| {
+                let normal = Vec3::from_array(geometry.normals[i]);
+                // Gram-Schmidt, falling back to any direction perpendicular to the normal
+                let tangent = (tangent - normal * normal.dot(tangent)).try_normalize().unwrap_or_else(|| normal.any_orthonormal_vector());
+                let handedness = if normal.cross(tangent).dot(bitangent) < 0. { -1. } else { 1. };
+                tangent.extend(handedness).to_array()
+            })
+            .collect();
+        self
+    }
+
+    /// Turns the surface inside out: reverses the winding of every triangle and negates the normals.
+    pub fn flip(mut self) -> Self {
+        self.reverse_winding();
+        self.geometry.normals.iter_mut().for_each(|n| *n = n.map(|x| -x));
+        // Keep the bitangent pointing the same way now that the normal is reversed
+        self.tangents.iter_mut().for_each(|t| t[3] = -t[3]);
+        self
+    }
+
+    /// Applies `matrix` to the positions, normals and tangents. Mirroring transforms also reverse the
+    /// winding, so the surface keeps facing outwards.
+    pub fn transform(mut self, matrix: Mat4) -> Self {
+        let linear = Mat3::from_mat4(matrix);
+        let normal_matrix = linear.inverse().transpose();
+        for position in &mut self.geometry.positions {
+            *position = matrix.transform_point3(Vec3::from_array(*position)).to_array();
+        }
+        for normal in &mut self.geometry.normals {
+            *normal = (normal_matrix * Vec3::from_array(*normal)).normalize_or_zero().to_array();
+        }
+        for tangent in &mut self.tangents {
+            let direction = (linear * Vec3::new(tangent[0], tangent[1], tangent[2])).normalize_or_zero();
+            *tangent = direction.extend(tangent[3]).to_array();
+        }
+        if linear.determinant() < 0. {
+            self.reverse_winding();
+        }
+        self
+    }
+
+    pub fn into_geometry(self) -> ExtrudedGeometry {
+        self.geometry
+    }
+
+    pub fn build(self) -> Mesh {
+        let mut mesh = extrude::geometry_mesh(self.geometry, PrimitiveTopology::TriangleList, self.index_format, self.asset_usage);
+        if !self.tangents.is_empty() {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_TANGENT, self.tangents);
+        }
+        mesh
+    }
+
+    fn reverse_winding(&mut self) {
+        self.geometry.indices.chunks_exact_mut(3).for_each(|triangle| triangle.swap(1, 2));
+    }
+
+    /// Keeps only the tangents of the vertices at `kept`, in that order, after the geometry was remapped.
+    fn keep_tangents(&mut self, kept: &[usize]) {
+        if !self.tangents.is_empty() {
+            self.tangents = kept.iter().map(|&v| self.tangents[v]).collect();
+        }
+    }
+}
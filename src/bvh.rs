@@ -0,0 +1,453 @@
+use std::ops::Range;
+use bevy::prelude::*;
+use crate::bezier::OrientedPoint;
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+
+/// Path segments per leaf. Each holds a quad per edge of the cross-section.
+const LEAF_SEGMENTS: usize = 2;
+
+/// A bounding volume hierarchy over the side quads of an extrusion, for ray and nearest-point queries
+/// ("which track segment did the player hit?") without a generic triangle raycaster. The segments are
+/// already in order along the path, so the tree just halves runs of them. Everything is in the mesh's
+/// space, and faces of the shape itself (see [`ExtrudeShape::from_mesh`]) are left out.
+#[derive(Debug, Clone)]
+pub struct ExtrusionBvh {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    loop_vertex_count: usize,
+    edges: Vec<[u32; 2]>,
+    bvh: Bvh,
+    /// The V coordinate of each edge loop.
+    v_coordinates: Vec<f32>,
+    /// The path parameter of each edge loop, see [`SurfaceLocation::t`].
+    parameters: Vec<f32>,
+    /// The cross-section's vertices in its own plane.
+    shape_points: Vec<Vec2>,
+}
+
+/// Where a query met the extruded surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceHit {
+    pub point: Vec3,
+    /// The face normal, on the same side as the mesh's normals.
+    pub normal: Vec3,
+    /// Along the ray for [`ExtrusionBvh::raycast`], from the query point for [`ExtrusionBvh::closest_point`].
+    pub distance: f32,
+    /// The path segment, between edge loops `segment` and `segment + 1` of the path as extruded.
+    pub segment: usize,
+    /// The edge of the cross-section whose quad was hit.
+    pub edge: usize,
+    /// How far along the segment the hit is, from 0 at edge loop `segment` to 1 at the next.
+    pub along: f32,
+    /// How far across the edge the hit is, from 0 at its first vertex to 1 at its second.
+    pub across: f32,
+}
+
+/// Where on the extrusion a point lies, in terms of the path and the cross-section, e.g. to place
+/// decals and damage or to respawn a player on the track.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceLocation {
+    pub hit: SurfaceHit,
+    /// The V coordinate along the path (the distance along it for generated paths).
+    pub v_coordinate: f32,
+    /// The path parameter, interpolated between the edge loops': the curve's `t` for paths from
+    /// [`crate::bezier::BezierCurve::generate_path`], or the parameters given to [`ExtrusionBvh::with_parameters`].
+    pub t: f32,
+    /// The edge loop nearest the point.
+    pub edge_loop: usize,
+    /// The point in the cross-section's plane, before the path's scale. `x` is the lateral offset
+    /// from the path.
+    pub cross_section: Vec2,
+}
+
+impl ExtrusionBvh {
+    /// Gives the path's points parameters evenly spaced from 0 to 1, as sampled by
+    /// [`crate::bezier::BezierCurve::generate_path`].
+    pub fn new(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) -> Self {
+        let last = path.len().saturating_sub(1).max(1) as f32;
+        let parameters: Vec<f32> = (0..path.len()).map(|i| i as f32 / last).collect();
+        Self::with_parameters(shape, path, &parameters, options)
+    }
+
+    /// Like [`ExtrusionBvh::new`], with the path parameter of each of the path's points, e.g. the `t`
+    /// each was sampled at.
+    pub fn with_parameters(shape: &ExtrudeShape, path: &[OrientedPoint], parameters: &[f32], options: &ExtrudeOptions) -> Self {
+        let (path, sources) = extrude::extruded_path_with_sources(shape, path, options);
+        let (positions, normals) = extrude::extruded_vertices(shape, &path, options);
+        let loop_vertex_count = shape.vertex_count();
+        // The double-sided copy adds nothing to hit
+        let vertex_count = loop_vertex_count * path.len();
+
+        let positions: Vec<Vec3> = positions[..vertex_count].iter().map(|p| Vec3::from_array(*p)).collect();
+        let edges: Vec<[u32; 2]> = shape.edges().chunks_exact(2).map(|edge| [edge[0], edge[1]]).collect();
+        let boxes: Vec<(Vec3, Vec3)> = if edges.is_empty() {
+            Vec::new()
+        } else {
+            (0..path.len().saturating_sub(1))
+                .map(|segment| bounds(&positions[segment * loop_vertex_count..(segment + 2) * loop_vertex_count]))
+                .collect()
+        };
+
+        Self {
+            normals: normals[..vertex_count].iter().map(|n| Vec3::from_array(*n)).collect(),
+            loop_vertex_count,
+            edges,
+            bvh: Bvh::in_order(&boxes, LEAF_SEGMENTS),
+            v_coordinates: path.iter().map(|point| point.v_coordinate).collect(),
+            parameters: sources.iter().map(|&i| parameters.get(i).copied().unwrap_or(0.)).collect(),
+            shape_points: shape.vertices().iter().map(|vertex| vertex.truncate()).collect(),
+            positions,
+        }
+    }
+
+    /// The number of path segments, one less than the edge loops.
+    pub fn segment_count(&self) -> usize {
+        self.bvh.len()
+    }
+
+    /// The first hit of `ray` within `max_distance`, from either side of the surface.
+    pub fn raycast(&self, ray: Ray3d, max_distance: f32) -> Option<SurfaceHit> {
+        let direction = Vec3::from(ray.direction);
+        self.bvh.raycast(ray, max_distance, |segment, limit| {
+            self.segment_hit(segment, limit, |triangle| ray_triangle(ray.origin, direction, triangle))
+        })
+    }
+
+    /// The point of the surface nearest to `point`, if one is within `max_distance`.
+    pub fn closest_point(&self, point: Vec3, max_distance: f32) -> Option<SurfaceHit> {
+        self.bvh.closest_point(point, max_distance, |segment, limit| {
+            self.segment_hit(segment, limit, |triangle| {
+                let (closest, weights) = closest_on_triangle(point, triangle);
+                Some((closest.distance(point), weights))
+            })
+        })
+    }
+
+    /// Locates the point of the surface nearest to `point`, if one is within `max_distance`. The
+    /// point is in the mesh's space, see [`ExtrusionBvh::locate_world`] for world positions.
+    pub fn locate(&self, point: Vec3, max_distance: f32) -> Option<SurfaceLocation> {
+        self.closest_point(point, max_distance).map(|hit| self.location(hit))
+    }
+
+    /// Like [`ExtrusionBvh::locate`], for a world position near the mesh placed by `transform`. The
+    /// hit's point, normal and distance are in world space too. Under non-uniform scale the nearest
+    /// point is picked in the mesh's space, so it may not be the nearest in the world.
+    pub fn locate_world(&self, point: Vec3, transform: &GlobalTransform, max_distance: f32) -> Option<SurfaceLocation> {
+        let matrix = transform.compute_matrix();
+        let min_scale = transform.compute_transform().scale.abs().min_element();
+        if min_scale <= 0. {
+            return None;
+        }
+
+        let mut location = self.locate(matrix.inverse().transform_point3(point), max_distance / min_scale)?;
+        let hit = &mut location.hit;
+        hit.point = matrix.transform_point3(hit.point);
+        hit.normal = (Mat3::from_mat4(matrix).inverse().transpose() * hit.normal).normalize_or_zero();
+        hit.distance = hit.point.distance(point);
+        (hit.distance <= max_distance).then_some(location)
+    }
+
+    /// Where a hit from [`ExtrusionBvh::raycast`] or [`ExtrusionBvh::closest_point`] lies.
+    pub fn location(&self, hit: SurfaceHit) -> SurfaceLocation {
+        let interpolate = |values: &[f32]| {
+            let value = |edge_loop: usize| values.get(edge_loop).copied().unwrap_or(0.);
+            value(hit.segment) + (value(hit.segment + 1) - value(hit.segment)) * hit.along
+        };
+        let [first, second] = self.edges[hit.edge].map(|vertex| self.shape_points[vertex as usize]);
+
+        SurfaceLocation {
+            hit,
+            v_coordinate: interpolate(&self.v_coordinates),
+            t: interpolate(&self.parameters),
+            edge_loop: hit.segment + (hit.along >= 0.5) as usize,
+            cross_section: first.lerp(second, hit.across),
+        }
+    }
+
+    /// The nearest of the segment's triangles within `limit` by `test`, which gives the distance to
+    /// a triangle and the barycentric weights of the point it found.
+    fn segment_hit(&self, segment: usize, mut limit: f32, test: impl Fn([Vec3; 3]) -> Option<(f32, Vec3)>) -> Option<(f32, SurfaceHit)> {
+        let mut best = None;
+        for edge in 0..self.edges.len() {
+            for (triangle, corners) in self.quad_triangles(segment, edge) {
+                let Some((distance, weights)) = test(triangle.map(|v| self.positions[v])) else {
+                    continue;
+                };
+                if distance <= limit {
+                    limit = distance;
+                    best = Some((distance, self.hit(segment, edge, triangle, corners, weights, distance)));
+                }
+            }
+        }
+
+        best
+    }
+
+    /// The quad's two triangles, split along the same diagonal as the mesh, each with its corners'
+    /// (along, across) coordinates.
+    fn quad_triangles(&self, segment: usize, edge: usize) -> [([usize; 3], [Vec2; 3]); 2] {
+        let offset = segment * self.loop_vertex_count;
+        let next = offset + self.loop_vertex_count;
+        let [first, second] = self.edges[edge].map(|v| v as usize);
+        let (a, b, c, d) = (next + first, offset + first, offset + second, next + second);
+        [
+            ([a, b, c], [Vec2::new(1., 0.), Vec2::new(0., 0.), Vec2::new(0., 1.)]),
+            ([c, d, a], [Vec2::new(0., 1.), Vec2::new(1., 1.), Vec2::new(1., 0.)]),
+        ]
+    }
+
+    fn hit(&self, segment: usize, edge: usize, triangle: [usize; 3], corners: [Vec2; 3], weights: Vec3, distance: f32) -> SurfaceHit {
+        let [a, b, c] = triangle.map(|v| self.positions[v]);
+        let smooth: Vec3 = triangle.iter().map(|&v| self.normals[v]).sum();
+        let normal = (b - a).cross(c - a).normalize_or(smooth.normalize_or_zero());
+        let coordinates = corners[0] * weights.x + corners[1] * weights.y + corners[2] * weights.z;
+
+        SurfaceHit {
+            point: a * weights.x + b * weights.y + c * weights.z,
+            normal: if normal.dot(smooth) < 0. { -normal } else { normal },
+            distance,
+            segment,
+            edge,
+            along: coordinates.x,
+            across: coordinates.y,
+        }
+    }
+}
+
+/// A bounding volume hierarchy over items numbered from 0, each with a bounding box, as used by
+/// [`ExtrusionBvh`] and [`crate::terrain::MeshSurface`]. Every node holds a contiguous run of items;
+/// the queries hand each item of the leaves they reach to a test of the caller's.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Bvh {
+    nodes: Vec<BvhNode>,
+}
+
+#[derive(Debug, Clone)]
+struct BvhNode {
+    min: Vec3,
+    max: Vec3,
+    items: Range<usize>,
+    /// The two child nodes, `None` for leaves.
+    children: Option<[usize; 2]>,
+}
+
+impl Bvh {
+    /// Halves runs of items that are already close to their neighbours, such as path segments.
+    pub(crate) fn in_order(boxes: &[(Vec3, Vec3)], leaf_size: usize) -> Self {
+        let mut order: Vec<usize> = (0..boxes.len()).collect();
+        Self::build(&mut order, boxes, leaf_size, false)
+    }
+
+    /// Splits the items at the median along the longest axis of each node. Returns the tree and the
+    /// order it numbers the items in, which the caller's items have to be rearranged to.
+    pub(crate) fn sorted(boxes: &[(Vec3, Vec3)], leaf_size: usize) -> (Self, Vec<usize>) {
+        let mut order: Vec<usize> = (0..boxes.len()).collect();
+        let bvh = Self::build(&mut order, boxes, leaf_size, true);
+        (bvh, order)
+    }
+
+    fn build(order: &mut [usize], boxes: &[(Vec3, Vec3)], leaf_size: usize, sort: bool) -> Self {
+        let mut bvh = Self::default();
+        if !order.is_empty() {
+            bvh.add_node(order, 0, boxes, leaf_size.max(1), sort);
+        }
+        bvh
+    }
+
+    fn add_node(&mut self, order: &mut [usize], start: usize, boxes: &[(Vec3, Vec3)], leaf_size: usize, sort: bool) -> usize {
+        let (min, max) = order.iter()
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), &i| (min.min(boxes[i].0), max.max(boxes[i].1)));
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode { min, max, items: start..start + order.len(), children: None });
+
+        if order.len() > leaf_size {
+            let middle = order.len() / 2;
+            if sort {
+                let size = max - min;
+                let axis = if size.x >= size.y && size.x >= size.z { 0 } else if size.y >= size.z { 1 } else { 2 };
+                let center = |i: usize| boxes[i].0[axis] + boxes[i].1[axis];
+                order.select_nth_unstable_by(middle, |a, b| center(*a).total_cmp(&center(*b)));
+            }
+            let (first, second) = order.split_at_mut(middle);
+            let first = self.add_node(first, start, boxes, leaf_size, sort);
+            let second = self.add_node(second, start + middle, boxes, leaf_size, sort);
+            self.nodes[index].children = Some([first, second]);
+        }
+
+        index
+    }
+
+    /// The number of items.
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.first().map_or(0, |root| root.items.len())
+    }
+
+    /// The box around every item, `None` when there are none.
+    pub(crate) fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.nodes.first().map(|root| (root.min, root.max))
+    }
+
+    /// The nearest result of `test` along `ray` within `max_distance`. `test` is given an item whose
+    /// box the ray passes through and the distance to beat, and returns a distance with its result.
+    pub(crate) fn raycast<H>(&self, ray: Ray3d, max_distance: f32, mut test: impl FnMut(usize, f32) -> Option<(f32, H)>) -> Option<H> {
+        let inverse_direction = Vec3::from(ray.direction).recip();
+        self.search(max_distance, |node, limit| ray_hits_box(ray.origin, inverse_direction, node.min, node.max, limit).then_some(0.), &mut test)
+    }
+
+    /// Like [`Bvh::raycast`], for the item nearest to `point`.
+    pub(crate) fn closest_point<H>(&self, point: Vec3, max_distance: f32, mut test: impl FnMut(usize, f32) -> Option<(f32, H)>) -> Option<H> {
+        self.search(max_distance, |node, limit| {
+            let distance = point.clamp(node.min, node.max).distance(point);
+            (distance <= limit).then_some(distance)
+        }, &mut test)
+    }
+
+    /// Depth first, skipping nodes `reach` rules out within the distance to beat. Of two children the
+    /// one `reach` finds nearer is searched first, so it tightens the limit for the other.
+    fn search<H>(&self, max_distance: f32, reach: impl Fn(&BvhNode, f32) -> Option<f32>, test: &mut impl FnMut(usize, f32) -> Option<(f32, H)>) -> Option<H> {
+        let mut best: Option<(f32, H)> = None;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let Some(node) = self.nodes.get(index) else {
+                continue;
+            };
+            let limit = best.as_ref().map_or(max_distance, |(distance, _)| *distance);
+            if reach(node, limit).is_none() {
+                continue;
+            }
+            if let Some([first, second]) = node.children {
+                let distance = |child: usize| reach(&self.nodes[child], f32::INFINITY).unwrap_or(f32::INFINITY);
+                stack.extend(if distance(first) < distance(second) { [second, first] } else { [first, second] });
+                continue;
+            }
+
+            for item in node.items.clone() {
+                let limit = best.as_ref().map_or(max_distance, |(distance, _)| *distance);
+                if let Some(hit) = test(item, limit).filter(|(distance, _)| *distance <= limit) {
+                    best = Some(hit);
+                }
+            }
+        }
+
+        best.map(|(_, hit)| hit)
+    }
+}
+
+/// The box around `points`.
+pub(crate) fn bounds(points: &[Vec3]) -> (Vec3, Vec3) {
+    points.iter().fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), &p| (min.min(p), max.max(p)))
+}
+
+fn ray_hits_box(origin: Vec3, inverse_direction: Vec3, min: Vec3, max: Vec3, max_distance: f32) -> bool {
+    let (t1, t2) = ((min - origin) * inverse_direction, (max - origin) * inverse_direction);
+    let (mut near, mut far) = (0f32, max_distance);
+    for axis in 0..3 {
+        // NaN when the ray runs along a face of the box, which counts as inside that slab
+        if t1[axis].is_nan() || t2[axis].is_nan() {
+            continue;
+        }
+        near = near.max(t1[axis].min(t2[axis]));
+        far = far.min(t1[axis].max(t2[axis]));
+    }
+    near <= far
+}
+
+/// Möller–Trumbore, hitting either side. Returns the distance along the ray and the barycentric weights.
+pub(crate) fn ray_triangle(origin: Vec3, direction: Vec3, [a, b, c]: [Vec3; 3]) -> Option<(f32, Vec3)> {
+    let (ab, ac) = (b - a, c - a);
+    let p = direction.cross(ac);
+    let determinant = ab.dot(p);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+    let inverse = 1. / determinant;
+    let to_origin = origin - a;
+    let u = to_origin.dot(p) * inverse;
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+    let q = to_origin.cross(ab);
+    let v = direction.dot(q) * inverse;
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+
+    let distance = ac.dot(q) * inverse;
+    (distance >= 0.).then_some((distance, Vec3::new(1. - u - v, u, v)))
+}
+
+/// The point of the triangle nearest to `p` and its barycentric weights (Ericson, Real-Time Collision Detection 5.1.5).
+pub(crate) fn closest_on_triangle(p: Vec3, [a, b, c]: [Vec3; 3]) -> (Vec3, Vec3) {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0. && d2 <= 0. {
+        return (a, Vec3::X);
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0. && d4 <= d3 {
+        return (b, Vec3::Y);
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0. && d1 >= 0. && d3 <= 0. {
+        let v = d1 / (d1 - d3);
+        return (a + ab * v, Vec3::new(1. - v, v, 0.));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0. && d5 <= d6 {
+        return (c, Vec3::Z);
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0. && d2 >= 0. && d6 <= 0. {
+        let w = d2 / (d2 - d6);
+        return (a + ac * w, Vec3::new(1. - w, 0., w));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0. && d4 - d3 >= 0. && d5 - d6 >= 0. {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return (b + (c - b) * w, Vec3::new(0., 1. - w, w));
+    }
+
+    let denominator = 1. / (va + vb + vc);
+    let (v, w) = (vb * denominator, vc * denominator);
+    (a + ab * v + ac * w, Vec3::new(1. - v - w, v, w))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2 by 2 square tube 10 long, running from the origin along -Z in segments of 1.
+    fn tube() -> ExtrusionBvh {
+        let outline = [Vec2::new(1., 1.), Vec2::new(-1., 1.), Vec2::new(-1., -1.), Vec2::new(1., -1.)];
+        let shape = ExtrudeShape::from_outline(&outline, true);
+        ExtrusionBvh::new(&shape, &crate::path::linear(Vec3::NEG_Z, 10., 10), &ExtrudeOptions::default())
+    }
+
+    #[test]
+    fn ray_hits_the_top_of_a_tube() {
+        let hit = tube().raycast(Ray3d::new(Vec3::new(0.5, 5., -4.25), Vec3::NEG_Y), 10.).unwrap();
+        assert!(hit.point.distance(Vec3::new(0.5, 1., -4.25)) < 1e-5);
+        assert!(hit.normal.distance(Vec3::Y) < 1e-5);
+        assert!((hit.distance - 4.).abs() < 1e-5);
+        assert_eq!((hit.segment, hit.edge), (4, 0));
+        assert!((hit.along - 0.25).abs() < 1e-4 && (hit.across - 0.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ray_misses_beyond_max_distance_and_past_the_end() {
+        let bvh = tube();
+        assert!(bvh.raycast(Ray3d::new(Vec3::new(0., 5., -4.), Vec3::NEG_Y), 3.).is_none());
+        assert!(bvh.raycast(Ray3d::new(Vec3::new(0., 5., -12.), Vec3::NEG_Y), 10.).is_none());
+    }
+
+    #[test]
+    fn locate_a_point_beside_the_tube() {
+        let location = tube().locate(Vec3::new(3., 0.5, -6.5), 5.).unwrap();
+        assert!(location.hit.point.distance(Vec3::new(1., 0.5, -6.5)) < 1e-5);
+        assert!((location.hit.distance - 2.).abs() < 1e-5);
+        assert!((location.v_coordinate - 6.5).abs() < 1e-4 && (location.t - 0.65).abs() < 1e-4);
+        assert!(location.cross_section.distance(Vec2::new(1., 0.5)) < 1e-4);
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use bevy::gltf::{Gltf, GltfNode};
+use bevy::prelude::*;
+use bevy::math::cubic_splines::{CubicCurve, CubicSegment};
+use crate::bezier::{BezierCurve, OrientedPoint, VMode};
+use crate::path::ExtrudePath;
+
+/// How smoothly a curve continues into the next one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Continuity {
+    /// The curves meet, but may form a corner.
+    C0,
+    /// The curves meet with the same tangent direction and speed.
+    C1,
+    /// The curves meet with the same tangent direction; the next curve keeps its handle length.
+    #[default]
+    G1,
+}
+
+impl Continuity {
+    /// Adjusts the start of `next` so it continues `previous` with this continuity.
+    pub fn apply(&self, previous: &BezierCurve, next: &BezierCurve) -> BezierCurve {
+        let previous_points = previous.points();
+        let mut points = next.points().to_vec();
+        let joint = previous_points[3];
+        let incoming = joint - previous_points[2];
+
+        let handle_length = points[1].distance(points[0]);
+        points[0] = joint;
+        match self {
+            Continuity::C0 => {}
+            Continuity::C1 => points[1] = joint + incoming,
+            Continuity::G1 => points[1] = joint + incoming.normalize_or_zero() * handle_length,
+        }
+
+        next.with_points(points)
+    }
+}
+
+/// A chain of cubic Bézier curves, each starting where the previous one ends.
+/// `t` runs from 0 to 1 over the whole chain, each segment taking an equal share of it.
+/// V coordinates run on from one segment to the next, in the [`VMode`] of the first segment.
+#[derive(Clone, Debug, Default)]
+pub struct CompositeCurve {
+    segments: Vec<BezierCurve>,
+}
+
+impl CompositeCurve {
+    pub fn new(segments: Vec<BezierCurve>) -> Self {
+        Self {
+            segments,
+        }
+    }
+
+    /// A smooth curve through the nodes of a glTF file named `prefix.000`, `prefix.001`, ... (as Blender
+    /// names duplicated empties), in numeric order, with `prefix` itself first if present. See
+    /// [`gltf_node_chain`] and [`CompositeCurve::from_waypoints`].
+    pub fn from_gltf_nodes(gltf: &Gltf, nodes: &Assets<GltfNode>, prefix: &str, tension: f32, closed: bool) -> Self {
+        let waypoints: Vec<Vec3> = gltf_node_chain(gltf, nodes, prefix).iter().map(|transform| transform.translation).collect();
+
+        Self::from_waypoints(&waypoints, tension, closed)
+    }
+
+    /// A smooth curve through the given waypoints with automatically placed handles (a cardinal spline).
+    /// `tension` 0 gives a Catmull-Rom spline, 1 straight lines between the waypoints.
+    /// Closed curves also connect the last waypoint back to the first.
+    pub fn from_waypoints(waypoints: &[Vec3], tension: f32, closed: bool) -> Self {
+        let n = waypoints.len();
+        if n < 2 {
+            return Self::default();
+        }
+
+        let scale = (1. - tension) * 0.5;
+        let tangent = |i: usize| -> Vec3 {
+            if closed {
+                (waypoints[(i + 1) % n] - waypoints[(i + n - 1) % n]) * scale
+            } else if i == 0 {
+                (waypoints[1] - waypoints[0]) * scale * 2.
+            } else if i == n - 1 {
+                (waypoints[n - 1] - waypoints[n - 2]) * scale * 2.
+            } else {
+                (waypoints[i + 1] - waypoints[i - 1]) * scale
+            }
+        };
+
+        let segment_count = if closed { n } else { n - 1 };
+        let segments = (0..segment_count)
+            .map(|i| {
+                let j = (i + 1) % n;
+                BezierCurve::new(vec![
+                    waypoints[i],
+                    waypoints[i] + tangent(i) / 3.,
+                    waypoints[j] - tangent(j) / 3.,
+                    waypoints[j],
+                ], None)
+            })
+            .collect();
+
+        Self::new(segments)
+    }
+
+    pub fn segments(&self) -> &[BezierCurve] {
+        &self.segments
+    }
+
+    /// A chain of segments from a flat list of control points, consecutive segments sharing their
+    /// joint: `[p0, p1, p2, p3, p4, p5, p6, ...]`. Returns `None` unless there are `3n + 1` points, `n >= 1`.
+    pub fn from_control_points(points: &[Vec3]) -> Option<Self> {
+        if points.len() < 4 || !(points.len() - 1).is_multiple_of(3) {
+            return None;
+        }
+
+        Some(Self::new(points.windows(4).step_by(3).map(|window| BezierCurve::new(window.to_vec(), None)).collect()))
+    }
+
+    /// The control points as a flat list with the joints listed once, see [`CompositeCurve::from_control_points`].
+    pub fn control_points(&self) -> Vec<Vec3> {
+        let mut points = Vec::with_capacity(self.segments.len() * 3 + 1);
+        for (i, segment) in self.segments.iter().enumerate() {
+            points.extend_from_slice(&segment.points()[if i == 0 { 0 } else { 1 }..]);
+        }
+
+        points
+    }
+
+    /// Appends `curve`, adjusting its start to continue from the current end with the given continuity.
+    pub fn push(&mut self, curve: BezierCurve, continuity: Continuity) {
+        let curve = match self.segments.last() {
+            Some(last) => continuity.apply(last, &curve),
+            None => curve,
+        };
+        self.segments.push(curve);
+    }
+
+    pub fn join(mut self, other: &CompositeCurve, continuity: Continuity) -> Self {
+        for (i, segment) in other.segments.iter().enumerate() {
+            // Only the seam between the two chains is adjusted
+            if i == 0 {
+                self.push(segment.clone(), continuity);
+            } else {
+                self.segments.push(segment.clone());
+            }
+        }
+
+        self
+    }
+
+    /// Splits the segment at `t`, adding a control point there without changing the shape.
+    pub fn insert_control_point_at(&mut self, t: f32) {
+        if self.segments.is_empty() {
+            return;
+        }
+
+        let (index, local_t) = self.locate(t);
+        let (head, tail) = self.segments[index].split(local_t);
+        self.segments.splice(index..=index, [head, tail]);
+    }
+
+    /// Merges the two segments meeting at `joint` (the end of segment `joint - 1`) into one.
+    /// Exactly undoes [`CompositeCurve::insert_control_point_at`], and approximates otherwise.
+    pub fn remove_control_point(&mut self, joint: usize) -> bool {
+        if joint == 0 || joint >= self.segments.len() {
+            return false;
+        }
+
+        let head = self.segments[joint - 1].points();
+        let tail = self.segments[joint].points();
+
+        // Where the joint sat on the merged curve, judging by the handle lengths around it
+        let before = head[3].distance(head[2]);
+        let after = tail[1].distance(tail[0]);
+        let t = if before + after > 0. { before / (before + after) } else { 0.5 };
+
+        let p1 = if t > 0. { head[0] + (head[1] - head[0]) / t } else { head[1] };
+        let p2 = if t < 1. { tail[3] + (tail[2] - tail[3]) / (1. - t) } else { tail[2] };
+        let merged = self.segments[joint - 1].with_points(vec![head[0], p1, p2, tail[3]]);
+        self.segments.splice(joint - 1..=joint, [merged]);
+
+        true
+    }
+
+    /// The same chain traversed from end to start.
+    pub fn reversed(&self) -> Self {
+        Self::new(self.segments.iter().rev().map(|segment| segment.reversed()).collect())
+    }
+
+    pub fn transform(&mut self, transform: &Transform) {
+        for segment in self.segments.iter_mut() {
+            segment.transform(transform);
+        }
+    }
+
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        let mut composite = self.clone();
+        composite.transform(transform);
+
+        composite
+    }
+
+    pub fn length(&self) -> f32 {
+        self.segments.iter().map(|segment| segment.length()).sum()
+    }
+
+    /// The segment `t` falls into and the local `t` within it.
+    pub fn locate(&self, t: f32) -> (usize, f32) {
+        let count = self.segments.len();
+        let f = t.clamp(0., 1.) * count as f32;
+        let index = (f.floor() as usize).min(count.saturating_sub(1));
+
+        (index, f - index as f32)
+    }
+
+    /// The `t` where a tube of `radius` around the curve is hit by `ray`, nearest the ray's origin,
+    /// see [`BezierCurve::raycast`].
+    pub fn raycast(&self, ray: Ray3d, radius: f32) -> Option<f32> {
+        let count = self.segments.len() as f32;
+        self.segments.iter()
+            .enumerate()
+            .filter_map(|(index, segment)| segment.ray_hit(ray, radius).map(|(t, along_ray)| ((index as f32 + t) / count, along_ray)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(t, _)| t)
+    }
+
+    /// The V coordinate of each joint by the segments' sample tables, from 0 at the start to the
+    /// total sampled length at the end.
+    fn v_offsets(&self) -> Vec<f32> {
+        let mut offsets = Vec::with_capacity(self.segments.len() + 1);
+        offsets.push(0.);
+        for segment in &self.segments {
+            offsets.push(offsets[offsets.len() - 1] + segment.sampled_length());
+        }
+
+        offsets
+    }
+
+    fn v_coordinate(&self, offsets: &[f32], index: usize, local_t: f32) -> f32 {
+        let distance = offsets[index] + self.segments[index].sampled_distance(local_t);
+        let total = offsets[offsets.len() - 1];
+
+        match self.segments[0].v_mode() {
+            VMode::Absolute => distance,
+            VMode::Normalized if total > 0. => distance / total,
+            VMode::Normalized => (index as f32 + local_t) / self.segments.len() as f32,
+        }
+    }
+
+    pub fn get_oriented_point(&self, t: f32) -> OrientedPoint {
+        let (index, local_t) = self.locate(t);
+        let mut point = self.segments[index].get_oriented_point(local_t);
+        point.v_coordinate = self.v_coordinate(&self.v_offsets(), index, local_t);
+
+        point
+    }
+
+    pub fn get_oriented_point_at_distance(&self, distance: f32) -> OrientedPoint {
+        let mut remaining = distance.max(0.);
+        for (index, segment) in self.segments.iter().enumerate() {
+            if remaining <= segment.length() || index == self.segments.len() - 1 {
+                let local_t = segment.t_at_distance(remaining);
+                let mut point = segment.get_oriented_point(local_t);
+                point.v_coordinate = self.v_coordinate(&self.v_offsets(), index, local_t);
+                return point;
+            }
+            remaining -= segment.length();
+        }
+
+        OrientedPoint::default()
+    }
+
+    /// Samples every segment with `subdivisions` steps, without repeating the points where segments meet.
+    pub fn generate_path(&self, subdivisions: u32) -> ExtrudePath {
+        let subdivisions = subdivisions.max(1);
+        let offsets = self.v_offsets();
+        let mut result = Vec::with_capacity(self.segments.len() * subdivisions as usize + 1);
+        for (index, segment) in self.segments.iter().enumerate() {
+            let first = if index == 0 { 0 } else { 1 };
+            for i in first..=subdivisions {
+                let local_t = i as f32 / subdivisions as f32;
+                let mut point = segment.get_oriented_point(local_t);
+                point.v_coordinate = self.v_coordinate(&offsets, index, local_t);
+                result.push(point);
+            }
+        }
+
+        result.into()
+    }
+}
+
+/// The world transforms of the glTF nodes named `prefix.000`, `prefix.001`, ... in numeric order,
+/// with `prefix` itself first if present, for track layouts authored as chains of empties. Nodes whose
+/// asset isn't loaded are skipped; use [`crate::path::ExtrudePath::from_transforms`] to sweep through
+/// them exactly, or [`CompositeCurve::from_gltf_nodes`] for a smooth curve.
+pub fn gltf_node_chain(gltf: &Gltf, nodes: &Assets<GltfNode>, prefix: &str) -> Vec<Transform> {
+    let mut chain: Vec<(i64, &GltfNode)> = gltf.named_nodes.iter()
+        .filter_map(|(name, handle)| {
+            let order = match name.strip_prefix(prefix)? {
+                "" => -1,
+                suffix => suffix.strip_prefix('.')?.parse().ok()?,
+            };
+            Some((order, nodes.get(handle)?))
+        })
+        .collect();
+    chain.sort_by_key(|(order, _)| *order);
+
+    // Node transforms are relative to their parents, so walk down from the roots to find the world ones
+    let mut world: HashMap<usize, Transform> = HashMap::new();
+    let child_indices: Vec<usize> = gltf.nodes.iter()
+        .filter_map(|handle| nodes.get(handle))
+        .flat_map(|node| node.children.iter().map(|child| child.index))
+        .collect();
+    for root in gltf.nodes.iter().filter_map(|handle| nodes.get(handle)).filter(|node| !child_indices.contains(&node.index)) {
+        collect_world_transforms(root, Transform::IDENTITY, &mut world);
+    }
+
+    chain.into_iter()
+        .map(|(_, node)| world.get(&node.index).copied().unwrap_or(node.transform))
+        .collect()
+}
+
+fn collect_world_transforms(node: &GltfNode, parent: Transform, world: &mut HashMap<usize, Transform>) {
+    let transform = parent.mul_transform(node.transform);
+    world.insert(node.index, transform);
+    for child in &node.children {
+        collect_world_transforms(child, transform, world);
+    }
+}
+
+impl From<&CubicCurve<Vec3>> for CompositeCurve {
+    /// A composite curve with one Bézier segment per segment of a `bevy_math` cubic curve (Bézier,
+    /// Hermite, Catmull-Rom, B-spline...), so splines built with Bevy can be extruded directly.
+    fn from(curve: &CubicCurve<Vec3>) -> Self {
+        Self::new(curve.segments().iter().map(BezierCurve::from).collect())
+    }
+}
+
+impl From<&CompositeCurve> for CubicCurve<Vec3> {
+    fn from(curve: &CompositeCurve) -> Self {
+        Self {
+            segments: curve.segments().iter().map(CubicSegment::from).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bend() -> CompositeCurve {
+        CompositeCurve::from_waypoints(&[Vec3::ZERO, Vec3::new(4., 0., -4.), Vec3::new(0., 0., -8.), Vec3::new(4., 0., -12.)], 0., false)
+    }
+
+    #[test]
+    fn v_is_continuous_across_joints() {
+        let composite = bend();
+        for joint in 1..composite.segments().len() {
+            let t = joint as f32 / composite.segments().len() as f32;
+            let before = composite.get_oriented_point(t - 1e-4).v_coordinate;
+            let after = composite.get_oriented_point(t).v_coordinate;
+            assert!((after - before).abs() < 1e-2, "joint {joint}: {before} -> {after}");
+        }
+
+        let path = composite.generate_path(10);
+        assert!(path.windows(2).all(|pair| pair[1].v_coordinate > pair[0].v_coordinate));
+        let last = path[path.len() - 1].v_coordinate;
+        assert!((last - composite.length()).abs() < composite.length() * 0.01, "{last} vs {}", composite.length());
+    }
+
+    #[test]
+    fn normalized_v_spans_the_whole_chain() {
+        let composite = CompositeCurve::new(bend().segments().iter().map(|segment| segment.clone().with_v_mode(VMode::Normalized)).collect());
+        let path = composite.generate_path(10);
+
+        assert_eq!(path[0].v_coordinate, 0.);
+        assert!((path[path.len() - 1].v_coordinate - 1.).abs() < 1e-5);
+        assert!(path.windows(2).all(|pair| pair[1].v_coordinate > pair[0].v_coordinate));
+    }
+
+    #[test]
+    fn continuity_keeps_the_settings_of_the_next_curve() {
+        let previous = BezierCurve::new(vec![Vec3::ZERO, Vec3::NEG_Z, Vec3::new(0., 0., -2.), Vec3::new(0., 0., -3.)], None);
+        let next = BezierCurve::new(vec![Vec3::new(1., 0., -3.), Vec3::new(1., 0., -4.), Vec3::new(2., 0., -5.), Vec3::new(3., 0., -6.)], Some(50))
+            .with_samples(30)
+            .with_v_mode(VMode::Normalized);
+        let joined = Continuity::C1.apply(&previous, &next);
+
+        assert_eq!(joined.points()[0], previous.points()[3]);
+        assert_eq!((joined.resolution(), joined.samples(), joined.v_mode()), (50, 30, VMode::Normalized));
+    }
+
+    #[test]
+    fn removing_an_inserted_control_point_restores_the_curve() {
+        let segment = BezierCurve::new(vec![Vec3::ZERO, Vec3::new(1., 0., -1.), Vec3::new(3., 0., -1.), Vec3::new(4., 0., 0.)], None)
+            .with_samples(25);
+        let mut composite = CompositeCurve::new(vec![segment.clone()]);
+        composite.insert_control_point_at(0.3);
+        assert_eq!(composite.segments().len(), 2);
+
+        assert!(composite.remove_control_point(1));
+        let merged = &composite.segments()[0];
+        for (a, b) in merged.points().iter().zip(segment.points()) {
+            assert!(a.distance(*b) < 1e-4, "{a} vs {b}");
+        }
+        assert_eq!(merged.samples(), 25);
+    }
+}
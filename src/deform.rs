@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use bevy::render::mesh::morph::{MorphAttributes, MorphBuildError, MorphTargetImage};
+use bevy::render::mesh::skinning::SkinnedMeshInverseBindposes;
+use bevy::render::mesh::{MeshVertexAttributeId, VertexAttributeValues};
+use bevy::render::render_asset::RenderAssetUsages;
+
+#[derive(Debug, Clone)]
+pub enum DeformError {
+    MissingAttribute(MeshVertexAttributeId),
+    AttributeCountMismatch { attribute: MeshVertexAttributeId, expected: usize, found: usize },
+    MorphTargetCountMismatch { target: usize, expected: usize, found: usize },
+    IndexOutOfBounds { index: u32, vertex_count: usize },
+    Morph(MorphBuildError),
+}
+
+/// Checks that a mesh can be skinned or morphed by Bevy's renderer: positions and normals present,
+/// every attribute holding one value per vertex and every index pointing at an existing vertex.
+pub fn validate_deformable(mesh: &Mesh) -> Result<(), DeformError> {
+    let vertex_count = mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        .ok_or(DeformError::MissingAttribute(Mesh::ATTRIBUTE_POSITION.id))?
+        .len();
+    if mesh.attribute(Mesh::ATTRIBUTE_NORMAL).is_none() {
+        return Err(DeformError::MissingAttribute(Mesh::ATTRIBUTE_NORMAL.id));
+    }
+
+    for (attribute, values) in mesh.attributes() {
+        if values.len() != vertex_count {
+            return Err(DeformError::AttributeCountMismatch { attribute, expected: vertex_count, found: values.len() });
+        }
+    }
+
+    if let Some(indices) = mesh.indices() {
+        if let Some(index) = indices.iter().find(|i| *i >= vertex_count) {
+            return Err(DeformError::IndexOutOfBounds { index: index as u32, vertex_count });
+        }
+    }
+
+    Ok(())
+}
+
+/// Inserts joint indices and weights in the formats Bevy's skinning expects, normalizing each weight set to sum to one.
+pub fn insert_skin_weights(mesh: &mut Mesh, joint_indices: Vec<[u16; 4]>, joint_weights: Vec<[f32; 4]>) -> Result<(), DeformError> {
+    let vertex_count = mesh.count_vertices();
+    if joint_indices.len() != vertex_count {
+        return Err(DeformError::AttributeCountMismatch { attribute: Mesh::ATTRIBUTE_JOINT_INDEX.id, expected: vertex_count, found: joint_indices.len() });
+    }
+    if joint_weights.len() != vertex_count {
+        return Err(DeformError::AttributeCountMismatch { attribute: Mesh::ATTRIBUTE_JOINT_WEIGHT.id, expected: vertex_count, found: joint_weights.len() });
+    }
+
+    let joint_weights: Vec<[f32; 4]> = joint_weights.into_iter().map(|weights| {
+        let sum: f32 = weights.iter().sum();
+        if sum > 0. { weights.map(|w| w / sum) } else { [1., 0., 0., 0.] }
+    }).collect();
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_JOINT_INDEX, VertexAttributeValues::Uint16x4(joint_indices));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT, joint_weights);
+
+    validate_deformable(mesh)
+}
+
+/// The inverse bind pose of each joint, given the joints' global transforms in the rest pose.
+pub fn inverse_bindposes(joints: &[GlobalTransform]) -> SkinnedMeshInverseBindposes {
+    joints.iter()
+        .map(|joint| joint.compute_matrix().inverse())
+        .collect::<Vec<Mat4>>()
+        .into()
+}
+
+/// Packs per-vertex morph target displacements into a morph target image and attaches it to the mesh.
+pub fn attach_morph_targets(mesh: &mut Mesh, targets: &[Vec<MorphAttributes>], images: &mut Assets<Image>) -> Result<Handle<Image>, DeformError> {
+    validate_deformable(mesh)?;
+
+    let vertex_count = mesh.count_vertices();
+    for (i, target) in targets.iter().enumerate() {
+        if target.len() != vertex_count {
+            return Err(DeformError::MorphTargetCountMismatch { target: i, expected: vertex_count, found: target.len() });
+        }
+    }
+
+    let image = MorphTargetImage::new(targets.iter().map(|target| target.iter().copied()), vertex_count, RenderAssetUsages::RENDER_WORLD)
+        .map_err(DeformError::Morph)?;
+    let handle = images.add(image.0);
+    mesh.set_morph_targets(handle.clone());
+
+    Ok(handle)
+}
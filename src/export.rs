@@ -0,0 +1,240 @@
+use std::io::Write;
+use bevy::prelude::*;
+use bevy::render::mesh::{MeshVertexAttributeId, PrimitiveTopology, VertexAttributeValues};
+
+const GLB_MAGIC: u32 = 0x4654_6c67;
+const GLB_CHUNK_JSON: u32 = 0x4e4f_534a;
+const GLB_CHUNK_BIN: u32 = 0x004e_4942;
+const GL_ARRAY_BUFFER: u32 = 34962;
+const GL_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const GL_FLOAT: u32 = 5126;
+const GL_UNSIGNED_INT: u32 = 5125;
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(std::io::Error),
+    MissingAttribute(MeshVertexAttributeId),
+    /// Only triangle lists can be exported.
+    UnsupportedTopology(PrimitiveTopology),
+    /// glTF accessors can't be empty, so a mesh without vertices can't be written as `.glb`.
+    EmptyMesh,
+}
+
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::MissingAttribute(id) => write!(f, "the mesh has no {id:?} attribute"),
+            Self::UnsupportedTopology(topology) => write!(f, "can't export {topology:?} meshes, only triangle lists"),
+            Self::EmptyMesh => write!(f, "the mesh has no vertices"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ExportError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// The parts of a mesh both formats store.
+struct MeshData<'a> {
+    positions: &'a [[f32; 3]],
+    normals: Option<&'a [[f32; 3]]>,
+    uvs: Option<&'a [[f32; 2]]>,
+    indices: Vec<u32>,
+}
+
+impl<'a> MeshData<'a> {
+    fn new(mesh: &'a Mesh) -> Result<Self, ExportError> {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return Err(ExportError::UnsupportedTopology(mesh.primitive_topology()));
+        }
+
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+            return Err(ExportError::MissingAttribute(Mesh::ATTRIBUTE_POSITION.id));
+        };
+        let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(normals)) => Some(normals.as_slice()),
+            _ => None,
+        };
+        let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => Some(uvs.as_slice()),
+            _ => None,
+        };
+        let indices = match mesh.indices() {
+            Some(indices) => indices.iter().map(|i| i as u32).collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+
+        Ok(Self {
+            positions,
+            normals,
+            uvs,
+            indices,
+        })
+    }
+}
+
+/// Writes a triangle mesh as Wavefront OBJ, with normals and UVs when the mesh has them. V is
+/// flipped, as OBJ puts the origin of the texture at the bottom left.
+pub fn write_obj(mesh: &Mesh, writer: &mut impl Write) -> Result<(), ExportError> {
+    let data = MeshData::new(mesh)?;
+
+    for [x, y, z] in data.positions {
+        writeln!(writer, "v {x} {y} {z}")?;
+    }
+    for [x, y, z] in data.normals.unwrap_or_default() {
+        writeln!(writer, "vn {x} {y} {z}")?;
+    }
+    for [u, v] in data.uvs.unwrap_or_default() {
+        writeln!(writer, "vt {u} {}", 1. - v)?;
+    }
+
+    // OBJ indices start at 1
+    let corner = |i: u32| match (data.uvs.is_some(), data.normals.is_some()) {
+        (true, true) => format!("{0}/{0}/{0}", i + 1),
+        (true, false) => format!("{0}/{0}", i + 1),
+        (false, true) => format!("{0}//{0}", i + 1),
+        (false, false) => format!("{}", i + 1),
+    };
+    for triangle in data.indices.chunks_exact(3) {
+        writeln!(writer, "f {} {} {}", corner(triangle[0]), corner(triangle[1]), corner(triangle[2]))?;
+    }
+
+    Ok(())
+}
+
+/// Writes a triangle mesh as a binary glTF 2.0 (`.glb`) file with a single node, keeping normals
+/// and UVs when the mesh has them.
+pub fn write_glb(mesh: &Mesh, writer: &mut impl Write) -> Result<(), ExportError> {
+    let data = MeshData::new(mesh)?;
+    if data.positions.is_empty() {
+        return Err(ExportError::EmptyMesh);
+    }
+
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut add_view = |bytes: &[u8], target: u32, buffer: &mut Vec<u8>| {
+        buffer_views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{target}}}"#, buffer.len(), bytes.len()));
+        buffer.extend_from_slice(bytes);
+        buffer_views.len() - 1
+    };
+
+    let (min, max) = data.positions.iter().fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), position| {
+        (min.min(Vec3::from_array(*position)), max.max(Vec3::from_array(*position)))
+    });
+    let view = add_view(&float_bytes(data.positions.iter().flatten()), GL_ARRAY_BUFFER, &mut buffer);
+    accessors.push(format!(
+        r#"{{"bufferView":{view},"componentType":{GL_FLOAT},"count":{},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+        data.positions.len(), min.x, min.y, min.z, max.x, max.y, max.z,
+    ));
+    let mut attributes = vec![format!(r#""POSITION":{}"#, accessors.len() - 1)];
+
+    if let Some(normals) = data.normals {
+        let view = add_view(&float_bytes(normals.iter().flatten()), GL_ARRAY_BUFFER, &mut buffer);
+        accessors.push(format!(r#"{{"bufferView":{view},"componentType":{GL_FLOAT},"count":{},"type":"VEC3"}}"#, normals.len()));
+        attributes.push(format!(r#""NORMAL":{}"#, accessors.len() - 1));
+    }
+    if let Some(uvs) = data.uvs {
+        let view = add_view(&float_bytes(uvs.iter().flatten()), GL_ARRAY_BUFFER, &mut buffer);
+        accessors.push(format!(r#"{{"bufferView":{view},"componentType":{GL_FLOAT},"count":{},"type":"VEC2"}}"#, uvs.len()));
+        attributes.push(format!(r#""TEXCOORD_0":{}"#, accessors.len() - 1));
+    }
+
+    let index_bytes: Vec<u8> = data.indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let view = add_view(&index_bytes, GL_ELEMENT_ARRAY_BUFFER, &mut buffer);
+    accessors.push(format!(r#"{{"bufferView":{view},"componentType":{GL_UNSIGNED_INT},"count":{},"type":"SCALAR"}}"#, data.indices.len()));
+
+    let mut json = format!(
+        r#"{{"asset":{{"version":"2.0","generator":"bevy_extrude_mesh"}},"scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"meshes":[{{"primitives":[{{"attributes":{{{}}},"indices":{},"mode":4}}]}}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{}}}]}}"#,
+        attributes.join(","), accessors.len() - 1, accessors.join(","), buffer_views.join(","), buffer.len(),
+    ).into_bytes();
+
+    // Chunks are 4-byte aligned, the JSON padded with spaces and the binary data with zeros
+    json.resize(json.len().next_multiple_of(4), b' ');
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+    let total_length = 12 + 8 + json.len() + 8 + buffer.len();
+
+    writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&(total_length as u32).to_le_bytes())?;
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(&GLB_CHUNK_JSON.to_le_bytes())?;
+    writer.write_all(&json)?;
+    writer.write_all(&(buffer.len() as u32).to_le_bytes())?;
+    writer.write_all(&GLB_CHUNK_BIN.to_le_bytes())?;
+    writer.write_all(&buffer)?;
+
+    Ok(())
+}
+
+fn float_bytes<'a>(values: impl Iterator<Item = &'a f32>) -> Vec<u8> {
+    values.flat_map(|value| value.to_le_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::mesh::Indices;
+    use bevy::render::render_asset::RenderAssetUsages;
+    use super::*;
+
+    fn triangle() -> Mesh {
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0., 0., 0.], [1., 0., 0.], [0., 2., 0.]])
+            .with_inserted_indices(Indices::U32(vec![0, 1, 2]))
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn glb_round_trip() {
+        let mut glb = Vec::new();
+        write_glb(&triangle(), &mut glb).unwrap();
+
+        assert_eq!(&glb[..4], b"glTF");
+        assert_eq!(read_u32(&glb, 4), 2);
+        assert_eq!(read_u32(&glb, 8) as usize, glb.len());
+
+        let json_length = read_u32(&glb, 12) as usize;
+        assert_eq!(json_length % 4, 0);
+        assert_eq!(&glb[16..20], b"JSON");
+        let json = std::str::from_utf8(&glb[20..20 + json_length]).unwrap();
+        assert!(json.contains(r#""count":3,"type":"VEC3","min":[0,0,0],"max":[1,2,0]"#), "{json}");
+
+        let bin = 20 + json_length;
+        assert_eq!(&glb[bin + 4..bin + 8], b"BIN\0");
+        let data = &glb[bin + 8..];
+        assert_eq!(read_u32(&glb, bin) as usize, data.len());
+        let positions: Vec<f32> = (0..9).map(|i| f32::from_bits(read_u32(data, i * 4))).collect();
+        assert_eq!(positions, [0., 0., 0., 1., 0., 0., 0., 2., 0.]);
+        let indices: Vec<u32> = (0..3).map(|i| read_u32(data, 36 + i * 4)).collect();
+        assert_eq!(indices, [0, 1, 2]);
+    }
+
+    #[test]
+    fn glb_rejects_empty_meshes() {
+        let mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<[f32; 3]>::new());
+        assert!(matches!(write_glb(&mesh, &mut Vec::new()), Err(ExportError::EmptyMesh)));
+    }
+
+    #[test]
+    fn obj_indices_start_at_one() {
+        let mut obj = Vec::new();
+        write_obj(&triangle(), &mut obj).unwrap();
+        assert_eq!(String::from_utf8(obj).unwrap(), "v 0 0 0\nv 1 0 0\nv 0 2 0\nf 1 2 3\n");
+    }
+}
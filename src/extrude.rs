@@ -1,17 +1,55 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::Arc;
+use bevy::gltf::GltfMesh;
+use bevy::math::{Mat3A, Vec3A};
 use bevy::prelude::*;
-use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::mesh::{Indices, MeshVertexAttribute, MeshVertexAttributeId, PrimitiveTopology, VertexAttributeValues};
+use bevy::render::primitives::{Aabb, Sphere};
 use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::VertexFormat;
+use bevy::tasks::{ComputeTaskPool, TaskPool};
 use crate::bezier::OrientedPoint;
+use crate::bounds::BoundingVolume;
+pub use crate::geometry::ExtrudedGeometry;
+use crate::{attributes, geometry, merge, path, slice};
 
+const FOLD_CLAMP_RATIO: f32 = 0.9;
+/// The gap around each lightmap chart, as a fraction of its grid cell, so texels don't bleed between charts.
+const LIGHTMAP_PADDING: f32 = 0.05;
+/// The most charts the lightmap is split into, so long and thin extrusions don't shrink to specks.
+const MAX_LIGHTMAP_CHARTS: usize = 64;
+
+/// The distance along the path at each vertex, for scrolling (conveyor, energy beam) and distance-based
+/// blending effects in shaders. Emitted when [`ExtrudeOptions::path_distance`] is set.
+pub const ATTRIBUTE_PATH_DISTANCE: MeshVertexAttribute = MeshVertexAttribute::new("PathDistance", 0x6a1f_3c0d_92b4_e571, VertexFormat::Float32);
+
+#[derive(Asset, TypePath, Clone, Debug)]
 pub struct ExtrudeShape {
-    vertices: Vec<[f32; 3]>,
-    normals: Vec<[f32; 3]>,
+    // Kept as `Vec3A` so extruding doesn't convert every vertex again each call
+    vertices: Vec<Vec3A>,
+    normals: Vec<Vec3A>,
     face_indices: Vec<u32>,
     edges: Vec<u32>,
     u_coords: Vec<f32>,
+    attributes: Vec<(MeshVertexAttribute, VertexAttributeValues)>,
+    /// A material index per edge, empty if the whole shape uses one material.
+    edge_materials: Vec<u32>,
 }
 
+/// The source-mesh attributes [`ExtrudeShape::from_mesh`] drops. Positions, normals and UV0 are
+/// recomputed by the extrusion, and tangents would need rotating along the path.
+const RECOMPUTED_ATTRIBUTES: [MeshVertexAttribute; 4] = [Mesh::ATTRIBUTE_POSITION, Mesh::ATTRIBUTE_NORMAL, Mesh::ATTRIBUTE_UV_0, Mesh::ATTRIBUTE_TANGENT];
+
+/// Built-in attributes a source mesh may have, so they keep their names when carried through.
+const BUILTIN_ATTRIBUTES: [MeshVertexAttribute; 4] = [Mesh::ATTRIBUTE_UV_1, Mesh::ATTRIBUTE_COLOR, Mesh::ATTRIBUTE_JOINT_INDEX, Mesh::ATTRIBUTE_JOINT_WEIGHT];
+
 impl ExtrudeShape {
+    /// Builds a cross-section from the boundary edges of a mesh. Every vertex attribute besides
+    /// positions, normals, UV0 and tangents is kept, to be replicated per edge loop with
+    /// [`ExtrudeOptions::carry_attributes`].
     pub fn from_mesh(mesh: &Mesh) -> Self {
         // Vertices
         let vertices = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().as_float3().unwrap().to_vec();
@@ -23,122 +61,1672 @@ impl ExtrudeShape {
             Indices::U16(i) => index_array = i.iter().map(|x| *x as u32).collect::<Vec<u32>>().clone(),
             Indices::U32(i) => index_array = i.clone(),
         }
+
+        // Weld vertices sharing a position so duplicated vertices (e.g. at UV seams) don't leave inner edges behind
+        let mut welded: HashMap<[i64; 3], u32> = HashMap::new();
+        let canonical: Vec<u32> = vertices.iter().enumerate()
+            .map(|(i, vertex)| *welded.entry(weld_key(*vertex)).or_insert(i as u32))
+            .collect();
+
+        // Edges used by exactly one triangle are on the boundary, whichever way the triangles are wound
         let mut edges = Vec::new();
-        for i in (0..index_array.len()).step_by(3) {
-            // triangle = 3 edges
-            let edge1 = (index_array[i], index_array[i+1]);
-            edges.push(edge1);
-            let edge2 = (index_array[i+1], index_array[i+2]);
-            edges.push(edge2);
-            let edge3 = (index_array[i+2], index_array[i]);
-            edges.push(edge3);
-        }
-
-        // A messy way to remove the unneeded edges of the triangles (the ones in the "center")
-        let edges_clone = edges.clone();
-        let mut removed = Vec::new();
-        edges.retain(|edge| {
-            if removed.contains(&(edge.1, edge.0)) || edges_clone.contains(&(edge.1, edge.0)) {
-                removed.push(*edge);
-                false
-            } else {
-                true
+        let mut edge_counts: HashMap<(u32, u32), u32> = HashMap::new();
+        for triangle in index_array.chunks_exact(3) {
+            for (a, b) in [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])] {
+                let (a, b) = (canonical[a as usize], canonical[b as usize]);
+                if a == b {
+                    continue;
+                }
+                edges.push((a, b));
+                *edge_counts.entry((a.min(b), a.max(b))).or_default() += 1;
             }
-        });
+        }
+        edges.retain(|(a, b)| edge_counts[&(*a.min(b), *a.max(b))] == 1);
         let edges_array: Vec<u32> = edges.iter().flat_map(|edge| [edge.0, edge.1]).collect();
 
         // UVs
         let uvs_result = mesh.attribute(Mesh::ATTRIBUTE_UV_0);
         let mut u_coords = Vec::new();
-        if let Some(uvs) = uvs_result {
-            if let Some(uv_array) = uvs.as_float3() {
-                u_coords = uv_array.iter().map(|uv| uv[0]).collect();
-            }
+        if let Some(VertexAttributeValues::Float32x2(uv_array)) = uvs_result {
+            u_coords = uv_array.iter().map(|uv| uv[0]).collect();
         }
 
-        // Normals
-        // Here we ignore the mesh normals and instead calculate normals that
-        let vertex_count = vertices.len();
-        let mut edge_normals = vec![[0., 0., 0.]; vertex_count];
-        for i in 0..vertex_count {
-            let j = (i + 1) % vertex_count;
-            let edge_vec = Vec3::from_array(vertices[j]) - Vec3::from_array(vertices[i]);
-            edge_normals[i] = Vec3::new(-edge_vec.y, edge_vec.x, edge_vec.z).normalize().to_array();
-        }
+        // Other attributes
+        let attributes = mesh.attributes()
+            .filter(|(id, values)| values.len() == vertices.len() && !RECOMPUTED_ATTRIBUTES.iter().any(|attribute| attribute.id == *id))
+            .map(|(id, values)| (carried_attribute(id, values), values.clone()))
+            .collect();
 
-        let mut vertex_normals = vec![[0., 0., 0.]; vertex_count];
-        for i in 0..vertex_count {
-            let j = (vertex_count + i - 1) % vertex_count;
-            vertex_normals[i] = (Vec3::from_array(edge_normals[i]) + Vec3::from_array(edge_normals[j])).normalize().to_array();
-        }
+        // Normals
+        // Here we ignore the mesh normals and instead calculate normals facing the same way as the
+        // extruded faces, by following the boundary edges around the outlines
+        let vertex_normals = outline_normals(&vertices, &trace_loops(&edges_array));
 
         Self {
-            vertices,
-            normals: vertex_normals,
+            vertices: vertices.into_iter().map(Vec3A::from_array).collect(),
+            normals: vertex_normals.into_iter().map(Vec3A::from_array).collect(),
             face_indices: index_array,
             edges: edges_array,
             u_coords,
+            attributes,
+            edge_materials: Vec::new(),
+        }
+    }
+
+    /// Builds a cross-section from every primitive of a glTF mesh, tagging each primitive's edges with
+    /// its index as the material (see [`extrude_submeshes`]). Primitives whose mesh isn't loaded are skipped.
+    pub fn from_gltf_mesh(gltf_mesh: &GltfMesh, meshes: &Assets<Mesh>) -> Self {
+        let parts: Vec<(Self, u32)> = gltf_mesh.primitives.iter()
+            .enumerate()
+            .filter_map(|(i, primitive)| Some((Self::from_mesh(meshes.get(&primitive.mesh)?), i as u32)))
+            .collect();
+
+        Self::merge(parts)
+    }
+
+    /// Combines several shapes into one, with every edge of a part tagged with the part's material.
+    fn merge(parts: Vec<(Self, u32)>) -> Self {
+        let mut merged = Self::from_outlines(&[]);
+        let has_u_coords = parts.iter().any(|(part, _)| !part.u_coords.is_empty());
+        merged.attributes = parts.first().map(|(part, _)| part.attributes.clone()).unwrap_or_default();
+
+        for (i, (part, material)) in parts.into_iter().enumerate() {
+            let offset = merged.vertices.len() as u32;
+            merged.edges.extend(part.edges.iter().map(|e| e + offset));
+            merged.face_indices.extend(part.face_indices.iter().map(|f| f + offset));
+            merged.edge_materials.extend(std::iter::repeat_n(material, part.edge_count()));
+            if has_u_coords {
+                if part.u_coords.is_empty() {
+                    merged.u_coords.extend(std::iter::repeat_n(0., part.vertices.len()));
+                } else {
+                    merged.u_coords.extend(part.u_coords);
+                }
+            }
+            merged.normals.extend(part.normals);
+            merged.vertices.extend(part.vertices);
+
+            // Only attributes every part has survive
+            if i > 0 {
+                merged.attributes = merged.attributes.iter()
+                    .filter_map(|(attribute, values)| {
+                        let (_, other) = part.attributes.iter().find(|(other, _)| other.id == attribute.id)?;
+                        Some((attribute.clone(), attributes::concat_values(values, other)?))
+                    })
+                    .collect();
+            }
+        }
+
+        merged
+    }
+
+    /// Builds a flat (z = 0) cross-section from an ordered 2D outline, with U running along its arc length.
+    /// Counter-clockwise outlines extrude into outward-facing surfaces.
+    pub fn from_outline(points: &[Vec2], closed: bool) -> Self {
+        Self::from_outlines(&[(points.to_vec(), closed)])
+    }
+
+    /// Like [`ExtrudeShape::from_outline`], for cross-sections made of several outlines (e.g. a tube with its inner wall).
+    pub fn from_outlines(outlines: &[(Vec<Vec2>, bool)]) -> Self {
+        let mut shape = Self {
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            face_indices: Vec::new(),
+            edges: Vec::new(),
+            u_coords: Vec::new(),
+            attributes: Vec::new(),
+            edge_materials: Vec::new(),
+        };
+
+        for (points, closed) in outlines {
+            let offset = shape.vertices.len() as u32;
+            let vertices: Vec<[f32; 3]> = points.iter().map(|p| [p.x, p.y, 0.]).collect();
+            let vertex_count = vertices.len();
+
+            let edge_count = if *closed { vertex_count } else { vertex_count.saturating_sub(1) };
+            shape.edges.extend((0..edge_count).flat_map(|i| [offset + i as u32, offset + ((i + 1) % vertex_count) as u32]));
+
+            // The side faces are wound to face right of the outline's direction, point the normals the same way
+            shape.normals.extend(calculate_normals(&vertices, *closed).into_iter().map(|n| -Vec3A::from_array(n)));
+            shape.u_coords.extend(outline_u_coords(&vertices, *closed));
+            shape.vertices.extend(vertices.into_iter().map(Vec3A::from_array));
+        }
+
+        shape
+    }
+
+    /// Turns an open cross-section into a closed one `thickness` thick, so flat profiles like ribbons
+    /// and sheet metal extrude into watertight solids. The thickness grows behind the faces, i.e. to the
+    /// left of the outline's direction; negative values grow it in front. Closed shapes are returned as is.
+    pub fn solidify(&self, thickness: f32) -> Self {
+        let (outline, closed) = self.outline();
+        if closed || outline.len() < 2 || thickness == 0. {
+            return self.clone();
+        }
+
+        let n = outline.len();
+        let segment_normals: Vec<Vec2> = outline.windows(2).map(|w| (w[1] - w[0]).perp().normalize_or_zero()).collect();
+        let offset: Vec<Vec2> = (0..n)
+            .map(|i| {
+                let before = segment_normals[i.saturating_sub(1)];
+                let after = segment_normals[i.min(n - 2)];
+                let normal = (before + after).normalize_or(after);
+                // Miter, limited so sharp corners don't shoot off
+                let scale = 1. / normal.dot(after).max(0.25);
+                outline[i] + normal * thickness * scale
+            })
+            .collect();
+
+        let mut points = outline;
+        points.extend(offset.into_iter().rev());
+        let area: f32 = (0..points.len()).map(|i| points[i].perp_dot(points[(i + 1) % points.len()])).sum();
+        if area < 0. {
+            points.reverse();
+        }
+
+        Self::from_outline(&points, true)
+    }
+
+    /// The number of boundary edges, each of which extrudes into a strip of quads.
+    pub fn edge_count(&self) -> usize {
+        self.edges.len() / 2
+    }
+
+    /// The number of vertices, i.e. of each edge loop of an extrusion.
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    /// Pairs of vertex indices, one pair per edge.
+    pub(crate) fn edges(&self) -> &[u32] {
+        &self.edges
+    }
+
+    pub(crate) fn vertices(&self) -> &[Vec3A] {
+        &self.vertices
+    }
+
+    /// Tags each edge with a material index, so [`extrude_submeshes`] can split e.g. road surface, curb
+    /// and rail into separate meshes. Edges without an entry use material 0.
+    pub fn with_edge_materials(mut self, materials: Vec<u32>) -> Self {
+        self.edge_materials = materials;
+        self
+    }
+
+    pub fn edge_material(&self, edge: usize) -> u32 {
+        self.edge_materials.get(edge).copied().unwrap_or(0)
+    }
+
+    /// The material indices used by the edges, sorted.
+    pub fn materials(&self) -> Vec<u32> {
+        let mut materials: Vec<u32> = (0..self.edge_count()).map(|edge| self.edge_material(edge)).collect();
+        materials.sort_unstable();
+        materials.dedup();
+        materials
+    }
+
+    /// The part of the shape made of the edges tagged with `material`, keeping only their vertices.
+    pub fn submesh(&self, material: u32) -> Self {
+        let edges: Vec<[u32; 2]> = self.edges.chunks_exact(2)
+            .enumerate()
+            .filter(|(edge, _)| self.edge_material(*edge) == material)
+            .map(|(_, edge)| [edge[0], edge[1]])
+            .collect();
+
+        self.with_edges(&edges, Vec::new())
+    }
+
+    /// A copy of the shape made of just `edges`, keeping only the vertices they use.
+    fn with_edges(&self, edges: &[[u32; 2]], edge_materials: Vec<u32>) -> Self {
+        let mut remap: HashMap<u32, u32> = HashMap::new();
+        let mut kept: Vec<usize> = Vec::new();
+        for &vertex in edges.iter().flatten() {
+            remap.entry(vertex).or_insert_with(|| {
+                kept.push(vertex as usize);
+                kept.len() as u32 - 1
+            });
+        }
+
+        Self {
+            vertices: kept.iter().map(|&i| self.vertices[i]).collect(),
+            normals: kept.iter().map(|&i| self.normals[i]).collect(),
+            face_indices: Vec::new(),
+            edges: edges.iter().flat_map(|edge| [remap[&edge[0]], remap[&edge[1]]]).collect(),
+            u_coords: if self.u_coords.is_empty() { Vec::new() } else { kept.iter().map(|&i| self.u_coords[i]).collect() },
+            attributes: self.attributes.iter()
+                .map(|(attribute, values)| (attribute.clone(), attributes::select_values(values, kept.iter().copied())))
+                .collect(),
+            edge_materials,
+        }
+    }
+
+    pub fn translate(&mut self, offset: Vec2) {
+        for vertex in self.vertices.iter_mut() {
+            vertex[0] += offset.x;
+            vertex[1] += offset.y;
+        }
+    }
+
+    /// Rotates the cross-section counter-clockwise by `angle` radians around its origin.
+    pub fn rotate(&mut self, angle: f32) {
+        let rotation = Vec2::from_angle(angle);
+        for vertex in self.vertices.iter_mut().chain(self.normals.iter_mut()) {
+            let rotated = rotation.rotate(Vec2::new(vertex[0], vertex[1]));
+            vertex[0] = rotated.x;
+            vertex[1] = rotated.y;
+        }
+    }
+
+    /// Scales the cross-section around its origin. A negative component mirrors it, keeping the faces pointing outwards.
+    pub fn scale(&mut self, scale: Vec2) {
+        for vertex in self.vertices.iter_mut() {
+            vertex[0] *= scale.x;
+            vertex[1] *= scale.y;
+        }
+        for normal in self.normals.iter_mut() {
+            let scaled = (Vec2::new(normal[0], normal[1]) / scale).normalize_or_zero();
+            normal[0] = scaled.x;
+            normal[1] = scaled.y;
+        }
+
+        // Mirroring turns the outlines around, reverse the edges so the faces still point outwards
+        if scale.x * scale.y < 0. {
+            for edge in self.edges.chunks_exact_mut(2) {
+                edge.swap(0, 1);
+            }
+        }
+    }
+
+    /// Mirrors the cross-section left to right.
+    pub fn mirror_x(&mut self) {
+        self.scale(Vec2::new(-1., 1.));
+    }
+
+    /// Mirrors the cross-section upside down.
+    pub fn mirror_y(&mut self) {
+        self.scale(Vec2::new(1., -1.));
+    }
+
+    /// Splits every edge longer than `max_edge_length` into equal pieces, e.g. so a wide flat road bends
+    /// smoothly under a deformer. The new vertices take the U coordinate along the edge and the other
+    /// attributes of the edge's start.
+    pub fn subdivide(&mut self, max_edge_length: f32) {
+        if max_edge_length <= 0. {
+            return;
+        }
+
+        let mut sources: Vec<usize> = (0..self.vertices.len()).collect();
+        let mut edges = Vec::with_capacity(self.edges.len());
+        let mut edge_materials = Vec::new();
+        let old_edges = std::mem::take(&mut self.edges);
+        for (e, edge) in old_edges.chunks_exact(2).enumerate() {
+            let (a, b) = (edge[0] as usize, edge[1] as usize);
+            let (start, end) = (Vec3::from(self.vertices[a]), Vec3::from(self.vertices[b]));
+            let pieces = (start.distance(end) / max_edge_length).ceil().max(1.) as usize;
+            // Right of the edge's direction, like the side faces
+            let direction = end - start;
+            let normal = Vec3A::new(direction.y, -direction.x, 0.).normalize_or_zero();
+
+            let mut previous = a as u32;
+            for k in 1..pieces {
+                let f = k as f32 / pieces as f32;
+                let index = self.vertices.len() as u32;
+                self.vertices.push(start.lerp(end, f).into());
+                self.normals.push(normal);
+                if !self.u_coords.is_empty() {
+                    self.u_coords.push(self.u_coords[a] + (self.u_coords[b] - self.u_coords[a]) * f);
+                }
+                sources.push(a);
+                edges.extend([previous, index]);
+                previous = index;
+            }
+            edges.extend([previous, b as u32]);
+            if !self.edge_materials.is_empty() {
+                edge_materials.extend(std::iter::repeat_n(self.edge_material(e), pieces));
+            }
+        }
+
+        self.edges = edges;
+        self.edge_materials = edge_materials;
+        for (_, values) in self.attributes.iter_mut() {
+            *values = attributes::select_values(values, sources.iter().copied());
+        }
+    }
+
+    /// Removes outline vertices where the outline turns by less than `angle_tolerance` radians, merging
+    /// their edges. The turn is measured from the last vertex kept, so gentle curves still keep a vertex
+    /// about every twice `angle_tolerance` of turning, as the chord from the last kept vertex turns half
+    /// as fast as the curve. Vertices between edges of different materials are kept, as are at least
+    /// three vertices of every closed outline.
+    pub fn simplify(&mut self, angle_tolerance: f32) {
+        let materials: HashMap<(u32, u32), u32> = self.edges.chunks_exact(2)
+            .enumerate()
+            .map(|(e, edge)| ((edge[0], edge[1]), self.edge_material(e)))
+            .collect();
+        let position = |i: u32| Vec3::from(self.vertices[i as usize]);
+
+        let mut edges: Vec<[u32; 2]> = Vec::new();
+        let mut edge_materials: Vec<u32> = Vec::new();
+        for (outline, closed) in trace_loops(&self.edges) {
+            let n = outline.len();
+            // The material of the edge leaving the outline's `k`th vertex
+            let material = |k: usize| materials.get(&(outline[k % n], outline[(k + 1) % n])).copied().unwrap_or(0);
+            let turn = |a: usize, b: usize, c: usize| {
+                (position(outline[b]) - position(outline[a])).angle_between(position(outline[c]) - position(outline[b]))
+            };
+
+            // Closed outlines start from a vertex that is kept anyway: a change of material, or else the sharpest corner
+            let start = if closed {
+                (0..n).find(|&k| material(k + n - 1) != material(k))
+                    .or_else(|| (0..n).max_by(|&a, &b| turn((a + n - 1) % n, a, (a + 1) % n).total_cmp(&turn((b + n - 1) % n, b, (b + 1) % n))))
+                    .unwrap_or(0)
+            } else {
+                0
+            };
+            let order: Vec<usize> = (0..n).map(|k| (start + k) % n).collect();
+
+            let mut kept = vec![order[0]];
+            for i in 1..n {
+                let k = order[i];
+                let keep = match order.get(i + 1).or(closed.then_some(&order[0])) {
+                    Some(&next) => material(k + n - 1) != material(k) || turn(kept[kept.len() - 1], k, next) >= angle_tolerance,
+                    None => true,
+                };
+                if keep {
+                    kept.push(k);
+                }
+            }
+            if closed && kept.len() < 3 {
+                kept = if n >= 3 { vec![order[0], order[n / 3], order[2 * n / 3]] } else { order };
+            }
+
+            let segment_count = if closed { kept.len() } else { kept.len() - 1 };
+            for j in 0..segment_count {
+                let (a, b) = (kept[j], kept[(j + 1) % kept.len()]);
+                // The material of the first original edge of the merged run
+                edges.push([outline[a], outline[b]]);
+                edge_materials.push(material(a));
+            }
+        }
+
+        if self.edge_materials.is_empty() {
+            edge_materials.clear();
+        }
+        *self = self.with_edges(&edges, edge_materials);
+    }
+
+    /// U coordinates running from 0 to 1 along each outline, proportional to the distance travelled,
+    /// so texel density stays uniform across the profile. Vertices off the outlines get 0.
+    pub fn arc_length_u_coords(&self) -> Vec<f32> {
+        let mut u_coords = vec![0.; self.vertices.len()];
+        for (outline, closed) in trace_loops(&self.edges) {
+            let positions: Vec<[f32; 3]> = outline.iter().map(|&i| self.vertices[i as usize].to_array()).collect();
+            for (&i, u) in outline.iter().zip(outline_u_coords(&positions, closed)) {
+                u_coords[i as usize] = u;
+            }
+        }
+
+        u_coords
+    }
+
+    /// Cuts `mesh` with the plane through `frame` facing along its direction and builds a cross-section
+    /// from the cut, in the frame's local coordinates. Extruding it along a path starting at `frame`
+    /// continues the sliced mesh seamlessly. Returns `None` if the plane misses the mesh.
+    pub fn from_mesh_slice(mesh: &Mesh, frame: &OrientedPoint) -> Option<Self> {
+        let outlines = slice::slice_mesh(mesh, frame);
+        if outlines.is_empty() {
+            return None;
+        }
+
+        Some(Self::from_outlines(&outlines))
+    }
+
+    /// The height of the top of the cross-section at lateral position `x`, if the outline spans it.
+    pub fn surface_height(&self, x: f32) -> Option<f32> {
+        self.edges.chunks_exact(2)
+            .filter_map(|edge| {
+                let a = self.vertices[edge[0] as usize];
+                let b = self.vertices[edge[1] as usize];
+                let (min, max) = (a[0].min(b[0]), a[0].max(b[0]));
+                if x < min || x > max {
+                    return None;
+                }
+                if max - min <= f32::EPSILON {
+                    return Some(a[1].max(b[1]));
+                }
+
+                Some(a[1] + (b[1] - a[1]) * (x - a[0]) / (b[0] - a[0]))
+            })
+            .reduce(f32::max)
+    }
+
+    /// The boundary of the cross-section as an ordered 2D polyline, and whether it is closed.
+    pub fn outline(&self) -> (Vec<Vec2>, bool) {
+        let (indices, closed) = self.trace_outline();
+        let points = indices.iter().map(|&i| Vec2::new(self.vertices[i as usize][0], self.vertices[i as usize][1])).collect();
+
+        (points, closed)
+    }
+
+    /// Every outline of the cross-section (e.g. a tube's outer and inner wall), as ordered 2D polylines.
+    pub fn outlines(&self) -> Vec<(Vec<Vec2>, bool)> {
+        trace_loops(&self.edges).into_iter()
+            .map(|(indices, closed)| (indices.iter().map(|&i| Vec2::new(self.vertices[i as usize][0], self.vertices[i as usize][1])).collect(), closed))
+            .collect()
+    }
+
+    /// Looks for problems that make the shape extrude into a broken mesh, most severe first. An empty
+    /// list means the shape is fine.
+    pub fn validate(&self) -> Vec<ShapeIssue> {
+        let mut issues = Vec::new();
+        if self.edges.is_empty() {
+            issues.push(ShapeIssue::NoEdges);
+        }
+
+        let point = |i: u32| Vec2::new(self.vertices[i as usize][0], self.vertices[i as usize][1]);
+        let edges: Vec<[u32; 2]> = self.edges.chunks_exact(2).map(|edge| [edge[0], edge[1]]).collect();
+        for (edge, [a, b]) in edges.iter().enumerate() {
+            if point(*a).distance(point(*b)) <= WELD_EPSILON {
+                issues.push(ShapeIssue::DegenerateEdge { edge });
+            }
+        }
+
+        for (first, [a, b]) in edges.iter().enumerate() {
+            let (a, b) = (point(*a), point(*b));
+            for (second, [c, d]) in edges.iter().enumerate().skip(first + 1) {
+                // Neighbouring edges touch at their shared vertex
+                let (c, d) = (point(*c), point(*d));
+                if a == c || a == d || b == c || b == d {
+                    continue;
+                }
+                if segments_intersect(a, b, c, d) {
+                    issues.push(ShapeIssue::SelfIntersection { edges: [first, second] });
+                }
+            }
+        }
+
+        // Outer outlines should run counter-clockwise and the holes inside them clockwise, so all side faces point outwards
+        let outlines = self.outlines();
+        for (index, (points, closed)) in outlines.iter().enumerate() {
+            if !closed || points.len() < 3 {
+                continue;
+            }
+            let depth = outlines.iter().enumerate()
+                .filter(|(other, (other_points, other_closed))| *other != index && *other_closed && polygon_contains(other_points, points[0]))
+                .count();
+            if (signed_area(points) > 0.) != depth.is_multiple_of(2) {
+                issues.push(ShapeIssue::InconsistentWinding { outline: index });
+            }
+        }
+
+        for (i, vertex) in self.vertices.iter().enumerate() {
+            if vertex[2].abs() > WELD_EPSILON {
+                issues.push(ShapeIssue::NonPlanarVertex { vertex: i as u32, z: vertex[2] });
+            }
+        }
+
+        if self.u_coords.len() != self.vertices.len() {
+            issues.push(ShapeIssue::MissingUvs);
+        }
+
+        issues
+    }
+
+    fn trace_outline(&self) -> (Vec<u32>, bool) {
+        if self.edges.is_empty() {
+            return ((0..self.vertices.len() as u32).collect(), false);
+        }
+
+        trace_loops(&self.edges).swap_remove(0)
+    }
+}
+
+/// A problem found by [`ExtrudeShape::validate`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShapeIssue {
+    /// The shape has no boundary edges, so nothing is extruded. Usually the source mesh is closed or has no faces.
+    NoEdges,
+    /// An edge whose ends coincide, giving zero-area faces and undefined normals.
+    DegenerateEdge { edge: usize },
+    /// Two edges cross each other, so the extruded surface passes through itself.
+    SelfIntersection { edges: [usize; 2] },
+    /// A closed outline wound the wrong way for its nesting (outer outlines should be counter-clockwise,
+    /// holes clockwise), so its side faces point inwards.
+    InconsistentWinding { outline: usize },
+    /// A vertex off the z = 0 plane. It is extruded offset along the path, which is rarely intended.
+    NonPlanarVertex { vertex: u32, z: f32 },
+    /// The shape has no source U coordinates; [`UMode::Source`] falls back to [`UMode::ArcLength`].
+    MissingUvs,
+}
+
+impl ShapeIssue {
+    /// Whether the issue breaks the extruded mesh, rather than just being unusual.
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::NoEdges | Self::DegenerateEdge { .. } | Self::SelfIntersection { .. })
+    }
+}
+
+/// Whether segments `a`-`b` and `c`-`d` cross or touch.
+fn segments_intersect(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+    let on_segment = |p: Vec2, q: Vec2, r: Vec2| r.cmpge(p.min(q)).all() && r.cmple(p.max(q)).all();
+    let (d1, d2) = (side(c, d, a), side(c, d, b));
+    let (d3, d4) = (side(a, b, c), side(a, b, d));
+
+    if ((d1 > 0. && d2 < 0.) || (d1 < 0. && d2 > 0.)) && ((d3 > 0. && d4 < 0.) || (d3 < 0. && d4 > 0.)) {
+        return true;
+    }
+
+    (d1 == 0. && on_segment(c, d, a)) || (d2 == 0. && on_segment(c, d, b))
+        || (d3 == 0. && on_segment(a, b, c)) || (d4 == 0. && on_segment(a, b, d))
+}
+
+pub(crate) fn signed_area(points: &[Vec2]) -> f32 {
+    let n = points.len();
+    (0..n).map(|i| points[i].perp_dot(points[(i + 1) % n])).sum::<f32>() * 0.5
+}
+
+/// Even-odd test of whether `point` lies inside the closed polygon `points`.
+pub(crate) fn polygon_contains(points: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        if (a.y > point.y) != (b.y > point.y) && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+/// Orders boundary edges into chains of vertices following the edge directions: open chains first,
+/// starting at vertices no edge leads into, then the closed loops.
+fn trace_loops(edges: &[u32]) -> Vec<(Vec<u32>, bool)> {
+    let next: HashMap<u32, u32> = edges.chunks_exact(2).map(|edge| (edge[0], edge[1])).collect();
+    let targets: HashSet<u32> = next.values().copied().collect();
+    let sources = edges.iter().step_by(2).copied();
+
+    let mut visited: HashSet<u32> = HashSet::new();
+    let mut loops = Vec::new();
+    for start in sources.clone().filter(|i| !targets.contains(i)).chain(sources) {
+        if !visited.insert(start) {
+            continue;
+        }
+
+        let mut chain = vec![start];
+        let mut closed = false;
+        let mut current = start;
+        while let Some(&following) = next.get(&current) {
+            if following == start {
+                closed = true;
+                break;
+            }
+            // Stop at branches into an already traced chain
+            if !visited.insert(following) {
+                break;
+            }
+            chain.push(following);
+            current = following;
+        }
+        loops.push((chain, closed));
+    }
+
+    loops
+}
+
+/// Per-vertex normals of the traced outlines, pointing right of the outline's direction like the side
+/// faces do. Vertices on no outline get a zero normal.
+fn outline_normals(vertices: &[[f32; 3]], loops: &[(Vec<u32>, bool)]) -> Vec<[f32; 3]> {
+    let mut normals = vec![[0., 0., 0.]; vertices.len()];
+    for (indices, closed) in loops {
+        let positions: Vec<[f32; 3]> = indices.iter().map(|&i| vertices[i as usize]).collect();
+        for (&i, normal) in indices.iter().zip(calculate_normals(&positions, *closed)) {
+            normals[i as usize] = normal.map(|n| -n);
+        }
+    }
+
+    normals
+}
+
+/// Positions closer than this are treated as the same vertex when extracting a mesh's boundary.
+const WELD_EPSILON: f32 = 1e-5;
+
+fn weld_key(vertex: [f32; 3]) -> [i64; 3] {
+    vertex.map(|v| (v / WELD_EPSILON).round() as i64)
+}
+
+/// The attribute `values` of a source mesh are stored under. The mesh only keeps the ids of its
+/// attributes, so custom ones get a generic name; the id and format are what the renderer matches on.
+fn carried_attribute(id: MeshVertexAttributeId, values: &VertexAttributeValues) -> MeshVertexAttribute {
+    BUILTIN_ATTRIBUTES.iter()
+        .find(|attribute| attribute.id == id)
+        .cloned()
+        .unwrap_or(MeshVertexAttribute { name: "ExtrudeCarried", id, format: VertexFormat::from(values) })
+}
+
+fn outline_u_coords(vertices: &[[f32; 3]], closed: bool) -> Vec<f32> {
+    let mut u_coords = vec![0.; vertices.len()];
+    let mut total = 0.;
+    for i in 1..vertices.len() {
+        total += Vec3::from_array(vertices[i]).distance(Vec3::from_array(vertices[i - 1]));
+        u_coords[i] = total;
+    }
+    if closed && vertices.len() > 1 {
+        total += Vec3::from_array(vertices[vertices.len() - 1]).distance(Vec3::from_array(vertices[0]));
+    }
+
+    if total > 0. {
+        u_coords.iter_mut().for_each(|u| *u /= total);
+    }
+
+    u_coords
+}
+
+fn calculate_normals(vertices: &[[f32; 3]], closed: bool) -> Vec<[f32; 3]> {
+    let vertex_count = vertices.len();
+    let mut edge_normals = vec![[0., 0., 0.]; vertex_count];
+    for i in 0..vertex_count {
+        let j = (i + 1) % vertex_count;
+        let edge_vec = Vec3::from_array(vertices[j]) - Vec3::from_array(vertices[i]);
+        edge_normals[i] = Vec3::new(-edge_vec.y, edge_vec.x, edge_vec.z).normalize().to_array();
+    }
+    if !closed && vertex_count > 1 {
+        edge_normals[vertex_count - 1] = edge_normals[vertex_count - 2];
+    }
+
+    let mut vertex_normals = vec![[0., 0., 0.]; vertex_count];
+    for i in 0..vertex_count {
+        let j = if !closed && i == 0 { 0 } else { (vertex_count + i - 1) % vertex_count };
+        vertex_normals[i] = (Vec3::from_array(edge_normals[i]) + Vec3::from_array(edge_normals[j])).normalize().to_array();
+    }
+
+    vertex_normals
+}
+
+/// Where the U (across the profile) texture coordinate comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum UMode {
+    /// The U coordinates of the source cross-section mesh, or [`UMode::ArcLength`] if it has none.
+    #[default]
+    Source,
+    /// U proportional to the distance along the outline, see [`ExtrudeShape::arc_length_u_coords`].
+    ArcLength,
+    /// No texture coordinates at all.
+    None,
+}
+
+/// What to do where the path bends tighter than the cross-section is wide, which would make the
+/// inside of the bend fold over itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FoldHandling {
+    /// Leave the geometry as is.
+    #[default]
+    None,
+    /// Pull the vertices on the inside of a bend in towards the path so they stop short of the bend's center.
+    Clamp,
+    /// Drop edge loops that would end up behind the previous one.
+    MergeLoops,
+}
+
+/// The index buffer format of the generated meshes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IndexFormat {
+    /// 16-bit indices whenever the mesh has few enough vertices, halving the index buffer, 32-bit otherwise.
+    #[default]
+    Auto,
+    /// Always 32-bit indices.
+    U32,
+}
+
+/// The primitive topology of the generated meshes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Topology {
+    #[default]
+    TriangleList,
+    /// One strip per edge of the cross-section, stitched together with degenerate triangles: about half
+    /// the indices of a list. Falls back to a list for shapes with faces of their own, and for welded
+    /// or optimized output. Exporters and anything else reading the triangles back expect lists.
+    TriangleStrip,
+}
+
+impl IndexFormat {
+    pub fn indices(&self, indices: Vec<u32>, vertex_count: usize) -> Indices {
+        match self {
+            IndexFormat::Auto if vertex_count <= u16::MAX as usize + 1 => Indices::U16(indices.into_iter().map(|i| i as u16).collect()),
+            _ => Indices::U32(indices),
+        }
+    }
+}
+
+/// The width of the cross-section along the path, as a factor of its X. Called with each path point's
+/// V coordinate, which for generated paths is the distance along the path, so the chunks of a chunked
+/// extrusion agree with each other. Divide by the path's length for a 0 to 1 parameter.
+#[derive(Clone)]
+pub struct WidthFn(pub Arc<dyn Fn(f32) -> f32 + Send + Sync>);
+
+impl WidthFn {
+    pub fn new(f: impl Fn(f32) -> f32 + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl std::fmt::Debug for WidthFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WidthFn")
+    }
+}
+
+/// By identity: clones of the same function hash alike.
+impl Hash for WidthFn {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<()>().hash(state);
+    }
+}
+
+// Called with the path, the cross-section's vertex count and the number of sides
+type AttributeGenerator = dyn Fn(&[OrientedPoint], usize, usize) -> VertexAttributeValues + Send + Sync;
+
+/// A custom vertex attribute filled in by a callback during extrusion, e.g. for vertex colors, ambient
+/// occlusion or lane ids. The callback gets the edge loop index, the cross-section vertex index and
+/// the loop's path point.
+#[derive(Clone)]
+pub struct CustomAttribute {
+    pub attribute: MeshVertexAttribute,
+    generate: Arc<AttributeGenerator>,
+}
+
+impl CustomAttribute {
+    pub fn new<T>(attribute: MeshVertexAttribute, f: impl Fn(usize, usize, &OrientedPoint) -> T + Send + Sync + 'static) -> Self
+    where
+        T: Copy + 'static,
+        Vec<T>: Into<VertexAttributeValues>,
+    {
+        Self {
+            attribute,
+            generate: Arc::new(move |path, shape_vertex_count, sides| {
+                let values: Vec<T> = path.iter().enumerate()
+                    .flat_map(|(i, point)| (0..shape_vertex_count).map(|j| f(i, j, point)).collect::<Vec<T>>())
+                    .collect();
+                values.repeat(sides).into()
+            }),
         }
     }
 }
 
-pub fn extrude(shape: &ExtrudeShape, path: &Vec<OrientedPoint>) -> Mesh {
+impl std::fmt::Debug for CustomAttribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomAttribute").field("attribute", &self.attribute.name).finish_non_exhaustive()
+    }
+}
+
+/// By the attribute's id and the callback's identity.
+impl Hash for CustomAttribute {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.attribute.id.hash(state);
+        Arc::as_ptr(&self.generate).cast::<()>().hash(state);
+    }
+}
+
+/// A color along the path, called with each path point's V coordinate like [`WidthFn`].
+#[derive(Clone)]
+pub struct ColorFn(pub Arc<dyn Fn(f32) -> Color + Send + Sync>);
+
+impl ColorFn {
+    pub fn new(f: impl Fn(f32) -> Color + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl std::fmt::Debug for ColorFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ColorFn")
+    }
+}
+
+/// By identity, like [`WidthFn`].
+impl Hash for ColorFn {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<()>().hash(state);
+    }
+}
+
+/// Where the vertex colors (`Mesh::ATTRIBUTE_COLOR`) of the extrusion come from.
+#[derive(Debug, Clone, Default, Hash)]
+pub enum VertexColors {
+    /// No vertex colors.
+    #[default]
+    None,
+    /// The colors of the cross-section mesh, if it had any.
+    Source,
+    /// A gradient along the path.
+    Gradient(ColorFn),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExtrudeOptions {
+    pub u_mode: UMode,
+    pub fold_handling: FoldHandling,
+    /// Shifts the cross-section sideways (X) and up (Y) relative to the path, e.g. for a guard rail
+    /// next to a road's centerline. The offset is in world units: the frames' scale stretches the
+    /// cross-section but not the offset. See [`crate::path::offset_path`] for spacing the edge loops
+    /// along the offset line instead.
+    pub offset: Vec2,
+    pub index_format: IndexFormat,
+    /// Where the generated meshes are kept: `MAIN_WORLD` keeps a CPU copy around for raycasting or
+    /// colliders, `RENDER_WORLD` alone frees it once the mesh is uploaded.
+    pub asset_usage: RenderAssetUsages,
+    /// Turn the faces inside out, for seeing the extrusion from the inside (tunnels, pipe interiors).
+    pub flip_winding: bool,
+    /// Point the normals the other way. Usually set along with `flip_winding` so lighting matches the visible side.
+    pub flip_normals: bool,
+    /// Also emit the back faces, as a copy of every vertex with the normal flipped and every triangle
+    /// with the winding reversed, so thin ribbons and fences are visible from both sides.
+    pub double_sided: bool,
+    /// Scales the cross-section along its local X only, for rivers that widen, roads with passing
+    /// lanes or tapering bridges.
+    pub width_fn: Option<WidthFn>,
+    /// Write [`ATTRIBUTE_PATH_DISTANCE`]: each edge loop's V coordinate, which for generated paths is the arc length.
+    pub path_distance: bool,
+    pub custom_attributes: Vec<CustomAttribute>,
+    pub vertex_colors: VertexColors,
+    /// Replicate the attributes kept from the source mesh (second UV set, colors, joints and any custom
+    /// attributes, see [`ExtrudeShape::from_mesh`]) on every edge loop.
+    pub carry_attributes: bool,
+    /// Generate `Mesh::ATTRIBUTE_UV_1` for lightmapping: the surface unrolled into charts packed in a
+    /// grid over the unit square, with padding between them. Each chart is a run of path segments about
+    /// as long as the outline, with U along the outline and V along the path, all at the same texel
+    /// density. The edge loop where a chart starts is doubled to give it a seam, and coincident path
+    /// points always start a new chart. Closed outlines overlap along their closing edge unless they
+    /// repeat their first point.
+    pub lightmap_uvs: bool,
+    /// Merge the vertices closer than this to each other, averaging their normals, for watertight
+    /// meshes to cook into colliders or run CSG on. See [`ExtrudedGeometry::weld`] for the details; every
+    /// other attribute is taken from the first of the merged vertices.
+    pub weld: Option<f32>,
+    pub topology: Topology,
+    /// Path points closer than this to the previous one are skipped, so coincident points don't leave
+    /// degenerate quads with broken normals behind. The path's last point is always kept, in place of
+    /// the one before it. `None`, the default, keeps every point: coincident points with different
+    /// rotations or V coordinates are how hard corners and texture seams are written, see
+    /// [`crate::path::polyline`].
+    pub min_segment_length: Option<f32>,
+    /// Reorder triangles and vertices for the GPU's vertex cache, overdraw and vertex fetch, see
+    /// [`geometry::optimize_for_gpu`]. Worth it for meshes that are drawn a lot, like long tracks.
+    #[cfg(feature = "meshopt")]
+    pub optimize: bool,
+}
+
+/// Hashes every option, floats by their bits and callbacks by identity, so meshes can be cached by
+/// the options they were extruded with (see [`crate::kit::CurveLibrary`]).
+impl Hash for ExtrudeOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.u_mode.hash(state);
+        self.fold_handling.hash(state);
+        self.offset.to_array().map(f32::to_bits).hash(state);
+        self.index_format.hash(state);
+        self.asset_usage.bits().hash(state);
+        (self.flip_winding, self.flip_normals, self.double_sided, self.path_distance).hash(state);
+        self.width_fn.hash(state);
+        self.custom_attributes.hash(state);
+        self.vertex_colors.hash(state);
+        (self.carry_attributes, self.lightmap_uvs).hash(state);
+        self.weld.map(f32::to_bits).hash(state);
+        self.topology.hash(state);
+        self.min_segment_length.map(f32::to_bits).hash(state);
+        #[cfg(feature = "meshopt")]
+        self.optimize.hash(state);
+    }
+}
+
+pub fn extrude(shape: &ExtrudeShape, path: &[OrientedPoint]) -> Mesh {
+    extrude_with_options(shape, path, &ExtrudeOptions::default())
+}
+
+pub fn extrude_with_options(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) -> Mesh {
+    let path = extruded_path(shape, path, options);
+    let shape_vertex_count = shape.vertices.len();
+    let sides = if options.double_sided { 2 } else { 1 };
+
+    let mut geometry = extruded_geometry(shape, &path, options);
+    let topology = match strip_indices(shape, path.len().saturating_sub(1), options) {
+        Some(indices) => {
+            geometry.indices = indices;
+            PrimitiveTopology::TriangleStrip
+        }
+        None => PrimitiveTopology::TriangleList,
+    };
+    let mut mesh = geometry_mesh(geometry, topology, options.index_format, options.asset_usage);
+    if options.path_distance {
+        let distances: Vec<f32> = path.iter().flat_map(|point| std::iter::repeat_n(point.v_coordinate, shape_vertex_count)).collect();
+        mesh.insert_attribute(ATTRIBUTE_PATH_DISTANCE, distances.repeat(sides));
+    }
+    let output_vertex_count = shape_vertex_count * path.len() * sides;
+    if options.carry_attributes {
+        for (attribute, values) in &shape.attributes {
+            mesh.insert_attribute(attribute.clone(), attributes::select_values(values, (0..output_vertex_count).map(|v| v % shape_vertex_count)));
+        }
+    }
+
+    let source_colors = shape.attributes.iter().find_map(|(attribute, values)| match values {
+        VertexAttributeValues::Float32x4(colors) if attribute.id == Mesh::ATTRIBUTE_COLOR.id => Some(colors),
+        _ => None,
+    });
+    let colors: Option<Vec<[f32; 4]>> = match &options.vertex_colors {
+        VertexColors::None => None,
+        VertexColors::Source => source_colors.map(|colors| colors.repeat(path.len())),
+        VertexColors::Gradient(color_fn) => Some(path.iter()
+            .flat_map(|point| std::iter::repeat_n(LinearRgba::from(color_fn.0(point.v_coordinate)).to_f32_array(), shape_vertex_count))
+            .collect()),
+    };
+    if let Some(colors) = colors {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors.repeat(sides));
+    }
+    if options.lightmap_uvs {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_1, lightmap_uvs(shape, &path).repeat(sides));
+    }
+    for custom in &options.custom_attributes {
+        mesh.insert_attribute(custom.attribute.clone(), (custom.generate)(&path, shape_vertex_count, sides));
+    }
+    if let Some(epsilon) = options.weld {
+        weld_mesh(&mut mesh, epsilon, options.index_format);
+    }
+    #[cfg(feature = "meshopt")]
+    if options.optimize {
+        optimize_mesh(&mut mesh, options.index_format);
+    }
+
+    mesh
+}
+
+/// Extrudes only the first `progress` (0 to 1) of the path's length, for tracks that grow in during
+/// gameplay; see [`crate::grow::GrowingExtrusion`] for animating it. The end follows `progress`
+/// smoothly rather than jumping from path point to path point, and shapes with faces of their own
+/// (see [`ExtrudeShape::from_mesh`]) are capped there with them.
+pub fn extrude_partial(shape: &ExtrudeShape, path: &[OrientedPoint], progress: f32) -> Mesh {
+    extrude_partial_with_options(shape, path, progress, &ExtrudeOptions::default())
+}
+
+/// Like [`extrude_partial`]. The mesh is a triangle list whatever `options.topology`, so the cap can be added.
+pub fn extrude_partial_with_options(shape: &ExtrudeShape, path: &[OrientedPoint], progress: f32, options: &ExtrudeOptions) -> Mesh {
+    let options = ExtrudeOptions {
+        topology: Topology::TriangleList,
+        ..options.clone()
+    };
+    let path = path::partial_path(path, progress);
+    let mut mesh = extrude_with_options(shape, &path, &options);
+    if options.weld.is_none() {
+        cap_end(&mut mesh, shape, &path, &options);
+    }
+
+    mesh
+}
+
+/// Closes the end of the extrusion with the shape's faces, using copies of the last edge loop's
+/// vertices facing along the path.
+fn cap_end(mesh: &mut Mesh, shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) {
+    let (Some(end), false) = (path.last(), shape.face_indices.is_empty()) else {
+        return;
+    };
+    let loop_vertex_count = shape.vertices.len();
+    let vertex_count = mesh.count_vertices();
+    // Double-sided meshes have their back copy after the front one, so the front's last loop comes first
+    let loop_start = loop_vertex_count * (path.len() - 1);
+    let cap_vertices = loop_start..loop_start + loop_vertex_count;
+    if cap_vertices.end > vertex_count {
+        return;
+    }
+
+    for (_, values) in mesh.attributes_mut() {
+        let copies = attributes::select_values(values, cap_vertices.clone());
+        if let Some(combined) = attributes::concat_values(values, &copies) {
+            *values = combined;
+        }
+    }
+    // Inside-out extrusions see the cap from within
+    let facing = if options.flip_winding { -end.forward() } else { end.forward() };
+    let normal = if options.flip_normals { -facing } else { facing };
+    if let Some(VertexAttributeValues::Float32x3(normals)) = mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL) {
+        normals[vertex_count..].fill(normal.to_array());
+    }
+
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return;
+    };
+    let mut indices: Vec<u32> = mesh.indices().map(|indices| indices.iter().map(|i| i as u32).collect()).unwrap_or_default();
+    for face in shape.face_indices.chunks_exact(3) {
+        let [a, b, c] = [face[0], face[1], face[2]].map(|i| vertex_count as u32 + i);
+        let [pa, pb, pc] = [a, b, c].map(|i| Vec3::from_array(positions[i as usize]));
+        // The source faces may wind either way
+        if (pb - pa).cross(pc - pa).dot(facing) >= 0. {
+            indices.extend_from_slice(&[a, b, c]);
+        } else {
+            indices.extend_from_slice(&[a, c, b]);
+        }
+    }
+    let total = mesh.count_vertices();
+    mesh.insert_indices(options.index_format.indices(indices, total));
+}
+
+/// The first option set that makes the vertices depend on more than the path's point count.
+pub(crate) fn vertex_remapping_option(options: &ExtrudeOptions) -> Option<&'static str> {
+    #[cfg(feature = "meshopt")]
+    if options.optimize {
+        return Some("optimize");
+    }
+    if options.weld.is_some() {
+        Some("weld")
+    } else if options.min_segment_length.is_some() {
+        Some("min_segment_length")
+    } else if options.fold_handling == FoldHandling::MergeLoops {
+        Some("fold_handling")
+    } else if options.lightmap_uvs {
+        // The seam loops between lightmap charts are placed by each path's own segment lengths
+        Some("lightmap_uvs")
+    } else {
+        None
+    }
+}
+
+/// Moves the vertices of `mesh`, extruded from `shape` with the same `options`, to follow `path`,
+/// rewriting only its positions and normals in place. Much cheaper than extruding again when only the
+/// curve's control points moved, as indices, UVs and other attributes stay as they are. Returns
+/// `false`, leaving the mesh untouched, if the new path gives a different vertex count or the options
+/// make the edge loops depend on the path (welding, skipping, merging or adding loops, or reordering
+/// vertices); extrude it again then.
+pub fn update_extrusion(mesh: &mut Mesh, shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) -> bool {
+    if vertex_remapping_option(options).is_some() {
+        return false;
+    }
+
+    let path = extruded_path(shape, path, options);
+    if mesh.count_vertices() != shape.vertices.len() * path.len() * if options.double_sided { 2 } else { 1 } {
+        return false;
+    }
+    let (positions, normals) = extruded_vertices(shape, &path, options);
+
+    if let Some(VertexAttributeValues::Float32x3(values)) = mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION) {
+        *values = positions;
+    }
+    if let Some(VertexAttributeValues::Float32x3(values)) = mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL) {
+        *values = normals;
+    }
+
+    true
+}
+
+/// Like [`extrude`], but returns the raw vertex and index buffers instead of a [`Mesh`], so they can be
+/// welded, simplified or cooked into a collider first. Turn them into a mesh with `Mesh::from`.
+pub fn extrude_raw(shape: &ExtrudeShape, path: &[OrientedPoint]) -> ExtrudedGeometry {
+    extrude_raw_with_options(shape, path, &ExtrudeOptions::default())
+}
+
+/// Like [`extrude_with_options`], but returns the raw buffers. Only the options affecting positions,
+/// normals, UVs and indices apply; extra attributes such as vertex colors are not generated.
+pub fn extrude_raw_with_options(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) -> ExtrudedGeometry {
+    let path = extruded_path(shape, path, options);
+    extruded_geometry(shape, &path, options)
+}
+
+impl From<ExtrudedGeometry> for Mesh {
+    fn from(geometry: ExtrudedGeometry) -> Self {
+        geometry_mesh(geometry, PrimitiveTopology::TriangleList, IndexFormat::default(), RenderAssetUsages::default())
+    }
+}
+
+/// Extrudes the shape straight along `direction` from the origin, see [`crate::path::linear`].
+pub fn extrude_linear(shape: &ExtrudeShape, direction: Vec3, length: f32, segments: u32) -> Mesh {
+    extrude_with_options(shape, &path::linear(direction, length, segments), &ExtrudeOptions::default())
+}
+
+/// Extrudes the shape through a sequence of transforms, e.g. a recorded trajectory, see [`path::ExtrudePath::from_transforms`].
+pub fn extrude_along_transforms(shape: &ExtrudeShape, transforms: &[Transform]) -> Mesh {
+    extrude_with_options(shape, &path::ExtrudePath::from_transforms(transforms), &ExtrudeOptions::default())
+}
+
+/// One mesh per material of the shape's edges (see [`ExtrudeShape::with_edge_materials`]), paired with
+/// the material index, so each part can use its own `StandardMaterial`.
+pub fn extrude_submeshes(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) -> Vec<(u32, Mesh)> {
+    shape.materials()
+        .into_iter()
+        .map(|material| (material, extrude_with_options(&shape.submesh(material), path, options)))
+        .collect()
+}
+
+/// Extrudes `shape` along each of `paths`, e.g. the road segments of a generated city, returning the
+/// meshes in the same order. With `parallel` the paths are spread over the `ComputeTaskPool`.
+pub fn extrude_batch<P: AsRef<[OrientedPoint]> + Sync>(shape: &ExtrudeShape, paths: &[P], options: &ExtrudeOptions, parallel: bool) -> Vec<Mesh> {
+    if parallel && paths.len() > 1 {
+        ComputeTaskPool::get_or_init(TaskPool::default).scope(|scope| {
+            for path in paths {
+                scope.spawn(async move { extrude_with_options(shape, path.as_ref(), options) });
+            }
+        })
+    } else {
+        paths.iter().map(|path| extrude_with_options(shape, path.as_ref(), options)).collect()
+    }
+}
+
+/// Like [`extrude_batch`], appending all the extrusions into a single mesh with [`merge::merge_meshes`]
+/// so they take one draw call. The mesh is always a triangle list.
+pub fn extrude_batch_merged<P: AsRef<[OrientedPoint]> + Sync>(shape: &ExtrudeShape, paths: &[P], options: &ExtrudeOptions, parallel: bool) -> Mesh {
+    let options = ExtrudeOptions {
+        topology: Topology::TriangleList,
+        ..options.clone()
+    };
+    merge::merge_meshes(extrude_batch(shape, paths, &options, parallel), options.index_format)
+}
+
+/// One piece of a chunked extrusion, see [`extrude_chunked`].
+#[derive(Debug, Clone)]
+pub struct MeshChunk {
+    pub mesh: Mesh,
+    /// Bounds of the chunk's vertices, to insert next to the mesh so Bevy can cull each chunk on its own.
+    pub aabb: Aabb,
+    /// A sphere around the chunk's vertices, for distance checks and broad-phase queries.
+    pub sphere: Sphere,
+    /// The path segments the chunk covers.
+    pub segments: Range<usize>,
+}
+
+/// Extrudes the path in chunks of `segments_per_chunk` segments instead of one big mesh, so long
+/// tracks can be frustum-culled and streamed piece by piece. Neighbouring chunks share their boundary edge loop.
+pub fn extrude_chunked(shape: &ExtrudeShape, path: &[OrientedPoint], segments_per_chunk: usize) -> Vec<MeshChunk> {
+    extrude_chunked_with_options(shape, path, segments_per_chunk, &ExtrudeOptions::default())
+}
+
+pub fn extrude_chunked_with_options(shape: &ExtrudeShape, path: &[OrientedPoint], segments_per_chunk: usize, options: &ExtrudeOptions) -> Vec<MeshChunk> {
+    let mut start = 0;
+    path_chunks(path, segments_per_chunk)
+        .into_iter()
+        .map(|chunk| {
+            let mesh = extrude_with_options(shape, chunk, options);
+            let segments = start..start + chunk.len() - 1;
+            start = segments.end;
+
+            let bounds = BoundingVolume::from_mesh(&mesh).unwrap_or_default();
+            MeshChunk {
+                aabb: bounds.aabb,
+                sphere: bounds.sphere,
+                mesh,
+                segments,
+            }
+        })
+        .collect()
+}
+
+/// Just the positions and triangles of the extrusion, without building a [`Mesh`], e.g. for physics colliders.
+pub fn extrude_triangles(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) -> (Vec<Vec3>, Vec<[u32; 3]>) {
+    let path = extruded_path(shape, path, options);
+    if path.is_empty() {
+        return (Vec::new(), Vec::new());
+    }
+
+    let mut vertices: Vec<Vec3> = (0..path.len()).flat_map(|i| edge_loop(shape, &path, i, options)).collect();
+    let mut indices = extruded_indices(shape, path.len() - 1, options.flip_winding);
+    if let Some(epsilon) = options.weld {
+        let positions: Vec<[f32; 3]> = vertices.iter().map(|v| v.to_array()).collect();
+        let (kept, groups) = geometry::weld_positions(&positions, epsilon);
+        vertices = kept.into_iter().map(|v| vertices[v]).collect();
+        indices = geometry::welded_indices(&indices, &groups);
+    }
+    let triangles = indices
+        .chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+        .filter(|[a, b, c]| a != b && b != c && c != a)
+        .collect();
+
+    (vertices, triangles)
+}
+
+/// The position of each shape vertex with the outlines side by side, each unrolled along its length,
+/// and their total length.
+fn unrolled_outlines(shape: &ExtrudeShape) -> (Vec<f32>, f32) {
+    let mut across = vec![0.; shape.vertices.len()];
+    let mut perimeter = 0.;
+    for (outline, closed) in trace_loops(&shape.edges) {
+        let positions: Vec<Vec3> = outline.iter().map(|&i| Vec3::from(shape.vertices[i as usize])).collect();
+        for (k, &i) in outline.iter().enumerate() {
+            if k > 0 {
+                perimeter += positions[k].distance(positions[k - 1]);
+            }
+            across[i as usize] = perimeter;
+        }
+        if closed && positions.len() > 1 {
+            perimeter += positions[positions.len() - 1].distance(positions[0]);
+        }
+    }
+
+    (across, perimeter)
+}
+
+/// The path points where a new lightmap chart starts, each run of segments in between about as long
+/// as the outlines, see [`ExtrudeOptions::lightmap_uvs`]. Coincident points already start one.
+fn lightmap_chart_starts(shape: &ExtrudeShape, path: &[OrientedPoint]) -> Vec<usize> {
+    let (_, perimeter) = unrolled_outlines(shape);
+    let total: f32 = path.windows(2).map(|pair| pair[0].position.distance(pair[1].position)).sum();
+    let chart_length = perimeter.max(total / MAX_LIGHTMAP_CHARTS as f32);
+    if chart_length <= f32::EPSILON {
+        return Vec::new();
+    }
+
+    let mut starts = Vec::new();
+    let mut length = 0.;
+    for i in 1..path.len().saturating_sub(1) {
+        let segment = path[i].position.distance(path[i - 1].position);
+        let next = path[i + 1].position.distance(path[i].position);
+        length += segment;
+        if next <= f32::EPSILON {
+            length = 0.;
+        } else if length + next * 0.5 > chart_length {
+            starts.push(i);
+            length = 0.;
+        }
+    }
+
+    starts
+}
+
+fn lightmap_uvs(shape: &ExtrudeShape, path: &[OrientedPoint]) -> Vec<[f32; 2]> {
+    let (across, perimeter) = unrolled_outlines(shape);
+
+    // The chart of each edge loop and how far along it the loop is
+    let mut charts: Vec<(usize, f32)> = Vec::with_capacity(path.len());
+    let mut lengths = vec![0.];
+    for (i, point) in path.iter().enumerate() {
+        let segment = if i > 0 { point.position.distance(path[i - 1].position) } else { 0. };
+        if i > 0 && segment <= f32::EPSILON && i < path.len() - 1 {
+            lengths.push(0.);
+        } else {
+            *lengths.last_mut().unwrap() += segment;
+        }
+        charts.push((lengths.len() - 1, lengths[lengths.len() - 1]));
+    }
+
+    // Square cells in a grid, with one scale for every chart and both directions to keep texel density even
+    let columns = (lengths.len() as f32).sqrt().ceil() as usize;
+    let rows = lengths.len().div_ceil(columns);
+    let cell = 1. / columns.max(rows) as f32;
+    let padding = cell * LIGHTMAP_PADDING;
+    let longest = lengths.iter().copied().fold(perimeter, f32::max);
+    let scale = (cell - 2. * padding) / longest.max(f32::EPSILON);
+
+    charts.iter()
+        .flat_map(|&(chart, v)| {
+            let corner = Vec2::new((chart % columns) as f32, (chart / columns) as f32) * cell + padding;
+            across.iter().map(move |u| [corner.x + u * scale, corner.y + v * scale])
+        })
+        .collect()
+}
+
+/// The buffers of the extrusion along a path already prepared by [`extruded_path`].
+fn extruded_geometry(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) -> ExtrudedGeometry {
+    let u_coords = match options.u_mode {
+        UMode::Source if !shape.u_coords.is_empty() => Cow::Borrowed(&shape.u_coords),
+        UMode::Source | UMode::ArcLength => Cow::Owned(shape.arc_length_u_coords()),
+        UMode::None => Cow::Owned(Vec::new()),
+    };
+
     let shape_vertex_count = shape.vertices.len();
     let segments = path.len() - 1;
-    let edge_loops = path.len();
-    let vertex_count = shape_vertex_count * edge_loops;
-    let tri_count = shape.edges.len() * segments + 2 * shape.face_indices.len();
-    let index_count = tri_count * 3;
+    let vertex_count = shape_vertex_count * path.len();
+
+    let (mesh_vertices, mesh_normals) = extruded_vertices(shape, path, options);
+    let mut mesh_uvs: Vec<[f32; 2]> = Vec::new();
+    if !u_coords.is_empty() {
+        mesh_uvs = path.iter()
+            .flat_map(|point| (0..shape_vertex_count).map(|j| [u_coords[j], point.v_coordinate]))
+            .collect();
+    }
+
+    let mut mesh_indices = extruded_indices(shape, segments, options.flip_winding);
+
+    if options.double_sided {
+        mesh_uvs.extend_from_within(..);
+        mesh_indices.extend(extruded_indices(shape, segments, !options.flip_winding).into_iter().map(|i| i + vertex_count as u32));
+    }
+
+    ExtrudedGeometry { positions: mesh_vertices, normals: mesh_normals, uvs: mesh_uvs, indices: mesh_indices }
+}
+
+/// The positions and normals of every edge loop, followed by their back side copy for double-sided output.
+pub(crate) fn extruded_vertices(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) -> (Vec<[f32; 3]>, Vec<[f32; 3]>) {
+    let loops = (0..path.len()).map(|i| LoopTransform::new(path, i, options));
+    let (mut mesh_vertices, mut mesh_normals) = geometry::sweep_loops(loops, &shape.vertices, &shape.normals, |transform, vertex, normal| {
+        let normal = transform.normal(normal);
+        (transform.position(vertex), if options.flip_normals { -normal } else { normal })
+    });
+    let vertex_count = mesh_vertices.len();
+
+    if options.double_sided {
+        mesh_vertices.extend_from_within(..);
+        mesh_normals.extend_from_within(..);
+        for normal in &mut mesh_normals[vertex_count..] {
+            *normal = normal.map(|n| -n);
+        }
+    }
+
+    (mesh_vertices, mesh_normals)
+}
 
-    //println!("extrude path (oriented points): {:?}", path);
+fn weld_mesh(mesh: &mut Mesh, epsilon: f32, index_format: IndexFormat) {
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return;
+    };
+    let (kept, groups) = geometry::weld_positions(positions, epsilon);
 
-    let mut mesh_vertices = vec![[0.,0.,0.]; vertex_count];
-    let mut mesh_indices: Vec<u32> = vec![0u32; index_count];
-    let mut mesh_normals: Vec<[f32; 3]> = vec![[0.,0.,0.]; vertex_count];
-    let mut mesh_uvs: Vec<[f32; 2]> = vec![[0.,0.]; vertex_count];
+    for (id, values) in mesh.attributes_mut() {
+        *values = match values {
+            VertexAttributeValues::Float32x3(normals) if id == Mesh::ATTRIBUTE_NORMAL.id => {
+                VertexAttributeValues::Float32x3(geometry::welded_normals(normals, &groups, kept.len()))
+            }
+            _ => attributes::select_values(values, kept.iter().copied()),
+        };
+    }
+    let indices: Vec<u32> = mesh.indices().map(|indices| indices.iter().map(|i| i as u32).collect()).unwrap_or_default();
+    mesh.insert_indices(index_format.indices(geometry::welded_indices(&indices, &groups), kept.len()));
+}
+
+#[cfg(feature = "meshopt")]
+fn optimize_mesh(mesh: &mut Mesh, index_format: IndexFormat) {
+    let (Some(VertexAttributeValues::Float32x3(positions)), Some(indices)) = (mesh.attribute(Mesh::ATTRIBUTE_POSITION), mesh.indices()) else {
+        return;
+    };
+    let mut indices: Vec<u32> = indices.iter().map(|i| i as u32).collect();
+    let sources = geometry::optimize_for_gpu(&mut indices, positions);
+
+    for (_, values) in mesh.attributes_mut() {
+        *values = attributes::select_values(values, sources.iter().copied());
+    }
+    mesh.insert_indices(index_format.indices(indices, sources.len()));
+}
+
+pub(crate) fn geometry_mesh(geometry: ExtrudedGeometry, topology: PrimitiveTopology, index_format: IndexFormat, asset_usage: RenderAssetUsages) -> Mesh {
+    let mut mesh = Mesh::new(topology, asset_usage);
+    mesh.insert_indices(index_format.indices(geometry.indices, geometry.positions.len()));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, geometry.positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, geometry.normals);
+    if !geometry.uvs.is_empty() {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, geometry.uvs);
+    }
+    mesh
+}
+
+/// The path with the width function and fold handling applied, i.e. the edge loops that actually get extruded.
+pub(crate) fn extruded_path<'a>(shape: &ExtrudeShape, path: &'a [OrientedPoint], options: &ExtrudeOptions) -> Cow<'a, [OrientedPoint]> {
+    extruded_path_with_sources(shape, path, options).0
+}
+
+/// Like [`extruded_path`], along with the index in `path` each point came from.
+pub(crate) fn extruded_path_with_sources<'a>(shape: &ExtrudeShape, path: &'a [OrientedPoint], options: &ExtrudeOptions) -> (Cow<'a, [OrientedPoint]>, Vec<usize>) {
+    let mut path = Cow::Borrowed(path);
+    let mut sources: Vec<usize> = (0..path.len()).collect();
+    if let Some(width_fn) = &options.width_fn {
+        path = Cow::Owned(path.iter().cloned().map(|mut point| {
+            point.scale.x *= width_fn.0(point.v_coordinate);
+            point
+        }).collect());
+    }
+
+    if let Some(min_length) = options.min_segment_length {
+        let kept = skip_short_segments(&path, min_length);
+        if kept.len() < path.len() {
+            path = Cow::Owned(kept.iter().map(|&i| path[i].clone()).collect());
+            sources = kept.into_iter().map(|i| sources[i]).collect();
+        }
+    }
+
+    if options.fold_handling == FoldHandling::MergeLoops {
+        let kept = merge_folded_loops(shape, &path);
+        path = Cow::Owned(kept.iter().map(|&i| path[i].clone()).collect());
+        sources = kept.into_iter().map(|i| sources[i]).collect();
+    }
+
+    if options.lightmap_uvs {
+        // A second edge loop where each lightmap chart starts, for the seam between the charts
+        let starts = lightmap_chart_starts(shape, &path);
+        if !starts.is_empty() {
+            let doubled: Vec<usize> = (0..path.len()).flat_map(|i| std::iter::repeat_n(i, 1 + starts.binary_search(&i).is_ok() as usize)).collect();
+            path = Cow::Owned(doubled.iter().map(|&i| path[i].clone()).collect());
+            sources = doubled.into_iter().map(|i| sources[i]).collect();
+        }
+    }
+
+    (path, sources)
+}
 
-    // Vertices + normals + UVs
+/// The indices of the path points at least `min_length` past the previous kept one. The first and
+/// last points are always kept, the last replacing the kept point before it if that is too close.
+fn skip_short_segments(path: &[OrientedPoint], min_length: f32) -> Vec<usize> {
+    let mut kept: Vec<usize> = Vec::with_capacity(path.len());
     for (i, point) in path.iter().enumerate() {
-        let offset = i * shape_vertex_count;
-        for j in 0..shape_vertex_count {
-            let id = offset + j;
-            mesh_vertices[id] = point.local_to_world(Vec3::from_array(shape.vertices[j])).to_array();
-            mesh_normals[id] = point.local_to_world_direction(Vec3::from_array(shape.normals[j])).to_array();
-            if !shape.u_coords.is_empty() {
-                mesh_uvs[id] = [shape.u_coords[j], point.v_coordinate];
+        match kept.last() {
+            Some(&last) if point.position.distance(path[last].position) <= min_length => {
+                if i == path.len() - 1 {
+                    if kept.len() > 1 {
+                        *kept.last_mut().unwrap() = i;
+                    } else {
+                        kept.push(i);
+                    }
+                }
+            }
+            _ => kept.push(i),
+        }
+    }
+
+    kept
+}
+
+/// The world-space positions of the cross-section's vertices at path point `i`.
+pub(crate) fn edge_loop<'a>(shape: &'a ExtrudeShape, path: &[OrientedPoint], i: usize, options: &ExtrudeOptions) -> impl Iterator<Item = Vec3> + 'a {
+    let transform = LoopTransform::new(path, i, options);
+    shape.vertices.iter().map(move |vertex| transform.position(*vertex).into())
+}
+
+/// An edge loop's frame as a matrix, so each vertex takes a few multiply-adds rather than a quaternion
+/// rotation of its own.
+struct LoopTransform {
+    rotation: Mat3A,
+    translation: Vec3A,
+    scale: Vec3A,
+    offset: Vec3A,
+    /// See [`bend_at`], for [`FoldHandling::Clamp`].
+    bend: Option<(Vec3A, f32)>,
+}
+
+impl LoopTransform {
+    fn new(path: &[OrientedPoint], i: usize, options: &ExtrudeOptions) -> Self {
+        let point = &path[i];
+        let bend = match options.fold_handling {
+            FoldHandling::Clamp => bend_at(path, i),
+            _ => None,
+        };
+
+        Self {
+            rotation: Mat3A::from_quat(point.rotation),
+            translation: point.position.into(),
+            scale: point.scale.into(),
+            offset: options.offset.extend(0.).into(),
+            bend: bend.map(|(toward_center, radius)| (toward_center.into(), radius)),
+        }
+    }
+
+    fn position(&self, vertex: Vec3A) -> Vec3A {
+        // The offset isn't scaled along with the cross-section
+        let mut vertex = vertex * self.scale + self.offset;
+        if let Some((toward_center, radius)) = self.bend {
+            let depth = vertex.dot(toward_center);
+            let limit = radius * FOLD_CLAMP_RATIO;
+            if depth > limit {
+                vertex -= toward_center * (depth - limit);
             }
         }
+        self.translation + self.rotation * vertex
+    }
+
+    /// Normals scale inversely to keep them perpendicular to the stretched surface.
+    fn normal(&self, normal: Vec3A) -> Vec3A {
+        self.rotation * (normal / self.scale).normalize_or_zero()
     }
+}
 
-    // Extruded indices
-    let mut tri_index = 0;
-    for i in 0..segments {
-        let offset = i * shape_vertex_count;
-        for j in (0..shape.edges.len()).step_by(2) {
-            let a = offset + shape.edges[j] as usize + shape_vertex_count;
-            let b = offset + shape.edges[j] as usize;
-            let c = offset + shape.edges[j+1] as usize;
-            let d = offset + shape.edges[j+1] as usize + shape_vertex_count;
+/// The strip indices for [`Topology::TriangleStrip`], or `None` where the options call for a list.
+fn strip_indices(shape: &ExtrudeShape, segments: usize, options: &ExtrudeOptions) -> Option<Vec<u32>> {
+    if options.topology != Topology::TriangleStrip || !shape.face_indices.is_empty() || options.weld.is_some() {
+        return None;
+    }
+    #[cfg(feature = "meshopt")]
+    if options.optimize {
+        return None;
+    }
 
-            mesh_indices[tri_index] = a as u32; tri_index += 1;
-            mesh_indices[tri_index] = b as u32; tri_index += 1;
-            mesh_indices[tri_index] = c as u32; tri_index += 1;
-            mesh_indices[tri_index] = c as u32; tri_index += 1;
-            mesh_indices[tri_index] = d as u32; tri_index += 1;
-            mesh_indices[tri_index] = a as u32; tri_index += 1;
+    let loop_vertex_count = shape.vertices.len();
+    let mut indices = geometry::sweep_strip_indices(&shape.edges, loop_vertex_count, segments, options.flip_winding);
+    if options.double_sided {
+        let back_offset = (loop_vertex_count * (segments + 1)) as u32;
+        let back = geometry::sweep_strip_indices(&shape.edges, loop_vertex_count, segments, !options.flip_winding);
+        if let (Some(&last), Some(&first)) = (indices.last(), back.first()) {
+            indices.extend_from_slice(&[last, first + back_offset]);
         }
+        indices.extend(back.into_iter().map(|i| i + back_offset));
     }
 
-    mesh_indices.reverse();
+    Some(indices)
+}
 
-    // Construct the mesh
-    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
-    mesh.insert_indices(Indices::U32(mesh_indices));
-    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, mesh_vertices);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, mesh_normals);
-    if !shape.u_coords.is_empty() {
-        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, mesh_uvs);
+fn extruded_indices(shape: &ExtrudeShape, segments: usize, flip_winding: bool) -> Vec<u32> {
+    geometry::sweep_indices(&shape.edges, shape.vertices.len(), segments, flip_winding)
+}
+
+/// The direction towards the center of the bend at path point `i`, in the point's local space, and the bend's radius.
+fn bend_at(path: &[OrientedPoint], i: usize) -> Option<(Vec3, f32)> {
+    if i == 0 || i + 1 >= path.len() {
+        return None;
     }
 
-    mesh
+    let (a, b, c) = (path[i - 1].position, path[i].position, path[i + 1].position);
+    let (ab, bc, ac) = (b - a, c - b, c - a);
+    let binormal = ab.cross(bc);
+    if binormal.length_squared() <= f32::EPSILON {
+        return None;
+    }
+
+    // Radius of the circle through the three points
+    let radius = ab.length() * bc.length() * ac.length() / (2. * binormal.length());
+    let toward_center = binormal.normalize().cross(ac.normalize());
+
+    Some((path[i].rotation.inverse() * toward_center, radius))
+}
+
+/// The indices of the path points to keep.
+fn merge_folded_loops(shape: &ExtrudeShape, path: &[OrientedPoint]) -> Vec<usize> {
+    let mut result: Vec<usize> = (0..path.len().min(1)).collect();
+    for (i, point) in path.iter().enumerate().skip(1) {
+        let kept = result[result.len() - 1];
+        let previous = &path[kept];
+        let forward = (previous.forward() + point.forward()).normalize_or_zero();
+        let folds = shape.vertices.iter().any(|vertex| {
+            let vertex = Vec3::from(*vertex);
+            (point.local_to_world(vertex) - previous.local_to_world(vertex)).dot(forward) <= 0.
+        });
+
+        // The end of the path always stays
+        if !folds || i == path.len() - 1 {
+            result.push(i);
+        }
+    }
+
+    result
+}
+
+/// Splits a path into runs of `segments_per_chunk` segments. Neighbouring runs share their boundary
+/// point so the chunks meet without gaps.
+pub(crate) fn path_chunks(path: &[OrientedPoint], segments_per_chunk: usize) -> Vec<&[OrientedPoint]> {
+    let segments = path.len().saturating_sub(1);
+    let segments_per_chunk = segments_per_chunk.max(1);
+
+    (0..segments.div_ceil(segments_per_chunk))
+        .map(|chunk| {
+            let start = chunk * segments_per_chunk;
+            &path[start..=(start + segments_per_chunk).min(segments)]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(mesh: &Mesh) -> Vec<[f32; 3]> {
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().as_float3().unwrap().to_vec()
+    }
+
+    #[test]
+    fn update_extrusion_matches_a_fresh_extrusion() {
+        let shape = ExtrudeShape::from_outline(&[Vec2::new(1., 0.), Vec2::new(0., 1.), Vec2::new(-1., 0.)], false);
+        let mut mesh = extrude(&shape, &path::linear(Vec3::NEG_Z, 10., 10));
+        let moved = path::arc(5., 1., 10);
+
+        assert!(update_extrusion(&mut mesh, &shape, &moved, &ExtrudeOptions::default()));
+        assert_eq!(positions(&mesh), positions(&extrude(&shape, &moved)));
+        assert!(!update_extrusion(&mut mesh, &shape, &path::arc(5., 1., 12), &ExtrudeOptions::default()));
+    }
+
+    #[test]
+    fn update_extrusion_rejects_options_that_move_edge_loops() {
+        let shape = ExtrudeShape::from_outline(&[Vec2::new(1., 0.), Vec2::new(0., 1.), Vec2::new(-1., 0.)], false);
+        let path = path::linear(Vec3::NEG_Z, 10., 10);
+        // Same point count, but the segments change length: one gets short, the rest stretch
+        let mut moved = path.clone();
+        for (i, point) in moved.iter_mut().enumerate() {
+            point.position.z = if i == 0 { 0. } else { -0.1 - (i as f32 - 1.) * 3. };
+        }
+
+        let options = [
+            ExtrudeOptions { weld: Some(1e-4), ..default() },
+            ExtrudeOptions { min_segment_length: Some(0.5), ..default() },
+            ExtrudeOptions { fold_handling: FoldHandling::MergeLoops, ..default() },
+            ExtrudeOptions { lightmap_uvs: true, ..default() },
+        ];
+        for options in options {
+            let mut mesh = extrude_with_options(&shape, &path, &options);
+            let before = positions(&mesh);
+            assert!(!update_extrusion(&mut mesh, &shape, &moved, &options), "{options:?}");
+            assert_eq!(positions(&mesh), before);
+        }
+    }
+
+    #[test]
+    fn skip_short_segments_keeps_both_ends() {
+        let short = path::linear(Vec3::NEG_Z, 0.2, 1);
+        assert_eq!(skip_short_segments(&short, 0.5), vec![0, 1]);
+
+        let path = path::linear(Vec3::NEG_Z, 2.2, 11);
+        assert_eq!(skip_short_segments(&path, 0.5), vec![0, 3, 6, 11]);
+    }
+
+    #[test]
+    fn simplify_drops_straight_through_vertices() {
+        // A square with a vertex halfway along every side
+        let points = [(1., 1.), (0., 1.), (-1., 1.), (-1., 0.), (-1., -1.), (0., -1.), (1., -1.), (1., 0.)].map(|(x, y)| Vec2::new(x, y));
+        let mut shape = ExtrudeShape::from_outline(&points, true);
+        shape.simplify(0.01);
+
+        assert_eq!(shape.edge_count(), 4);
+        let (outline, closed) = &shape.outlines()[0];
+        assert!(*closed);
+        for corner in [Vec2::new(1., 1.), Vec2::new(-1., 1.), Vec2::new(-1., -1.), Vec2::new(1., -1.)] {
+            assert!(outline.contains(&corner), "{outline:?}");
+        }
+    }
+
+    #[test]
+    fn simplify_keeps_gentle_curves() {
+        // 64 steps around a circle turn by about 0.1 radians each, so every fifth vertex is kept
+        let circle: Vec<Vec2> = (0..64).map(|i| Vec2::from_angle(i as f32 / 64. * std::f32::consts::TAU)).collect();
+        let mut shape = ExtrudeShape::from_outline(&circle, true);
+        shape.simplify(0.25);
+
+        assert_eq!(shape.edge_count(), 13);
+        let (outline, _) = &shape.outlines()[0];
+        assert!(outline.iter().all(|point| (point.length() - 1.).abs() < 1e-5));
+    }
 }
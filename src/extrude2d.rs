@@ -0,0 +1,363 @@
+use std::f32::consts::PI;
+use bevy::math::cubic_splines::CubicCurve;
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::render_asset::RenderAssetUsages;
+use crate::extrude::{self, IndexFormat};
+use crate::geometry::ExtrudedGeometry;
+
+/// How far the inner corner of a sharp turn may reach, as a factor of the offset. Beyond it the stroke
+/// folds over itself anyway.
+const MAX_INNER_MITER: f32 = 8.;
+
+/// How the outer side of a stroke, or of an extrusion along [`crate::path::polyline`], turns a corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineJoin {
+    /// Extend the edges until they meet, falling back to a bevel where the corner would reach further
+    /// than `limit` times the distance from the centerline.
+    Miter { limit: f32 },
+    /// Cut the corner straight across.
+    Bevel,
+    /// Round the corner off with `segments` pieces per half turn.
+    Round { segments: u32 },
+}
+
+impl Default for LineJoin {
+    fn default() -> Self {
+        LineJoin::Miter { limit: 4. }
+    }
+}
+
+/// How an open stroke ends.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LineCap {
+    /// Square, at the end points.
+    #[default]
+    Butt,
+    /// Square, extended past the end points by half the width.
+    Square,
+    /// A half circle of `segments` pieces.
+    Round { segments: u32 },
+}
+
+/// Which side of a polygon's border [`stroke_polygon`] draws on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StrokeAlignment {
+    Inside,
+    #[default]
+    Centered,
+    Outside,
+}
+
+/// A 1D profile swept along a 2D curve, see [`extrude_2d`].
+#[derive(Debug, Clone)]
+pub struct Stroke2d {
+    /// The positions across the stroke, left of the curve positive, in ascending order. Each is a row of
+    /// vertices along the stroke, e.g. for a road with a darker shoulder texture; U runs from 0 at the
+    /// first to 1 at the last.
+    pub profile: Vec<f32>,
+    /// Runs U from 1 at the first position of the profile to 0 at the last instead.
+    pub flip_u: bool,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub index_format: IndexFormat,
+}
+
+impl Default for Stroke2d {
+    fn default() -> Self {
+        Self::new(1.)
+    }
+}
+
+impl Stroke2d {
+    /// A stroke `width` wide, centered on the curve.
+    pub fn new(width: f32) -> Self {
+        Self {
+            profile: vec![-width / 2., width / 2.],
+            flip_u: false,
+            join: LineJoin::default(),
+            cap: LineCap::default(),
+            index_format: IndexFormat::default(),
+        }
+    }
+
+    /// A stroke `width` wide along the border of `polygon`, on the side given by `alignment`, for
+    /// [`extrude_2d_closed`]. U runs from 0 on the inside to 1 on the outside either way round.
+    pub fn for_polygon(polygon: &[Vec2], width: f32, alignment: StrokeAlignment) -> Self {
+        // Left of the border is inside for counter-clockwise polygons
+        let counter_clockwise = extrude::signed_area(polygon) >= 0.;
+        let (outside, inside) = match alignment {
+            StrokeAlignment::Inside => (0., width),
+            StrokeAlignment::Centered => (-width / 2., width / 2.),
+            StrokeAlignment::Outside => (-width, 0.),
+        };
+
+        if counter_clockwise {
+            Self::new(width).with_profile(vec![outside, inside]).with_flip_u(true)
+        } else {
+            Self::new(width).with_profile(vec![-inside, -outside])
+        }
+    }
+
+    pub fn with_profile(mut self, mut profile: Vec<f32>) -> Self {
+        profile.sort_by(f32::total_cmp);
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_flip_u(mut self, flip_u: bool) -> Self {
+        self.flip_u = flip_u;
+        self
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    fn lateral_extent(&self) -> (f32, f32) {
+        match (self.profile.first(), self.profile.last()) {
+            (Some(min), Some(max)) => (*min, *max),
+            _ => (0., 0.),
+        }
+    }
+
+    fn u_coordinate(&self, offset: f32) -> f32 {
+        let (min, max) = self.lateral_extent();
+        let u = if max > min { (offset - min) / (max - min) } else { 0. };
+        if self.flip_u { 1. - u } else { u }
+    }
+}
+
+/// Sweeps `stroke` along the polyline through `points` in the XY plane, facing +Z, for
+/// `MaterialMesh2dBundle`s: thick lines, rivers and roads in top-down games. V is the distance along
+/// the polyline.
+pub fn extrude_2d(points: &[Vec2], stroke: &Stroke2d) -> Mesh {
+    geometry_mesh(stroke_geometry(points, false, stroke), stroke)
+}
+
+/// Like [`extrude_2d`], joining the last point back to the first instead of capping the ends.
+pub fn extrude_2d_closed(points: &[Vec2], stroke: &Stroke2d) -> Mesh {
+    geometry_mesh(stroke_geometry(points, true, stroke), stroke)
+}
+
+/// A border ribbon `width` wide around the closed `polygon`, inside, outside or centered on its edges,
+/// for selection outlines, territory borders and minimap roads. Holes are separate polygons.
+pub fn stroke_polygon(polygon: &[Vec2], width: f32, alignment: StrokeAlignment, join: LineJoin) -> Mesh {
+    extrude_2d_closed(polygon, &Stroke2d::for_polygon(polygon, width, alignment).with_join(join))
+}
+
+/// Like [`extrude_2d`], along `subdivisions` pieces of each segment of a curve.
+pub fn extrude_2d_curve(curve: &CubicCurve<Vec2>, subdivisions: usize, stroke: &Stroke2d) -> Mesh {
+    let points: Vec<Vec2> = curve.iter_positions(subdivisions).collect();
+    extrude_2d(&points, stroke)
+}
+
+fn geometry_mesh(geometry: ExtrudedGeometry, stroke: &Stroke2d) -> Mesh {
+    extrude::geometry_mesh(geometry, PrimitiveTopology::TriangleList, stroke.index_format, RenderAssetUsages::default())
+}
+
+/// A row of vertices across the stroke.
+#[derive(Clone, Copy)]
+struct Station {
+    position: Vec2,
+    /// Where the outer side of a turn, or both sides elsewhere, are placed per unit of offset.
+    normal: Vec2,
+    /// Where the inner side of a turn is placed per unit of offset.
+    inner: Vec2,
+    /// Positive where the curve turns left, negative right, zero elsewhere.
+    turn: f32,
+    distance: f32,
+}
+
+impl Station {
+    fn straight(position: Vec2, normal: Vec2, distance: f32) -> Self {
+        Self { position, normal, inner: normal, turn: 0., distance }
+    }
+
+    fn vertex(&self, offset: f32) -> Vec2 {
+        if offset * self.turn > 0. {
+            self.position + self.inner * offset
+        } else {
+            self.position + self.normal * offset
+        }
+    }
+}
+
+/// The buffers of [`extrude_2d`] or, when `closed`, [`extrude_2d_closed`], for post-processing.
+pub fn stroke_geometry(points: &[Vec2], closed: bool, stroke: &Stroke2d) -> ExtrudedGeometry {
+    let mut points: Vec<Vec2> = points.to_vec();
+    points.dedup_by(|a, b| a.distance_squared(*b) <= f32::EPSILON);
+    if closed && points.len() > 1 && points[0].distance_squared(points[points.len() - 1]) <= f32::EPSILON {
+        points.pop();
+    }
+    let mut geometry = ExtrudedGeometry::default();
+    if points.len() < 2 || stroke.profile.is_empty() {
+        return geometry;
+    }
+
+    let (min, max) = stroke.lateral_extent();
+    let last = points.len() - 1;
+    if !closed && stroke.cap == LineCap::Square {
+        let half_width = (max - min) / 2.;
+        let (start, end) = ((points[1] - points[0]).normalize(), (points[last] - points[last - 1]).normalize());
+        points[0] -= start * half_width;
+        points[last] += end * half_width;
+    }
+
+    let mut stations = Vec::new();
+    let mut distance = 0.;
+    for i in 0..points.len() {
+        if i > 0 {
+            distance += points[i].distance(points[i - 1]);
+        }
+        let previous = if i > 0 { Some(points[i - 1]) } else if closed { Some(points[last]) } else { None };
+        let next = if i < last { Some(points[i + 1]) } else if closed { Some(points[0]) } else { None };
+        let position = points[i];
+        match (previous, next) {
+            (Some(previous), Some(next)) => join_stations(&mut stations, previous, position, next, distance, stroke.join),
+            (None, Some(next)) => stations.push(Station::straight(position, (next - position).normalize().perp(), distance)),
+            (Some(previous), None) => stations.push(Station::straight(position, (position - previous).normalize().perp(), distance)),
+            (None, None) => {}
+        }
+    }
+    if closed {
+        distance += points[0].distance(points[last]);
+        let first = &stations[0];
+        stations.push(Station { distance, ..*first });
+    }
+
+    // Rows of vertices, quads between consecutive rows
+    let columns = stroke.profile.len();
+    for station in &stations {
+        for &offset in &stroke.profile {
+            let vertex = station.vertex(offset);
+            geometry.positions.push([vertex.x, vertex.y, 0.]);
+            geometry.uvs.push([stroke.u_coordinate(offset), station.distance]);
+        }
+    }
+    for row in 0..stations.len() - 1 {
+        for column in 0..columns - 1 {
+            let a = (row * columns + column) as u32;
+            let b = a + columns as u32;
+            geometry.indices.extend([a, b, b + 1, a, b + 1, a + 1]);
+        }
+    }
+
+    if !closed {
+        if let LineCap::Round { segments } = stroke.cap {
+            let (first, end) = (&stations[0], &stations[stations.len() - 1]);
+            round_cap(&mut geometry, stroke, first.position, first.normal, first.normal.perp(), first.distance, segments);
+            round_cap(&mut geometry, stroke, end.position, end.normal, -end.normal.perp(), end.distance, segments);
+        }
+    }
+
+    geometry.normals = vec![[0., 0., 1.]; geometry.positions.len()];
+    geometry
+}
+
+/// The stations turning the corner at `position`.
+fn join_stations(stations: &mut Vec<Station>, previous: Vec2, position: Vec2, next: Vec2, distance: f32, join: LineJoin) {
+    let (incoming, outgoing) = ((position - previous).normalize(), (next - position).normalize());
+    let (normal_in, normal_out) = (incoming.perp(), outgoing.perp());
+    let turn = incoming.perp_dot(outgoing);
+    let bisector = (normal_in + normal_out).normalize_or_zero();
+    let cos_half = bisector.dot(normal_in);
+    if turn.abs() <= 1e-5 && cos_half > 0. {
+        stations.push(Station::straight(position, normal_in, distance));
+        return;
+    }
+
+    let miter = if cos_half > 1. / MAX_INNER_MITER { bisector / cos_half } else { bisector * MAX_INNER_MITER };
+    let normals: Vec<Vec2> = match join {
+        LineJoin::Miter { limit } if cos_half > 0. && 1. / cos_half <= limit => {
+            stations.push(Station::straight(position, miter, distance));
+            return;
+        }
+        LineJoin::Miter { .. } | LineJoin::Bevel => vec![normal_in, normal_out],
+        LineJoin::Round { segments } => {
+            let angle = normal_in.angle_between(normal_out);
+            let steps = (angle.abs() / PI * segments as f32).ceil().max(1.) as u32;
+            (0..=steps).map(|step| Vec2::from_angle(angle * step as f32 / steps as f32).rotate(normal_in)).collect()
+        }
+    };
+    stations.extend(normals.into_iter().map(|normal| Station { position, normal, inner: miter, turn, distance }));
+}
+
+/// A half circle fan across the end of the stroke at `position`, bulging along `outward`.
+fn round_cap(geometry: &mut ExtrudedGeometry, stroke: &Stroke2d, position: Vec2, normal: Vec2, outward: Vec2, distance: f32, segments: u32) {
+    let (min, max) = stroke.lateral_extent();
+    let center = position + normal * ((min + max) / 2.);
+    let radius = (max - min) / 2.;
+    let direction = -normal.perp();
+    // From the side `outward` turns away from, so the fan stays counter-clockwise at both ends
+    let side = normal.perp_dot(outward);
+    let segments = segments.max(1);
+
+    let start = geometry.positions.len() as u32;
+    let arc = (0..=segments).map(|step| {
+        let angle = PI * step as f32 / segments as f32;
+        center + normal * (radius * angle.cos() * side) + outward * (radius * angle.sin())
+    });
+    for point in std::iter::once(center).chain(arc) {
+        let local = point - position;
+        geometry.positions.push([point.x, point.y, 0.]);
+        geometry.uvs.push([stroke.u_coordinate(local.dot(normal)), distance + local.dot(direction)]);
+    }
+    for step in 0..segments {
+        geometry.indices.extend([start, start + 1 + step, start + 2 + step]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A left turn: 10 along X, then 10 along Y.
+    const CORNER: [Vec2; 3] = [Vec2::ZERO, Vec2::new(10., 0.), Vec2::new(10., 10.)];
+
+    fn positions(geometry: &ExtrudedGeometry) -> Vec<Vec2> {
+        geometry.positions.iter().map(|p| Vec2::new(p[0], p[1])).collect()
+    }
+
+    #[test]
+    fn miter_join() {
+        let geometry = stroke_geometry(&CORNER, false, &Stroke2d::new(2.));
+        let positions = positions(&geometry);
+        // One row at the corner, both sides meeting on the bisector
+        assert_eq!(positions.len(), 6);
+        assert!(positions[2].distance(Vec2::new(11., -1.)) < 1e-5);
+        assert!(positions[3].distance(Vec2::new(9., 1.)) < 1e-5);
+    }
+
+    #[test]
+    fn bevel_join() {
+        let geometry = stroke_geometry(&CORNER, false, &Stroke2d::new(2.).with_join(LineJoin::Bevel));
+        let positions = positions(&geometry);
+        // Two rows at the corner: the outer side cut across, the inner side still mitered
+        assert_eq!(positions.len(), 8);
+        assert!(positions[2].distance(Vec2::new(10., -1.)) < 1e-5);
+        assert!(positions[4].distance(Vec2::new(11., 0.)) < 1e-5);
+        assert!(positions[3].distance(Vec2::new(9., 1.)) < 1e-5);
+        assert!(positions[5].distance(Vec2::new(9., 1.)) < 1e-5);
+    }
+
+    #[test]
+    fn polygon_border_runs_u_from_inside_to_outside() {
+        let square = [Vec2::ZERO, Vec2::new(10., 0.), Vec2::new(10., 10.), Vec2::new(0., 10.)];
+        let reversed: Vec<Vec2> = square.iter().rev().copied().collect();
+        for polygon in [&square[..], &reversed[..]] {
+            let stroke = Stroke2d::for_polygon(polygon, 1., StrokeAlignment::Inside);
+            let geometry = stroke_geometry(polygon, true, &stroke);
+            for (position, uv) in geometry.positions.iter().zip(&geometry.uvs) {
+                let on_border = [position[0], position[1]].iter().any(|c| c.abs() < 1e-4 || (c - 10.).abs() < 1e-4);
+                assert_eq!(uv[0], if on_border { 1. } else { 0. }, "{position:?}");
+            }
+        }
+    }
+}
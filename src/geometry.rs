@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+use std::ops::Neg;
+use glam::{Mat3, Quat, Vec3, Vec3A};
+
+/// A frame along an extrusion path: where an edge loop goes, which way it faces, how it is stretched
+/// and the V coordinate of its vertices. The cross-section's X runs to the right, Y up and the path
+/// continues along -Z.
+#[derive(Debug, Clone)]
+pub struct OrientedPoint {
+    pub position: Vec3,
+    pub rotation: Quat,
+    /// Stretches the cross-section's edge loop at this point along the frame's axes (sideways, up, along).
+    pub scale: Vec3,
+    pub v_coordinate: f32, // the V of the UV coordinates
+}
+
+impl Default for OrientedPoint {
+    fn default() -> Self {
+        Self::new(Vec3::ZERO, Quat::IDENTITY, 0.)
+    }
+}
+
+impl OrientedPoint {
+    pub fn new(position: Vec3, rotation: Quat, v_coordinate: f32) -> Self {
+        Self {
+            position,
+            rotation,
+            scale: Vec3::ONE,
+            v_coordinate,
+        }
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn local_to_world(&self, point: Vec3) -> Vec3 {
+        self.position + self.rotation * (point * self.scale)
+    }
+
+    /// The inverse of [`OrientedPoint::local_to_world`]. Axes the frame's scale flattens to zero come
+    /// back as zero, as every local coordinate along them lands on the same point.
+    pub fn world_to_local(&self, point: Vec3) -> Vec3 {
+        let local = self.rotation.inverse() * (point - self.position);
+        Vec3::select(self.scale.abs().cmpgt(Vec3::splat(f32::EPSILON)), local / self.scale, Vec3::ZERO)
+    }
+
+    /// Rotates `dir` from the frame into world space. Scale is ignored, so unit directions stay unit.
+    pub fn local_to_world_direction(&self, dir: Vec3) -> Vec3 {
+        self.rotation * dir
+    }
+
+    /// The direction of travel along the path.
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+}
+
+/// The rotation of a frame facing along `forward`, with its up axis as close to `up` as possible.
+pub fn frame_rotation(forward: Vec3, up: Vec3) -> Quat {
+    let f = forward.normalize();
+    let r = Vec3::cross(f, up).normalize();
+    let u = Vec3::cross(r, f);
+
+    Quat::from_mat3(&Mat3::from_cols(r, u, f.neg()))
+}
+
+/// The position at `t` on the cubic Bézier curve with the given control points.
+pub fn cubic_bezier_point(points: [Vec3; 4], t: f32) -> Vec3 {
+    let it = 1. - t;
+
+    points[0] * (it * it * it) +
+        points[1] * (3. * it * it * t) +
+        points[2] * (3. * it * t * t) +
+        points[3] * (t * t * t)
+}
+
+/// The derivative (velocity) at `t` of the cubic Bézier curve with the given control points.
+pub fn cubic_bezier_derivative(points: [Vec3; 4], t: f32) -> Vec3 {
+    let it = 1. - t;
+
+    (points[1] - points[0]) * (3. * it * it) +
+        (points[2] - points[1]) * (6. * it * t) +
+        (points[3] - points[2]) * (3. * t * t)
+}
+
+/// `subdivisions + 1` frames along a cubic Bézier curve, evenly spaced in `t`, facing along the curve
+/// with their up axis towards `up`. V is the distance travelled through the frames.
+pub fn cubic_bezier_frames(points: [Vec3; 4], subdivisions: u32, up: Vec3) -> Vec<OrientedPoint> {
+    let subdivisions = subdivisions.max(1);
+    let mut distance = 0.;
+    let mut previous: Option<Vec3> = None;
+
+    (0..=subdivisions)
+        .map(|i| {
+            let t = i as f32 / subdivisions as f32;
+            let position = cubic_bezier_point(points, t);
+            if let Some(previous) = previous {
+                distance += position.distance(previous);
+            }
+            previous = Some(position);
+
+            OrientedPoint::new(position, frame_rotation(cubic_bezier_derivative(points, t), up), distance)
+        })
+        .collect()
+}
+
+/// Vertex and index buffers of an extrusion, ready for post-processing, uploading or physics cooking.
+/// `uvs` is empty when no texture coordinates were generated.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtrudedGeometry {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+impl ExtrudedGeometry {
+    /// Merges every vertex into the first earlier vertex within `epsilon` of it, whatever their normals
+    /// and UVs, so the surface becomes watertight: edge loops that meet at coincident path points share
+    /// vertices, as do the duplicated corners of the cross-section. Merged vertices get the average of their normals and the
+    /// UVs of the first of them, and triangles that collapse are dropped. Not meant for double-sided
+    /// output, whose front and back vertices would cancel out.
+    /// Returns, for each remaining vertex, the first of the vertices merged into it.
+    pub fn weld(&mut self, epsilon: f32) -> Vec<usize> {
+        let (kept, groups) = weld_positions(&self.positions, epsilon);
+        self.normals = welded_normals(&self.normals, &groups, kept.len());
+        self.positions = kept.iter().map(|&v| self.positions[v]).collect();
+        if !self.uvs.is_empty() {
+            self.uvs = kept.iter().map(|&v| self.uvs[v]).collect();
+        }
+        self.indices = welded_indices(&self.indices, &groups);
+
+        kept
+    }
+
+    /// Merges every vertex into the first earlier vertex whose position, normal and UV all lie within
+    /// `epsilon` of its own, such as the ones left behind by coincident path points, keeping hard edges
+    /// and UV seams. Triangles that collapse are dropped. Returns, for each remaining vertex, the first of the vertices merged into it.
+    pub fn weld_matching(&mut self, epsilon: f32) -> Vec<usize> {
+        let (kept, groups) = weld_matching_vertices(&self.positions, &self.normals, &self.uvs, epsilon);
+        self.positions = kept.iter().map(|&v| self.positions[v]).collect();
+        if !self.normals.is_empty() {
+            self.normals = kept.iter().map(|&v| self.normals[v]).collect();
+        }
+        if !self.uvs.is_empty() {
+            self.uvs = kept.iter().map(|&v| self.uvs[v]).collect();
+        }
+        self.indices = welded_indices(&self.indices, &groups);
+
+        kept
+    }
+
+    /// Reorders the buffers for faster rendering, see [`optimize_for_gpu`]. Returns, for each new vertex,
+    /// the vertex it came from.
+    #[cfg(feature = "meshopt")]
+    pub fn optimize(&mut self) -> Vec<usize> {
+        let sources = optimize_for_gpu(&mut self.indices, &self.positions);
+        self.positions = sources.iter().map(|&v| self.positions[v]).collect();
+        self.normals = sources.iter().map(|&v| self.normals[v]).collect();
+        if !self.uvs.is_empty() {
+            self.uvs = sources.iter().map(|&v| self.uvs[v]).collect();
+        }
+
+        sources
+    }
+
+    /// Replaces the normals with the area-weighted average of the faces around each vertex. Vertices
+    /// that are split (at hard edges or UV seams) stay split, so their normals stay faceted.
+    pub fn recompute_normals(&mut self) {
+        let mut normals = vec![Vec3::ZERO; self.positions.len()];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| Vec3::from_array(self.positions[i as usize]));
+            // Not normalized, so larger faces weigh more
+            let normal = (b - a).cross(c - a);
+            for &i in triangle {
+                normals[i as usize] += normal;
+            }
+        }
+        self.normals = normals.into_iter().map(|n| n.normalize_or_zero().to_array()).collect();
+    }
+}
+
+/// The same surface as [`sweep_indices`], as a single triangle strip: one strip per edge running
+/// along the path, joined by repeating the last index of one and the first of the next, which makes
+/// degenerate triangles the GPU skips.
+pub fn sweep_strip_indices(edges: &[u32], loop_vertex_count: usize, segments: usize, flip_winding: bool) -> Vec<u32> {
+    let mut indices: Vec<u32> = Vec::with_capacity(edges.len() * (segments + 2));
+    for edge in edges.chunks_exact(2) {
+        let (first, second) = if flip_winding { (edge[0], edge[1]) } else { (edge[1], edge[0]) };
+        // Every strip has an even length, so the next one starts with the same winding
+        if let Some(&last) = indices.last() {
+            indices.extend_from_slice(&[last, first]);
+        }
+        for i in 0..=segments {
+            let offset = (i * loop_vertex_count) as u32;
+            indices.extend_from_slice(&[offset + first, offset + second]);
+        }
+    }
+
+    indices
+}
+
+/// Reorders the triangles for the GPU's vertex cache and then for less overdraw, and the vertices in
+/// the order the triangles first use them, leaving out unused ones. Long, thin extrusions otherwise
+/// reference vertices far apart in the buffer. Rewrites `indices` in place and returns, for each new
+/// vertex, the index of the old vertex it came from.
+#[cfg(feature = "meshopt")]
+pub fn optimize_for_gpu(indices: &mut Vec<u32>, positions: &[[f32; 3]]) -> Vec<usize> {
+    let vertex_count = positions.len();
+    let mut optimized = meshopt::optimize_vertex_cache(indices, vertex_count);
+    let vertices = meshopt::VertexDataAdapter::new(meshopt::typed_to_bytes(positions), std::mem::size_of::<[f32; 3]>(), 0)
+        .expect("positions are tightly packed");
+    // Allow the cache hit ratio to get up to 5% worse in exchange for less overdraw
+    meshopt::optimize_overdraw_in_place(&mut optimized, &vertices, 1.05);
+
+    let remap = meshopt::optimize_vertex_fetch_remap(&optimized, vertex_count);
+    let mut sources = vec![0; remap.iter().filter(|&&new| new != u32::MAX).count()];
+    for (old, &new) in remap.iter().enumerate() {
+        if new != u32::MAX {
+            sources[new as usize] = old;
+        }
+    }
+    *indices = optimized.into_iter().map(|i| remap[i as usize]).collect();
+
+    sources
+}
+
+/// Groups each vertex with the first earlier vertex within `epsilon` of it, or starts a new group.
+/// Returns the first vertex of each group, in order, and the group of every vertex.
+pub fn weld_positions(positions: &[[f32; 3]], epsilon: f32) -> (Vec<usize>, Vec<u32>) {
+    group_nearby(positions, epsilon, |_, _| true)
+}
+
+/// Like [`weld_positions`], but only groups vertices whose normals and UVs are within `epsilon` of
+/// each other as well. Either may be empty.
+pub fn weld_matching_vertices(positions: &[[f32; 3]], normals: &[[f32; 3]], uvs: &[[f32; 2]], epsilon: f32) -> (Vec<usize>, Vec<u32>) {
+    let close = |a: &[f32], b: &[f32]| a.iter().zip(b).all(|(a, b)| (a - b).abs() <= epsilon);
+    group_nearby(positions, epsilon, |a, b| {
+        normals.get(a).zip(normals.get(b)).is_none_or(|(a, b)| close(a, b))
+            && uvs.get(a).zip(uvs.get(b)).is_none_or(|(a, b)| close(a, b))
+    })
+}
+
+/// The grouping behind [`weld_positions`], with `matches` deciding whether two nearby vertices may
+/// share a group. Vertices are bucketed in an `epsilon` grid and checked against the groups started
+/// in the neighbouring cells too, so close vertices on either side of a cell border still meet.
+fn group_nearby(positions: &[[f32; 3]], epsilon: f32, matches: impl Fn(usize, usize) -> bool) -> (Vec<usize>, Vec<u32>) {
+    let epsilon = epsilon.max(f32::EPSILON);
+    let mut cells: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+    let mut kept: Vec<usize> = Vec::new();
+    let mut groups = Vec::with_capacity(positions.len());
+    for (v, position) in positions.iter().enumerate() {
+        let point = Vec3::from_array(*position);
+        let cell = position.map(|x| (x / epsilon).floor() as i64);
+        let neighbours = (0..27).map(|i| [cell[0] + i % 3 - 1, cell[1] + i / 3 % 3 - 1, cell[2] + i / 9 - 1]);
+        let found = neighbours
+            .filter_map(|neighbour| cells.get(&neighbour))
+            .flatten()
+            .copied()
+            .filter(|&group| {
+                let first = kept[group as usize];
+                point.distance(Vec3::from_array(positions[first])) <= epsilon && matches(v, first)
+            })
+            .min();
+
+        let group = found.unwrap_or_else(|| {
+            kept.push(v);
+            let group = kept.len() as u32 - 1;
+            cells.entry(cell).or_default().push(group);
+            group
+        });
+        groups.push(group);
+    }
+
+    (kept, groups)
+}
+
+/// The normal of each group from [`weld_positions`]: the normalized sum of its vertices' normals.
+pub fn welded_normals(normals: &[[f32; 3]], groups: &[u32], group_count: usize) -> Vec<[f32; 3]> {
+    let mut sums = vec![Vec3::ZERO; group_count];
+    for (normal, &group) in normals.iter().zip(groups) {
+        sums[group as usize] += Vec3::from_array(*normal);
+    }
+
+    sums.into_iter().map(|n| n.normalize_or_zero().to_array()).collect()
+}
+
+/// Triangle indices pointing at the groups from [`weld_positions`], without the triangles that
+/// collapsed into a line or a point.
+pub fn welded_indices(indices: &[u32], groups: &[u32]) -> Vec<u32> {
+    indices.chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]].map(|i| groups[i as usize]))
+        .filter(|[a, b, c]| a != b && b != c && c != a)
+        .flatten()
+        .collect()
+}
+
+/// Sweeps a cross-section along `path`: one copy of `vertices` (in the frame's local space) per path
+/// point, joined into quads along `edges` (pairs of vertex indices). UVs are the vertex's U and the
+/// point's V. Edges run with the surface to their right, so counter-clockwise outlines face outwards.
+pub fn sweep(vertices: &[Vec3], normals: &[Vec3], u_coords: &[f32], edges: &[u32], path: &[OrientedPoint]) -> ExtrudedGeometry {
+    let vertices: Vec<Vec3A> = vertices.iter().map(|vertex| Vec3A::from(*vertex)).collect();
+    let normals: Vec<Vec3A> = (0..vertices.len()).map(|j| normals.get(j).map_or(Vec3A::ZERO, |normal| Vec3A::from(*normal))).collect();
+    let (positions, normals) = sweep_loops(path, &vertices, &normals, |point, vertex, normal| {
+        // Normals scale inversely to keep them perpendicular to the stretched surface
+        let normal = (Vec3::from(normal) / point.scale).normalize_or_zero();
+        (point.local_to_world(vertex.into()).into(), point.local_to_world_direction(normal).into())
+    });
+    let uvs = path.iter()
+        .flat_map(|point| (0..vertices.len()).map(|j| [u_coords.get(j).copied().unwrap_or(0.), point.v_coordinate]))
+        .collect();
+    let indices = sweep_indices(edges, vertices.len(), path.len().saturating_sub(1), false);
+
+    ExtrudedGeometry { positions, normals, uvs, indices }
+}
+
+/// One copy of `vertices` and `normals` per item of `loops`, each moved into place by `place`. The
+/// vertex part of both [`sweep`] and the extrusion.
+pub(crate) fn sweep_loops<L>(loops: impl IntoIterator<Item = L>, vertices: &[Vec3A], normals: &[Vec3A], place: impl Fn(&L, Vec3A, Vec3A) -> (Vec3A, Vec3A)) -> (Vec<[f32; 3]>, Vec<[f32; 3]>) {
+    let loops = loops.into_iter();
+    let capacity = loops.size_hint().0 * vertices.len();
+    let (mut positions, mut world_normals) = (Vec::with_capacity(capacity), Vec::with_capacity(capacity));
+    for item in loops {
+        for (vertex, normal) in vertices.iter().zip(normals) {
+            let (position, normal) = place(&item, *vertex, *normal);
+            positions.push(position.to_array());
+            world_normals.push(normal.to_array());
+        }
+    }
+
+    (positions, world_normals)
+}
+
+/// The triangles joining `segments + 1` consecutive edge loops of `loop_vertex_count` vertices,
+/// two per edge and segment. `flip_winding` turns them to face the other way.
+pub fn sweep_indices(edges: &[u32], loop_vertex_count: usize, segments: usize, flip_winding: bool) -> Vec<u32> {
+    let mut indices = Vec::with_capacity(edges.len() * segments * 3);
+    for i in 0..segments {
+        let offset = (i * loop_vertex_count) as u32;
+        let next = offset + loop_vertex_count as u32;
+        for edge in edges.chunks_exact(2) {
+            let (a, b, c, d) = (next + edge[0], offset + edge[0], offset + edge[1], next + edge[1]);
+            indices.extend_from_slice(&[a, b, c, c, d, a]);
+        }
+    }
+
+    // The quads above wind clockwise seen from outside a counter-clockwise cross-section. Reversing
+    // the whole list reverses every triangle, so the faces point outwards; leaving it turns them inwards.
+    if !flip_winding {
+        indices.reverse();
+    }
+
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weld_merges_close_points_across_cell_borders() {
+        // 0.0099 and 0.0101 round to different cells of a 0.01 grid
+        let positions = [[0.0099, 0., 0.], [0.0101, 0., 0.], [0.5, 0., 0.], [0.0099, 0.02, 0.]];
+        let (kept, groups) = weld_positions(&positions, 1e-3);
+
+        assert_eq!(kept, vec![0, 2, 3]);
+        assert_eq!(groups, vec![0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn weld_matching_keeps_seams() {
+        let positions = [[0., 0., 0.], [0., 0., 1e-5], [0., 0., 0.]];
+        let normals = [[0., 1., 0.], [0., 1., 0.], [1., 0., 0.]];
+        let (kept, groups) = weld_matching_vertices(&positions, &normals, &[], 1e-4);
+
+        assert_eq!(kept, vec![0, 2]);
+        assert_eq!(groups, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn weld_drops_collapsed_triangles() {
+        let mut geometry = ExtrudedGeometry {
+            positions: vec![[0., 0., 0.], [1., 0., 0.], [1., 0., 1e-6], [0., 0., 1.]],
+            normals: vec![[0., 1., 0.]; 4],
+            uvs: Vec::new(),
+            indices: vec![0, 1, 2, 0, 2, 3],
+        };
+        assert_eq!(geometry.weld(1e-4), vec![0, 1, 3]);
+        assert_eq!(geometry.indices, vec![0, 1, 2]);
+    }
+}
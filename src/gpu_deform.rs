@@ -0,0 +1,98 @@
+use bevy::asset::load_internal_asset;
+use bevy::pbr::{ExtendedMaterial, MaterialExtension};
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+use crate::bezier::OrientedPoint;
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+use crate::path::{self, cumulative_distances, interpolate_path};
+
+const PATH_DEFORM_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x6b1f_02d4_93a5_4c87_8e3a_1f5c_d270_b941);
+const PATH_DEFORM_VERTEX_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x6b1f_02d4_93a5_4c87_8e3a_1f5c_d270_b942);
+const PATH_DEFORM_PREPASS_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x6b1f_02d4_93a5_4c87_8e3a_1f5c_d270_b943);
+
+/// A `StandardMaterial` that bends a straight mesh from [`extrude_straight`] along a path on the GPU.
+pub type PathDeformMaterial = ExtendedMaterial<StandardMaterial, PathDeformExtension>;
+
+/// Registers [`PathDeformMaterial`] and its shaders. Kept apart from [`crate::plugin::ExtrudeMeshPlugin`]
+/// as it needs the renderer.
+pub struct PathDeformPlugin;
+
+impl Plugin for PathDeformPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, PATH_DEFORM_HANDLE, "shaders/path_deform.wgsl", Shader::from_wgsl);
+        load_internal_asset!(app, PATH_DEFORM_VERTEX_HANDLE, "shaders/path_deform_vertex.wgsl", Shader::from_wgsl);
+        load_internal_asset!(app, PATH_DEFORM_PREPASS_HANDLE, "shaders/path_deform_prepass.wgsl", Shader::from_wgsl);
+        app.add_plugins(MaterialPlugin::<PathDeformMaterial>::default());
+    }
+}
+
+/// Extrudes `shape` along a straight line of `length` down -Z, the mesh [`PathDeformMaterial`] bends
+/// along its path. Its bounds are those of the straight mesh, so give the entity a matching `Aabb`
+/// or `NoFrustumCulling`.
+pub fn extrude_straight(shape: &ExtrudeShape, length: f32, segments: u32, options: &ExtrudeOptions) -> Mesh {
+    extrude::extrude_with_options(shape, &path::linear(Vec3::NEG_Z, length, segments), options)
+}
+
+/// The path a [`PathDeformMaterial`] bends its mesh along, as frames at equal distances. Editing the
+/// curve then only means uploading new frames with [`PathDeformExtension::set_path`], rather than
+/// extruding the mesh again. Morph targets and skinning are not supported.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct PathDeformExtension {
+    /// The distance between consecutive frames.
+    #[uniform(100)]
+    frame_spacing: f32,
+    /// Three per frame: the position, the rotation quaternion and the scale.
+    #[storage(101, read_only)]
+    frames: Vec<Vec4>,
+    frame_count: usize,
+}
+
+impl PathDeformExtension {
+    /// Samples `path` at `frame_count` (at least two) frames. More frames follow tight bends better.
+    pub fn new(path: &[OrientedPoint], frame_count: usize) -> Self {
+        let mut extension = Self {
+            frame_spacing: 1.,
+            frames: Vec::new(),
+            frame_count: frame_count.max(2),
+        };
+        extension.set_path(path);
+        extension
+    }
+
+    pub fn set_path(&mut self, path: &[OrientedPoint]) {
+        let fallback = [OrientedPoint::default()];
+        let path = if path.is_empty() { &fallback[..] } else { path };
+        let distances = cumulative_distances(path);
+        let length = distances[distances.len() - 1];
+        self.frame_spacing = (length / (self.frame_count - 1) as f32).max(f32::EPSILON);
+
+        let mut previous = Quat::IDENTITY;
+        self.frames = (0..self.frame_count)
+            .flat_map(|i| {
+                let frame = interpolate_path(path, &distances, self.frame_spacing * i as f32);
+                // Keep neighbouring quaternions in the same hemisphere so the shader blends the short way round
+                let rotation = if frame.rotation.dot(previous) < 0. { -frame.rotation } else { frame.rotation };
+                previous = rotation;
+                [frame.position.extend(1.), Vec4::from(rotation), frame.scale.extend(1.)]
+            })
+            .collect();
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+}
+
+impl MaterialExtension for PathDeformExtension {
+    fn vertex_shader() -> ShaderRef {
+        PATH_DEFORM_VERTEX_HANDLE.into()
+    }
+
+    fn prepass_vertex_shader() -> ShaderRef {
+        PATH_DEFORM_PREPASS_HANDLE.into()
+    }
+
+    fn deferred_vertex_shader() -> ShaderRef {
+        PATH_DEFORM_PREPASS_HANDLE.into()
+    }
+}
@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+use crate::path::ExtrudePath;
+
+/// An extrusion that grows along its path, for tracks drawing themselves in. The entity's mesh is
+/// regenerated with [`extrude::extrude_partial_with_options`] whenever `progress` changes, and
+/// `progress` advances by `speed` every second until the whole path is covered.
+#[derive(Component, Clone, Debug)]
+pub struct GrowingExtrusion {
+    pub shape: Handle<ExtrudeShape>,
+    pub path: ExtrudePath,
+    pub options: ExtrudeOptions,
+    /// How much of the path's length is extruded, from 0 to 1.
+    pub progress: f32,
+    /// Added to `progress` per second. Zero leaves it to be set by hand.
+    pub speed: f32,
+}
+
+impl GrowingExtrusion {
+    pub fn new(shape: Handle<ExtrudeShape>, path: impl Into<ExtrudePath>, speed: f32) -> Self {
+        Self {
+            shape,
+            path: path.into(),
+            options: ExtrudeOptions::default(),
+            progress: 0.,
+            speed,
+        }
+    }
+
+    pub fn with_options(mut self, options: ExtrudeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress >= 1.
+    }
+}
+
+pub(crate) fn grow_extrusions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    shapes: Res<Assets<ExtrudeShape>>,
+    mut extrusions: Query<(Entity, &mut GrowingExtrusion, Option<&Handle<Mesh>>)>,
+) {
+    for (entity, mut extrusion, mesh_handle) in &mut extrusions {
+        if extrusion.speed != 0. && !extrusion.is_finished() {
+            extrusion.progress = (extrusion.progress + extrusion.speed * time.delta_seconds()).clamp(0., 1.);
+        }
+        if mesh_handle.is_some() && !extrusion.is_changed() {
+            continue;
+        }
+        let Some(shape) = shapes.get(&extrusion.shape) else {
+            continue;
+        };
+
+        let mesh = extrude::extrude_partial_with_options(shape, &extrusion.path, extrusion.progress, &extrusion.options);
+        match mesh_handle {
+            Some(handle) => {
+                meshes.insert(handle, mesh);
+            }
+            None => {
+                commands.entity(entity).insert(meshes.add(mesh));
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use crate::bezier::OrientedPoint;
+use crate::path::ExtrudePath;
+
+#[cfg(feature = "csv")]
+const PATH_CSV_HEADER: &str = "x,y,z,qx,qy,qz,qw,sx,sy,sz,v";
+
+#[derive(Debug)]
+pub enum InterchangeError {
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    /// The data parsed but doesn't have the expected layout, e.g. a point with two coordinates.
+    Format { entry: usize, message: String },
+}
+
+impl std::fmt::Display for InterchangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json(error) => write!(f, "{error}"),
+            Self::Format { entry, message } => write!(f, "entry {entry}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for InterchangeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Control points as a JSON array of `[x, y, z]`, see [`crate::composite::CompositeCurve::control_points`].
+#[cfg(feature = "json")]
+pub fn control_points_to_json(points: &[Vec3]) -> String {
+    serde_json::Value::from(points.iter().map(|point| point.to_array().to_vec()).collect::<Vec<_>>()).to_string()
+}
+
+#[cfg(feature = "json")]
+pub fn control_points_from_json(json: &str) -> Result<Vec<Vec3>, InterchangeError> {
+    let values: Vec<Vec<f32>> = serde_json::from_str(json).map_err(InterchangeError::Json)?;
+    values.iter().enumerate()
+        .map(|(entry, values)| Ok(Vec3::from_array(fixed(values, entry)?)))
+        .collect()
+}
+
+/// A sampled path as a JSON array of `{ "position", "rotation" (x, y, z, w), "scale", "v" }` objects.
+#[cfg(feature = "json")]
+pub fn path_to_json(path: &[OrientedPoint]) -> String {
+    let points: Vec<serde_json::Value> = path.iter()
+        .map(|point| serde_json::json!({
+            "position": point.position.to_array(),
+            "rotation": point.rotation.to_array(),
+            "scale": point.scale.to_array(),
+            "v": point.v_coordinate,
+        }))
+        .collect();
+
+    serde_json::Value::from(points).to_string()
+}
+
+/// Reads a path written by [`path_to_json`]. `scale` may be left out and defaults to one.
+#[cfg(feature = "json")]
+pub fn path_from_json(json: &str) -> Result<ExtrudePath, InterchangeError> {
+    let values: Vec<serde_json::Value> = serde_json::from_str(json).map_err(InterchangeError::Json)?;
+    values.iter().enumerate()
+        .map(|(entry, value)| {
+            let field = |name: &str| -> Result<Vec<f32>, InterchangeError> {
+                serde_json::from_value(value.get(name).cloned().unwrap_or_default())
+                    .map_err(|_| InterchangeError::Format { entry, message: format!("missing or invalid `{name}`") })
+            };
+            let scale = match value.get("scale") {
+                Some(_) => Vec3::from_array(fixed(&field("scale")?, entry)?),
+                None => Vec3::ONE,
+            };
+            let v = value.get("v").and_then(serde_json::Value::as_f64)
+                .ok_or_else(|| InterchangeError::Format { entry, message: "missing or invalid `v`".into() })?;
+
+            Ok(OrientedPoint::new(Vec3::from_array(fixed(&field("position")?, entry)?), Quat::from_array(fixed(&field("rotation")?, entry)?).normalize(), v as f32)
+                .with_scale(scale))
+        })
+        .collect()
+}
+
+/// One `x,y,z` line per control point, without a header.
+#[cfg(feature = "csv")]
+pub fn control_points_to_csv(points: &[Vec3]) -> String {
+    points.iter().map(|point| format!("{},{},{}\n", point.x, point.y, point.z)).collect()
+}
+
+/// Reads `x,y,z` lines, skipping blank lines and a header line if there is one. Extra columns are ignored.
+#[cfg(feature = "csv")]
+pub fn control_points_from_csv(csv: &str) -> Result<Vec<Vec3>, InterchangeError> {
+    csv_rows(csv)
+        .map(|(entry, values)| {
+            let values = values?;
+            Ok(Vec3::from_array(fixed(values.get(..3).unwrap_or(&values), entry)?))
+        })
+        .collect()
+}
+
+/// A sampled path as CSV with a header line, one line of position, rotation (x, y, z, w), scale and V per point.
+#[cfg(feature = "csv")]
+pub fn path_to_csv(path: &[OrientedPoint]) -> String {
+    let mut csv = format!("{PATH_CSV_HEADER}\n");
+    for point in path {
+        let (p, r, s) = (point.position, point.rotation, point.scale);
+        csv += &format!("{},{},{},{},{},{},{},{},{},{},{}\n", p.x, p.y, p.z, r.x, r.y, r.z, r.w, s.x, s.y, s.z, point.v_coordinate);
+    }
+
+    csv
+}
+
+/// Reads a path written by [`path_to_csv`]: lines of position, rotation, scale and V.
+#[cfg(feature = "csv")]
+pub fn path_from_csv(csv: &str) -> Result<ExtrudePath, InterchangeError> {
+    csv_rows(csv)
+        .map(|(entry, values)| {
+            let [x, y, z, qx, qy, qz, qw, sx, sy, sz, v] = fixed(&values?, entry)?;
+            Ok(OrientedPoint::new(Vec3::new(x, y, z), Quat::from_xyzw(qx, qy, qz, qw).normalize(), v)
+                .with_scale(Vec3::new(sx, sy, sz)))
+        })
+        .collect()
+}
+
+/// The numbers on each non-blank line, numbered from 0. The first line is taken for a header and
+/// skipped when none of its fields is a number, so a mistyped first row is still reported.
+#[cfg(feature = "csv")]
+fn csv_rows(csv: &str) -> impl Iterator<Item = (usize, Result<Vec<f32>, InterchangeError>)> + '_ {
+    csv.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .filter(|(entry, line)| !(*entry == 0 && line.split(',').all(|value| value.trim().parse::<f32>().is_err())))
+        .map(|(entry, line)| {
+            let values = line.split(',')
+                .map(|value| value.trim().parse::<f32>().map_err(|_| InterchangeError::Format { entry, message: format!("`{}` isn't a number", value.trim()) }))
+                .collect();
+            (entry, values)
+        })
+}
+
+fn fixed<const N: usize>(values: &[f32], entry: usize) -> Result<[f32; N], InterchangeError> {
+    values.try_into().map_err(|_| InterchangeError::Format { entry, message: format!("expected {N} values, found {}", values.len()) })
+}
@@ -0,0 +1,188 @@
+use std::f32::consts::TAU;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use crate::bezier::OrientedPoint;
+use crate::extrude::ExtrudeShape;
+use crate::path::ExtrudePath;
+
+/// A planar junction where several paths extruded with the same cross-section meet, e.g. a T-, Y- or
+/// X-intersection of roads. The arms are trimmed back to `radius` around `center`, and the hole they
+/// leave is filled by a patch joining the arms' end loops.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Junction {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl Junction {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self {
+            center,
+            radius,
+        }
+    }
+
+    /// Cuts off the ends of `path` lying inside the junction, ending it exactly on the junction's radius.
+    pub fn trim(&self, path: &[OrientedPoint]) -> ExtrudePath {
+        let inside = |point: &OrientedPoint| point.position.distance(self.center) < self.radius;
+        let Some(first) = path.iter().position(|point| !inside(point)) else {
+            return ExtrudePath::default();
+        };
+        let last = path.iter().rposition(|point| !inside(point)).unwrap_or(first);
+
+        let mut result = Vec::with_capacity(last - first + 3);
+        if first > 0 {
+            result.push(self.crossing(&path[first - 1], &path[first]));
+        }
+        result.extend_from_slice(&path[first..=last]);
+        if last + 1 < path.len() {
+            result.push(self.crossing(&path[last + 1], &path[last]));
+        }
+
+        result.into()
+    }
+
+    /// The surface filling the junction between the trimmed `arms`. Each arm contributes the end loop
+    /// lying closest to the center; the loops are joined by a flat top and vertical side walls down to
+    /// the bottom of `shape`, so the arms should come in roughly level.
+    pub fn patch_mesh(&self, shape: &ExtrudeShape, arms: &[Vec<OrientedPoint>]) -> Mesh {
+        let (outline, _) = shape.outline();
+        let left = outline.iter().map(|p| p.x).reduce(f32::min).unwrap_or(0.);
+        let right = outline.iter().map(|p| p.x).reduce(f32::max).unwrap_or(0.);
+        let bottom = outline.iter().map(|p| p.y).reduce(f32::min).unwrap_or(0.);
+
+        let ends: Vec<&OrientedPoint> = arms.iter()
+            .filter_map(|arm| {
+                let (first, last) = (arm.first()?, arm.last()?);
+                Some(if first.position.distance(self.center) <= last.position.distance(self.center) { first } else { last })
+            })
+            .collect();
+        let up = ends.iter().map(|end| end.local_to_world_direction(Vec3::Y)).sum::<Vec3>().normalize_or(Vec3::Y);
+
+        // Top and bottom corners of every arm's end loop, tagged with the arm they belong to
+        let mut corners: Vec<(usize, Vec3, Vec3)> = Vec::with_capacity(ends.len() * 2);
+        for (arm, end) in ends.iter().enumerate() {
+            for x in [left, right] {
+                let top = shape.surface_height(x).unwrap_or(0.);
+                corners.push((arm, end.local_to_world(Vec3::new(x, top, 0.)), end.local_to_world(Vec3::new(x, bottom, 0.))));
+            }
+        }
+
+        // Counter-clockwise around the junction, seen from above
+        let basis = Quat::from_rotation_arc(up, Vec3::Y);
+        let angle = |point: Vec3| {
+            let local = basis * (point - self.center);
+            (-local.z).atan2(local.x).rem_euclid(TAU)
+        };
+        corners.sort_by(|a, b| angle(a.1).total_cmp(&angle(b.1)));
+
+        let mut vertices: Vec<[f32; 3]> = Vec::new();
+        let mut normals: Vec<[f32; 3]> = Vec::new();
+        let mut uvs: Vec<[f32; 2]> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        if corners.len() < 3 {
+            return build_mesh(vertices, normals, uvs, indices);
+        }
+
+        let mean_top = corners.iter().map(|corner| corner.1).sum::<Vec3>() / corners.len() as f32;
+        let hub = self.center + up * up.dot(mean_top - self.center);
+        let planar_uv = |point: Vec3| {
+            let local = basis * (point - self.center) / (2. * self.radius.max(f32::EPSILON));
+            [local.x + 0.5, local.z + 0.5]
+        };
+
+        // Top: a fan around the hub
+        vertices.push(hub.to_array());
+        normals.push(up.to_array());
+        uvs.push(planar_uv(hub));
+        for corner in &corners {
+            vertices.push(corner.1.to_array());
+            normals.push(up.to_array());
+            uvs.push(planar_uv(corner.1));
+        }
+        let n = corners.len() as u32;
+        for i in 0..n {
+            indices.extend_from_slice(&[0, 1 + i, 1 + (i + 1) % n]);
+        }
+
+        // Sides: walls between neighbouring arms, the arms themselves close the rest
+        for i in 0..corners.len() {
+            let (a, b) = (&corners[i], &corners[(i + 1) % corners.len()]);
+            if a.0 == b.0 {
+                continue;
+            }
+
+            let normal = (b.1 - a.1).cross(up).normalize_or_zero();
+            let width = a.1.distance(b.1);
+            let base = vertices.len() as u32;
+            for (position, uv) in [(a.1, [0., 0.]), (a.2, [0., 1.]), (b.2, [width, 1.]), (b.1, [width, 0.])] {
+                vertices.push(position.to_array());
+                normals.push(normal.to_array());
+                uvs.push(uv);
+            }
+            indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
+        }
+
+        build_mesh(vertices, normals, uvs, indices)
+    }
+
+    fn crossing(&self, inner: &OrientedPoint, outer: &OrientedPoint) -> OrientedPoint {
+        // Distance to the center grows roughly linearly along a short segment
+        let (d0, d1) = (inner.position.distance(self.center), outer.position.distance(self.center));
+        let f = if d1 > d0 { ((self.radius - d0) / (d1 - d0)).clamp(0., 1.) } else { 1. };
+
+        OrientedPoint::new(
+            inner.position.lerp(outer.position, f),
+            inner.rotation.slerp(outer.rotation, f),
+            inner.v_coordinate + (outer.v_coordinate - inner.v_coordinate) * f,
+        ).with_scale(inner.scale.lerp(outer.scale, f))
+    }
+}
+
+fn build_mesh(vertices: Vec<[f32; 3]>, normals: Vec<[f32; 3]>, uvs: Vec<[f32; 2]>, indices: Vec<u32>) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+    mesh.insert_indices(Indices::U32(indices));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arm(direction: Vec3) -> Vec<OrientedPoint> {
+        // From far out in `direction` towards the center
+        let mut path = crate::path::linear(-direction, 10., 10);
+        path.transform(&Transform::from_translation(direction * 10.));
+        path.into_inner()
+    }
+
+    #[test]
+    fn trim_ends_arms_on_the_radius() {
+        let junction = Junction::new(Vec3::ZERO, 2.5);
+        let trimmed = junction.trim(&arm(Vec3::X));
+
+        assert_eq!(trimmed.len(), 9);
+        assert!((trimmed[8].position.length() - 2.5).abs() < 1e-5);
+        assert!((trimmed[8].v_coordinate - 7.5).abs() < 1e-5);
+        assert!(junction.trim(&crate::path::linear(Vec3::X, 1., 2)).is_empty());
+    }
+
+    #[test]
+    fn patch_joins_the_arms_from_above() {
+        let junction = Junction::new(Vec3::ZERO, 2.5);
+        let shape = ExtrudeShape::from_outline(&[Vec2::new(1., 0.), Vec2::new(1., -0.2), Vec2::new(-1., -0.2), Vec2::new(-1., 0.)], false);
+        let arms: Vec<Vec<OrientedPoint>> = [Vec3::X, Vec3::NEG_X, Vec3::NEG_Z].iter().map(|&direction| junction.trim(&arm(direction)).into_inner()).collect();
+        let mesh = junction.patch_mesh(&shape, &arms);
+
+        // A fan of six corners, and a wall between each pair of neighbouring arms
+        assert_eq!(mesh.count_vertices(), 1 + 6 + 3 * 4);
+        assert_eq!(mesh.indices().unwrap().len(), 6 * 3 + 3 * 6);
+        let normals = mesh.attribute(Mesh::ATTRIBUTE_NORMAL).unwrap().as_float3().unwrap();
+        assert!(normals[..7].iter().all(|normal| *normal == [0., 1., 0.]));
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use bevy::prelude::*;
+use crate::bezier::{self, BezierCurve, FrameMode};
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+use crate::settings::ExtrudeMeshSettings;
+
+/// Named, reusable track pieces. Meshes are cached by what they are built from (the curve, shape,
+/// subdivision count, frame mode and options) rather than by name, so every instance of a piece, and
+/// every piece with the same curve, shares one mesh.
+#[derive(Resource, Default)]
+pub struct CurveLibrary {
+    curves: HashMap<String, BezierCurve>,
+    /// Keyed on [`mesh_key`], alongside [`curve_key`] of the curve they were built from.
+    meshes: HashMap<u64, (u64, Handle<Mesh>)>,
+}
+
+impl CurveLibrary {
+    /// Registers a piece under `name`, replacing (and dropping the meshes of) any previous piece with that name.
+    pub fn register(&mut self, name: impl Into<String>, curve: BezierCurve) {
+        let name = name.into();
+        if let Some(previous) = self.curves.insert(name, curve) {
+            self.forget_meshes(&previous);
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<BezierCurve> {
+        let curve = self.curves.remove(name)?;
+        self.forget_meshes(&curve);
+
+        Some(curve)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&BezierCurve> {
+        self.curves.get(name)
+    }
+
+    /// The shared mesh of a piece, extruding it on first use.
+    pub fn mesh(&mut self, name: &str, shape: &Handle<ExtrudeShape>, subdivisions: u32, settings: &ExtrudeMeshSettings, shapes: &Assets<ExtrudeShape>, meshes: &mut Assets<Mesh>) -> Option<Handle<Mesh>> {
+        let curve = self.curves.get(name)?;
+        let options = ExtrudeOptions::from(settings);
+        let curve_key = curve_key(curve);
+        let key = mesh_key(curve_key, shape.id(), subdivisions, settings.frame_mode, &options);
+        if let Some((_, handle)) = self.meshes.get(&key) {
+            return Some(handle.clone());
+        }
+
+        let shape = shapes.get(shape)?;
+        let mut path = curve.generate_path(subdivisions);
+        bezier::apply_frame_mode(&mut path, settings.frame_mode);
+        let handle = meshes.add(extrude::extrude_with_options(shape, &path, &options));
+        self.meshes.insert(key, (curve_key, handle.clone()));
+
+        Some(handle)
+    }
+
+    pub fn clear_meshes(&mut self) {
+        self.meshes.clear();
+    }
+
+    /// Drops the meshes of `curve`, unless another piece still uses the same curve.
+    fn forget_meshes(&mut self, curve: &BezierCurve) {
+        let key = curve_key(curve);
+        if !self.curves.values().any(|other| curve_key(other) == key) {
+            self.meshes.retain(|_, (curve_key, _)| *curve_key != key);
+        }
+    }
+}
+
+/// A hash of everything about `curve` that ends up in its generated paths.
+fn curve_key(curve: &BezierCurve) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for point in curve.points() {
+        point.to_array().map(f32::to_bits).hash(&mut hasher);
+    }
+    (curve.samples(), curve.v_mode(), curve.resolution()).hash(&mut hasher);
+
+    hasher.finish()
+}
+
+fn mesh_key(curve_key: u64, shape: AssetId<ExtrudeShape>, subdivisions: u32, frame_mode: FrameMode, options: &ExtrudeOptions) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (curve_key, shape, subdivisions).hash(&mut hasher);
+    match frame_mode {
+        FrameMode::FixedUp(up) => up.to_array().map(f32::to_bits).hash(&mut hasher),
+        FrameMode::RotationMinimizing => u32::MAX.hash(&mut hasher),
+    }
+    options.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+#[derive(Clone, Debug)]
+pub struct KitPiece {
+    pub name: String,
+    pub transform: Transform,
+}
+
+impl KitPiece {
+    pub fn new(name: impl Into<String>, transform: Transform) -> Self {
+        Self {
+            name: name.into(),
+            transform,
+        }
+    }
+}
+
+/// A track composed of library pieces. Each piece is spawned as a child entity sharing the piece's mesh.
+#[derive(Component, Clone, Debug)]
+pub struct KitTrack {
+    pub shape: Handle<ExtrudeShape>,
+    pub material: Handle<StandardMaterial>,
+    pub subdivisions: u32,
+    pub pieces: Vec<KitPiece>,
+}
+
+#[derive(Component)]
+pub struct KitTrackResolved;
+
+pub(crate) fn resolve_kit_tracks(
+    mut commands: Commands,
+    mut library: ResMut<CurveLibrary>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    shapes: Res<Assets<ExtrudeShape>>,
+    settings: Res<ExtrudeMeshSettings>,
+    tracks: Query<(Entity, Ref<KitTrack>, Has<KitTrackResolved>)>,
+) {
+    if settings.is_changed() {
+        library.clear_meshes();
+    }
+
+    for (entity, track, resolved) in &tracks {
+        if resolved && !track.is_changed() && !settings.is_changed() {
+            continue;
+        }
+        // Wait for the cross-section to load
+        if !shapes.contains(&track.shape) {
+            continue;
+        }
+
+        let mut pieces = Vec::with_capacity(track.pieces.len());
+        for piece in &track.pieces {
+            match library.mesh(&piece.name, &track.shape, track.subdivisions, &settings, &shapes, &mut meshes) {
+                Some(mesh) => pieces.push((mesh, piece)),
+                None => warn!("Kit piece \"{}\" is not registered in the curve library", piece.name),
+            }
+        }
+
+        commands.entity(entity)
+            .insert(KitTrackResolved)
+            .despawn_descendants()
+            .with_children(|parent| {
+                for (mesh, piece) in pieces {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh,
+                            material: track.material.clone(),
+                            transform: piece.transform,
+                            ..default()
+                        },
+                        Name::new(piece.name.clone()),
+                    ));
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::extrude::UMode;
+    use super::*;
+
+    fn curve(end: f32) -> BezierCurve {
+        BezierCurve::new(vec![Vec3::ZERO, Vec3::new(0., 0., -1.), Vec3::new(1., 0., -2.), Vec3::new(end, 0., -3.)], None)
+    }
+
+    #[test]
+    fn pieces_with_the_same_curve_share_a_mesh() {
+        let mut shapes = Assets::<ExtrudeShape>::default();
+        let mut meshes = Assets::<Mesh>::default();
+        let shape = shapes.add(ExtrudeShape::from_outline(&[Vec2::new(1., 0.), Vec2::new(-1., 0.)], false));
+        let settings = ExtrudeMeshSettings::default();
+
+        let mut library = CurveLibrary::default();
+        library.register("hairpin_A", curve(2.));
+        library.register("hairpin_B", curve(2.));
+        let a = library.mesh("hairpin_A", &shape, 8, &settings, &shapes, &mut meshes).unwrap();
+        let b = library.mesh("hairpin_B", &shape, 8, &settings, &shapes, &mut meshes).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(meshes.len(), 1);
+
+        let finer = library.mesh("hairpin_A", &shape, 16, &settings, &shapes, &mut meshes).unwrap();
+        let arc_length_u = ExtrudeMeshSettings { u_mode: UMode::ArcLength, ..default() };
+        let other_options = library.mesh("hairpin_A", &shape, 8, &arc_length_u, &shapes, &mut meshes).unwrap();
+        assert!(finer != a && other_options != a && finer != other_options);
+    }
+
+    #[test]
+    fn reregistering_a_piece_rebuilds_its_mesh() {
+        let mut shapes = Assets::<ExtrudeShape>::default();
+        let mut meshes = Assets::<Mesh>::default();
+        let shape = shapes.add(ExtrudeShape::from_outline(&[Vec2::new(1., 0.), Vec2::new(-1., 0.)], false));
+        let settings = ExtrudeMeshSettings::default();
+
+        let mut library = CurveLibrary::default();
+        library.register("chicane", curve(2.));
+        let before = library.mesh("chicane", &shape, 8, &settings, &shapes, &mut meshes).unwrap();
+        library.register("chicane", curve(2.));
+        assert_eq!(library.mesh("chicane", &shape, 8, &settings, &shapes, &mut meshes).unwrap(), before);
+
+        library.register("chicane", curve(3.));
+        assert_ne!(library.mesh("chicane", &shape, 8, &settings, &shapes, &mut meshes).unwrap(), before);
+        assert_eq!(library.meshes.len(), 1);
+    }
+
+    #[test]
+    fn options_hash_tells_callbacks_apart() {
+        let hash = |options: &ExtrudeOptions| {
+            let mut hasher = DefaultHasher::new();
+            options.hash(&mut hasher);
+            hasher.finish()
+        };
+        let width = extrude::WidthFn::new(|v| 1. + v);
+        let options = ExtrudeOptions { width_fn: Some(width.clone()), ..default() };
+
+        assert_eq!(hash(&options), hash(&options.clone()));
+        assert_ne!(hash(&options), hash(&ExtrudeOptions { width_fn: Some(extrude::WidthFn::new(|v| 1. + v)), ..default() }));
+        assert_ne!(hash(&options), hash(&ExtrudeOptions { weld: Some(1e-4), ..options.clone() }));
+    }
+}
@@ -1,2 +1,76 @@
+pub mod geometry;
+pub mod simplify;
+#[cfg(feature = "bevy")]
 pub mod extrude;
+#[cfg(feature = "bevy")]
 pub mod bezier;
+#[cfg(feature = "bevy")]
+pub mod profile;
+#[cfg(feature = "bevy")]
+pub mod deform;
+#[cfg(feature = "bevy")]
+pub mod path_local;
+#[cfg(feature = "bevy")]
+pub mod kit;
+#[cfg(feature = "bevy")]
+pub mod plugin;
+#[cfg(feature = "bevy")]
+pub mod spline;
+#[cfg(feature = "bevy")]
+pub mod settings;
+#[cfg(feature = "bevy")]
+pub mod composite;
+#[cfg(feature = "bevy")]
+pub mod slice;
+#[cfg(feature = "bevy")]
+pub mod scatter;
+#[cfg(feature = "bevy")]
+pub mod noise;
+#[cfg(feature = "bevy")]
+pub mod path;
+#[cfg(feature = "bevy")]
+pub mod road;
+#[cfg(feature = "bevy")]
+pub mod junction;
+#[cfg(feature = "bevy")]
+pub mod network;
+#[cfg(feature = "bevy")]
+pub mod physics;
+#[cfg(feature = "bevy")]
+pub mod lod;
+#[cfg(feature = "bevy")]
+pub mod streaming;
+#[cfg(feature = "bevy")]
+pub mod birail;
+#[cfg(feature = "bevy")]
+pub mod attributes;
+#[cfg(feature = "bevy")]
+pub mod builder;
+#[cfg(feature = "bevy")]
+pub mod grow;
+#[cfg(feature = "bevy")]
+pub mod morph;
+#[cfg(feature = "bevy")]
+pub mod rig;
+#[cfg(feature = "bevy")]
+pub mod gpu_deform;
+#[cfg(feature = "bevy")]
+pub mod merge;
+#[cfg(feature = "bevy")]
+pub mod bounds;
+#[cfg(feature = "bevy")]
+pub mod bvh;
+#[cfg(feature = "bevy")]
+pub mod volume;
+#[cfg(feature = "bevy")]
+pub mod terrain;
+#[cfg(feature = "bevy")]
+pub mod supports;
+#[cfg(feature = "bevy")]
+pub mod rail;
+#[cfg(feature = "bevy")]
+pub mod extrude2d;
+#[cfg(all(feature = "bevy", any(feature = "json", feature = "csv")))]
+pub mod interchange;
+#[cfg(feature = "bevy")]
+pub mod export;
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use crate::bezier::OrientedPoint;
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+use crate::path::ExtrudePath;
+use crate::{builder, profile};
+
+/// One level of detail for [`extrude_lods`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodSettings {
+    /// Keep every `path_step`-th edge loop of the path (the last one is always kept).
+    pub path_step: usize,
+    /// Resample the cross-section's outline to this many vertices. Resampled outlines use arc-length U coordinates.
+    pub profile_vertices: Option<usize>,
+    /// The camera distance up to which this level is shown by [`ExtrudedLods`].
+    pub max_distance: f32,
+    /// Decimate the extruded mesh down to this fraction of its triangles, see [`crate::simplify::simplify`].
+    /// Simplified levels only have positions, normals and UVs.
+    pub simplify: Option<f32>,
+}
+
+impl LodSettings {
+    pub fn new(path_step: usize, profile_vertices: Option<usize>, max_distance: f32) -> Self {
+        Self {
+            path_step,
+            profile_vertices,
+            max_distance,
+            simplify: None,
+        }
+    }
+
+    pub fn with_simplify(mut self, target_ratio: f32) -> Self {
+        self.simplify = Some(target_ratio);
+        self
+    }
+}
+
+/// A mesh per level of detail, in the order of `levels`.
+pub fn extrude_lods(shape: &ExtrudeShape, path: &[OrientedPoint], levels: &[LodSettings]) -> Vec<Mesh> {
+    extrude_lods_with_options(shape, path, levels, &ExtrudeOptions::default())
+}
+
+pub fn extrude_lods_with_options(shape: &ExtrudeShape, path: &[OrientedPoint], levels: &[LodSettings], options: &ExtrudeOptions) -> Vec<Mesh> {
+    levels.iter()
+        .map(|level| {
+            let path = decimate_path(path, level.path_step);
+            let extrude = |shape: &ExtrudeShape| match level.simplify {
+                Some(ratio) => builder::extrude_builder(shape, &path, options).simplify(ratio, f32::INFINITY).build(),
+                None => extrude::extrude_with_options(shape, &path, options),
+            };
+            match level.profile_vertices {
+                Some(count) => {
+                    let (outline, closed) = shape.outline();
+                    extrude(&ExtrudeShape::from_outline(&profile::resample_outline(&outline, count, closed), closed))
+                }
+                None => extrude(shape),
+            }
+        })
+        .collect()
+}
+
+/// Every `step`-th point of the path, always ending on its last point.
+pub fn decimate_path(path: &[OrientedPoint], step: usize) -> ExtrudePath {
+    let mut result: Vec<OrientedPoint> = path.iter().step_by(step.max(1)).cloned().collect();
+    if path.len() > 1 && !(path.len() - 1).is_multiple_of(step.max(1)) {
+        result.push(path[path.len() - 1].clone());
+    }
+
+    result.into()
+}
+
+/// Swaps the entity's mesh for the level matching its distance to the nearest camera. Levels are
+/// `(max_distance, mesh)` pairs sorted by distance; past the last one the last mesh stays.
+#[derive(Component, Debug, Clone, Default)]
+pub struct ExtrudedLods {
+    pub levels: Vec<(f32, Handle<Mesh>)>,
+}
+
+impl ExtrudedLods {
+    /// Adds the meshes from [`extrude_lods`] to the assets, pairing them with their level's distance.
+    pub fn new(meshes: Vec<Mesh>, levels: &[LodSettings], assets: &mut Assets<Mesh>) -> Self {
+        Self {
+            levels: meshes.into_iter().zip(levels).map(|(mesh, level)| (level.max_distance, assets.add(mesh))).collect(),
+        }
+    }
+
+    pub fn level_at(&self, distance: f32) -> Option<&Handle<Mesh>> {
+        self.levels.iter()
+            .find(|(max_distance, _)| distance <= *max_distance)
+            .or(self.levels.last())
+            .map(|(_, mesh)| mesh)
+    }
+}
+
+pub(crate) fn update_lods(
+    cameras: Query<&GlobalTransform, With<Camera>>,
+    mut lods: Query<(&ExtrudedLods, &GlobalTransform, &mut Handle<Mesh>)>,
+) {
+    for (lods, transform, mut mesh) in &mut lods {
+        let Some(distance) = cameras.iter()
+            .map(|camera| camera.translation().distance(transform.translation()))
+            .reduce(f32::min)
+        else {
+            continue;
+        };
+
+        if let Some(level) = lods.level_at(distance) {
+            if *mesh != *level {
+                *mesh = level.clone();
+            }
+        }
+    }
+}
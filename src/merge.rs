@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use crate::attributes;
+use crate::extrude::IndexFormat;
+
+/// Appends meshes into one with a single draw call, e.g. the many short extrusions of a fence or a pipe
+/// run. The result keeps only the attributes every mesh has, with `index_format` picked for the combined
+/// vertex count. Meshes must be triangle lists (the default [`crate::extrude::Topology`]), others are skipped.
+pub fn merge_meshes(meshes: impl IntoIterator<Item = Mesh>, index_format: IndexFormat) -> Mesh {
+    merge_transformed_meshes(meshes.into_iter().map(|mesh| (mesh, Transform::IDENTITY)), index_format)
+}
+
+/// Like [`merge_meshes`], first moving each mesh by its transform, so one extruded piece can be placed
+/// several times. Mirroring transforms keep the faces pointing outwards.
+pub fn merge_transformed_meshes(pieces: impl IntoIterator<Item = (Mesh, Transform)>, index_format: IndexFormat) -> Mesh {
+    let mut merged: Option<Mesh> = None;
+    let mut indices = Vec::new();
+    let mut vertex_count = 0;
+    for (mut mesh, transform) in pieces {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            warn!("Skipped merging a mesh that is not a triangle list");
+            continue;
+        }
+        if transform != Transform::IDENTITY {
+            mesh.transform_by(transform);
+        }
+        let mirrored = transform.scale.x * transform.scale.y * transform.scale.z < 0.;
+        let mesh_indices: Vec<u32> = match mesh.indices() {
+            Some(indices) => indices.iter().map(|i| i as u32).collect(),
+            None => (0..mesh.count_vertices() as u32).collect(),
+        };
+        indices.extend(mesh_indices.chunks_exact(3).flat_map(|triangle| {
+            let triangle = if mirrored { [triangle[0], triangle[2], triangle[1]] } else { [triangle[0], triangle[1], triangle[2]] };
+            triangle.map(|i| (vertex_count + i as usize) as u32)
+        }));
+        vertex_count += mesh.count_vertices();
+
+        let Some(merged) = merged.as_mut() else {
+            merged = Some(mesh);
+            continue;
+        };
+        let missing: Vec<_> = merged.attributes_mut()
+            .filter_map(|(id, values)| (!mesh.attribute(id).is_some_and(|other| attributes::extend_values(values, other))).then_some(id))
+            .collect();
+        for id in missing {
+            merged.remove_attribute(id);
+        }
+    }
+
+    let Some(mut merged) = merged else {
+        return Mesh::new(PrimitiveTopology::TriangleList, default());
+    };
+    merged.insert_indices(index_format.indices(indices, vertex_count));
+    merged
+}
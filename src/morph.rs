@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use bevy::render::mesh::morph::MorphAttributes;
+use bevy::render::mesh::{MeshVertexAttribute, VertexAttributeValues};
+use crate::bezier::OrientedPoint;
+use crate::deform::{self, DeformError};
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+
+#[derive(Debug)]
+pub enum MorphError {
+    /// Extruding along target `target` gave a different number of vertices than along the base path,
+    /// usually because the paths have different point counts.
+    VertexCountMismatch { target: usize, expected: usize, found: usize },
+    /// The named option drops or reorders vertices depending on the path, so the vertices of the
+    /// targets wouldn't line up with the base.
+    UnsupportedOption(&'static str),
+    Deform(DeformError),
+}
+
+impl From<DeformError> for MorphError {
+    fn from(error: DeformError) -> Self {
+        MorphError::Deform(error)
+    }
+}
+
+/// Extrudes `shape` along `base`, with a named morph target per entry of `targets` that deforms the
+/// mesh into the extrusion along that path instead. The GPU blends between them by the entity's
+/// [`bevy::render::mesh::morph::MeshMorphWeights`] (one weight per target, in order), so a track
+/// can sway or buckle without being extruded again every frame. Every path needs as many points as
+/// `base`; the morph target texture is added to `images`. Options that weld, skip, merge or add edge
+/// loops or reorder vertices are rejected with [`MorphError::UnsupportedOption`].
+pub fn extrude_with_morph_targets(
+    shape: &ExtrudeShape,
+    base: &[OrientedPoint],
+    targets: &[(&str, &[OrientedPoint])],
+    options: &ExtrudeOptions,
+    images: &mut Assets<Image>,
+) -> Result<Mesh, MorphError> {
+    if let Some(option) = extrude::vertex_remapping_option(options) {
+        return Err(MorphError::UnsupportedOption(option));
+    }
+
+    let mut mesh = extrude::extrude_with_options(shape, base, options);
+    let base_positions = float3_values(&mesh, Mesh::ATTRIBUTE_POSITION);
+    let base_normals = float3_values(&mesh, Mesh::ATTRIBUTE_NORMAL);
+
+    let mut deltas = Vec::with_capacity(targets.len());
+    for (target, (_, path)) in targets.iter().enumerate() {
+        let morphed = extrude::extrude_with_options(shape, path, options);
+        if morphed.count_vertices() != base_positions.len() {
+            return Err(MorphError::VertexCountMismatch { target, expected: base_positions.len(), found: morphed.count_vertices() });
+        }
+        let positions = float3_values(&morphed, Mesh::ATTRIBUTE_POSITION);
+        let normals = float3_values(&morphed, Mesh::ATTRIBUTE_NORMAL);
+        deltas.push(
+            positions.iter().zip(&base_positions).zip(normals.iter().zip(&base_normals))
+                .map(|((position, base_position), (normal, base_normal))| {
+                    MorphAttributes::new(*position - *base_position, *normal - *base_normal, Vec3::ZERO)
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let image = deform::attach_morph_targets(&mut mesh, &deltas, images)?;
+    if let Some(image) = images.get_mut(&image) {
+        image.asset_usage = options.asset_usage;
+    }
+    mesh.set_morph_target_names(targets.iter().map(|(name, _)| name.to_string()).collect());
+
+    Ok(mesh)
+}
+
+fn float3_values(mesh: &Mesh, attribute: MeshVertexAttribute) -> Vec<Vec3> {
+    match mesh.attribute(attribute) {
+        Some(VertexAttributeValues::Float32x3(values)) => values.iter().map(|v| Vec3::from_array(*v)).collect(),
+        _ => vec![Vec3::ZERO; mesh.count_vertices()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::extrude::FoldHandling;
+    use super::*;
+
+    fn shape() -> ExtrudeShape {
+        ExtrudeShape::from_outline(&[Vec2::new(1., 0.), Vec2::new(0., 1.), Vec2::new(-1., 0.)], false)
+    }
+
+    #[test]
+    fn targets_hold_the_offset_to_each_path() {
+        let base = crate::path::linear(Vec3::NEG_Z, 10., 5);
+        let mut raised = base.clone();
+        for point in raised.iter_mut() {
+            point.position.y += 2.;
+        }
+        let mut images = Assets::<Image>::default();
+        let mesh = extrude_with_morph_targets(&shape(), &base, &[("raised", &raised)], &ExtrudeOptions::default(), &mut images).unwrap();
+
+        assert_eq!(mesh.morph_target_names(), Some(&["raised".to_string()][..]));
+        assert!(mesh.has_morph_targets());
+        assert_eq!(images.len(), 1);
+    }
+
+    #[test]
+    fn rejects_options_that_move_edge_loops() {
+        let base = crate::path::linear(Vec3::NEG_Z, 10., 5);
+        let target = crate::path::linear(Vec3::NEG_Z, 12., 5);
+        let mut images = Assets::<Image>::default();
+        let options = [
+            (ExtrudeOptions { weld: Some(1e-4), ..default() }, "weld"),
+            (ExtrudeOptions { min_segment_length: Some(0.5), ..default() }, "min_segment_length"),
+            (ExtrudeOptions { fold_handling: FoldHandling::MergeLoops, ..default() }, "fold_handling"),
+            (ExtrudeOptions { lightmap_uvs: true, ..default() }, "lightmap_uvs"),
+        ];
+        for (options, name) in options {
+            let result = extrude_with_morph_targets(&shape(), &base, &[("longer", &target)], &options, &mut images);
+            assert!(matches!(result, Err(MorphError::UnsupportedOption(option)) if option == name), "{name}: {result:?}");
+        }
+        assert_eq!(images.len(), 0);
+    }
+}
@@ -0,0 +1,227 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use bevy::prelude::*;
+use crate::bezier::BezierCurve;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EdgeId(pub usize);
+
+#[derive(Debug, Clone)]
+pub struct PathNode {
+    pub position: Vec3,
+}
+
+/// A curve running from `start` to `end`. Edges can be traversed in both directions.
+#[derive(Debug, Clone)]
+pub struct PathEdge {
+    pub start: NodeId,
+    pub end: NodeId,
+    pub curve: BezierCurve,
+}
+
+impl PathEdge {
+    /// The node at the other end of the edge from `node`.
+    pub fn opposite(&self, node: NodeId) -> NodeId {
+        if node == self.start { self.end } else { self.start }
+    }
+
+    /// The direction of travel when leaving `node` along this edge.
+    pub fn direction_from(&self, node: NodeId) -> Vec3 {
+        if node == self.start {
+            self.curve.derivative(0.).normalize_or_zero()
+        } else {
+            -self.curve.derivative(1.).normalize_or_zero()
+        }
+    }
+}
+
+/// A graph of curves connected at shared nodes, e.g. a road or rail network, for navigating across junctions.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PathNetwork {
+    nodes: Vec<PathNode>,
+    edges: Vec<PathEdge>,
+    adjacency: HashMap<NodeId, Vec<EdgeId>>,
+}
+
+impl PathNetwork {
+    pub fn add_node(&mut self, position: Vec3) -> NodeId {
+        self.nodes.push(PathNode { position });
+        NodeId(self.nodes.len() - 1)
+    }
+
+    /// Connects two nodes with `curve`, which is expected to run from `start` to `end`.
+    pub fn connect(&mut self, start: NodeId, end: NodeId, curve: BezierCurve) -> EdgeId {
+        let id = EdgeId(self.edges.len());
+        self.edges.push(PathEdge { start, end, curve });
+        self.adjacency.entry(start).or_default().push(id);
+        if end != start {
+            self.adjacency.entry(end).or_default().push(id);
+        }
+
+        id
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&PathNode> {
+        self.nodes.get(id.0)
+    }
+
+    pub fn edge(&self, id: EdgeId) -> Option<&PathEdge> {
+        self.edges.get(id.0)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &PathNode)> {
+        self.nodes.iter().enumerate().map(|(i, node)| (NodeId(i), node))
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (EdgeId, &PathEdge)> {
+        self.edges.iter().enumerate().map(|(i, edge)| (EdgeId(i), edge))
+    }
+
+    /// The edges meeting at `node`.
+    pub fn edges_at(&self, node: NodeId) -> &[EdgeId] {
+        self.adjacency.get(&node).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// The edges to continue on after arriving at `node` along `edge`, straightest first. U-turns
+    /// back onto the arriving edge are left out.
+    pub fn continuations(&self, edge: EdgeId, node: NodeId) -> Vec<EdgeId> {
+        let Some(arriving) = self.edge(edge) else {
+            return Vec::new();
+        };
+        let heading = -arriving.direction_from(node);
+
+        let mut candidates: Vec<(EdgeId, f32)> = self.edges_at(node).iter()
+            .filter(|&&id| id != edge)
+            .map(|&id| (id, self.edges[id.0].direction_from(node).dot(heading)))
+            .collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        candidates.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// The straightest way on after arriving at `node` along `edge`, if there is one.
+    pub fn continue_across(&self, edge: EdgeId, node: NodeId) -> Option<EdgeId> {
+        self.continuations(edge, node).first().copied()
+    }
+
+    /// The shortest route from `from` to `to` as the sequence of edges to follow, weighted by curve length (A*).
+    pub fn shortest_path(&self, from: NodeId, to: NodeId) -> Option<Vec<EdgeId>> {
+        let goal = self.node(to)?.position;
+        self.node(from)?;
+
+        let mut open = BinaryHeap::new();
+        let mut costs: HashMap<NodeId, f32> = HashMap::from([(from, 0.)]);
+        let mut came_from: HashMap<NodeId, (NodeId, EdgeId)> = HashMap::new();
+        open.push(OpenNode { node: from, estimate: self.nodes[from.0].position.distance(goal) });
+
+        while let Some(OpenNode { node, estimate }) = open.pop() {
+            if node == to {
+                let mut route = Vec::new();
+                let mut current = to;
+                while let Some(&(previous, edge)) = came_from.get(&current) {
+                    route.push(edge);
+                    current = previous;
+                }
+                route.reverse();
+                return Some(route);
+            }
+
+            let cost = costs[&node];
+            // Skip stale heap entries
+            if estimate > cost + self.nodes[node.0].position.distance(goal) {
+                continue;
+            }
+
+            for &id in self.edges_at(node) {
+                let edge = &self.edges[id.0];
+                let next = edge.opposite(node);
+                let next_cost = cost + edge.curve.length();
+                if costs.get(&next).is_none_or(|&known| next_cost < known) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, (node, id));
+                    open.push(OpenNode { node: next, estimate: next_cost + self.nodes[next.0].position.distance(goal) });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+struct OpenNode {
+    node: NodeId,
+    estimate: f32,
+}
+
+// Reversed so the binary heap pops the lowest estimate first
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for OpenNode {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(a: Vec3, b: Vec3) -> BezierCurve {
+        BezierCurve::new(vec![a, a.lerp(b, 1. / 3.), a.lerp(b, 2. / 3.), b], None)
+    }
+
+    /// A crossroads at the origin with arms to the north, east and west, and a detour from the north
+    /// end to the east end.
+    fn crossroads() -> (PathNetwork, [NodeId; 4], [EdgeId; 4]) {
+        let mut network = PathNetwork::default();
+        let positions = [Vec3::ZERO, Vec3::new(0., 0., -10.), Vec3::new(10., 0., 0.), Vec3::new(-10., 0., 0.)];
+        let nodes = positions.map(|position| network.add_node(position));
+        let edges = [
+            network.connect(nodes[1], nodes[0], line(positions[1], positions[0])),
+            network.connect(nodes[0], nodes[2], line(positions[0], positions[2])),
+            network.connect(nodes[3], nodes[0], line(positions[3], positions[0])),
+            network.connect(nodes[1], nodes[2], BezierCurve::new(vec![positions[1], Vec3::new(30., 0., -40.), Vec3::new(40., 0., -30.), positions[2]], None)),
+        ];
+
+        (network, nodes, edges)
+    }
+
+    #[test]
+    fn continuations_go_straightest_first() {
+        let mut network = PathNetwork::default();
+        let [center, south, north, east] = [Vec3::ZERO, Vec3::new(0., 0., 10.), Vec3::new(0., 0., -10.), Vec3::new(10., 0., 0.)].map(|position| network.add_node(position));
+        let positions = |id: NodeId| network.node(id).unwrap().position;
+        let (to_center, to_north, to_east) = (line(positions(south), positions(center)), line(positions(center), positions(north)), line(positions(east), positions(center)));
+        let arriving = network.connect(south, center, to_center);
+        let straight = network.connect(center, north, to_north);
+        let turn = network.connect(east, center, to_east);
+
+        assert_eq!(network.continuations(arriving, center), vec![straight, turn]);
+        assert_eq!(network.continue_across(straight, center), Some(arriving));
+        assert_eq!(network.edges_at(center).len(), 3);
+    }
+
+    #[test]
+    fn shortest_path_avoids_the_detour() {
+        let (network, nodes, edges) = crossroads();
+
+        assert_eq!(network.shortest_path(nodes[1], nodes[2]), Some(vec![edges[0], edges[1]]));
+        assert_eq!(network.shortest_path(nodes[3], nodes[3]), Some(Vec::new()));
+        assert_eq!(network.shortest_path(nodes[3], NodeId(9)), None);
+    }
+}
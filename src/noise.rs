@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use crate::bezier::OrientedPoint;
+
+// Everything here sticks to integer hashing and basic float arithmetic (no libm calls such as
+// `sin` or `exp`, whose results differ between platforms), so a seed yields bit-identical
+// results on every client.
+
+/// SplitMix64 finalizer.
+pub fn hash(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// A hash mapped to `0..1`.
+pub fn hash_unit(x: u64) -> f32 {
+    (hash(x) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// A small seeded random number generator.
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        hash(self.state)
+    }
+
+    /// A value in `0..1`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+/// Seeded value noise in `-1..1`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Noise {
+    pub seed: u64,
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+        }
+    }
+
+    fn lattice(&self, x: i64, y: i64) -> f32 {
+        let key = self.seed ^ hash((x as u64).wrapping_mul(0x8DA6_B343) ^ (y as u64).wrapping_mul(0xD816_3841));
+        hash_unit(key) * 2. - 1.
+    }
+
+    pub fn sample_1d(&self, x: f32) -> f32 {
+        self.sample_2d(x, 0.)
+    }
+
+    pub fn sample_2d(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (fade(x - x0), fade(y - y0));
+        let (ix, iy) = (x0 as i64, y0 as i64);
+
+        let bottom = self.lattice(ix, iy) + (self.lattice(ix + 1, iy) - self.lattice(ix, iy)) * fx;
+        let top = self.lattice(ix, iy + 1) + (self.lattice(ix + 1, iy + 1) - self.lattice(ix, iy + 1)) * fx;
+
+        bottom + (top - bottom) * fy
+    }
+
+    /// Fractal noise: `octaves` layers, each at double the frequency and half the amplitude of the previous one.
+    pub fn fbm_2d(&self, x: f32, y: f32, octaves: u32) -> f32 {
+        let mut total = 0.;
+        let mut amplitude = 1.;
+        let mut frequency = 1.;
+        let mut normalization = 0.;
+        for octave in 0..octaves {
+            let layer = Noise::new(self.seed.wrapping_add(octave as u64));
+            total += layer.sample_2d(x * frequency, y * frequency) * amplitude;
+            normalization += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.;
+        }
+
+        if normalization > 0. { total / normalization } else { 0. }
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+/// Offsets every point of a path sideways and up in its own frame by noise over the distance travelled.
+pub fn displace_path(path: &mut [OrientedPoint], noise: &Noise, frequency: f32, amplitude: Vec2) {
+    let mut distance = 0.;
+    let mut previous = path.first().map(|point| point.position);
+    for point in path.iter_mut() {
+        if let Some(previous) = previous {
+            distance += point.position.distance(previous);
+        }
+        previous = Some(point.position);
+
+        let offset = Vec3::new(
+            noise.sample_2d(distance * frequency, 0.) * amplitude.x,
+            noise.sample_2d(distance * frequency, 17.) * amplitude.y,
+            0.,
+        );
+        point.position += point.local_to_world_direction(offset);
+    }
+}
+
+/// A random walk of `count` waypoints on the XZ plane, `step` apart, turning at most `max_turn` radians
+/// and climbing at most `max_slope` (rise over run) per step. Feed it to
+/// [`crate::composite::CompositeCurve::from_waypoints`] for a smooth random track.
+pub fn random_waypoints(seed: u64, count: usize, step: f32, max_turn: f32, max_slope: f32) -> Vec<Vec3> {
+    let mut rng = SeededRng::new(seed);
+    let mut heading = 0.;
+    let mut position = Vec3::ZERO;
+
+    let mut result = Vec::with_capacity(count);
+    for _ in 0..count {
+        result.push(position);
+
+        heading += rng.range(-max_turn, max_turn);
+        let (sin, cos) = sin_cos(heading);
+        position += Vec3::new(sin * step, rng.range(-max_slope, max_slope) * step, -cos * step);
+    }
+
+    result
+}
+
+/// Sine and cosine from a polynomial, identical on every platform unlike the libm versions.
+fn sin_cos(angle: f32) -> (f32, f32) {
+    use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+    // Reduce to -pi..pi, then to -pi/2..pi/2 where the polynomial is accurate
+    let mut x = angle - (angle / TAU).round() * TAU;
+    let mut cos_sign = 1.;
+    if x > FRAC_PI_2 {
+        x = PI - x;
+        cos_sign = -1.;
+    } else if x < -FRAC_PI_2 {
+        x = -PI - x;
+        cos_sign = -1.;
+    }
+
+    let x2 = x * x;
+    let sin = x * (1. - x2 / 6. * (1. - x2 / 20. * (1. - x2 / 42. * (1. - x2 / 72.))));
+    let cos = 1. - x2 / 2. * (1. - x2 / 12. * (1. - x2 / 30. * (1. - x2 / 56. * (1. - x2 / 90.))));
+
+    (sin, cos * cos_sign)
+}
@@ -0,0 +1,446 @@
+use std::f32::consts::{PI, TAU};
+use std::ops::{Deref, DerefMut, Range};
+use bevy::math::cubic_splines::CubicCurve;
+use bevy::prelude::*;
+use crate::bezier::{self, frame_rotation, OrientedPoint};
+use crate::extrude2d::LineJoin;
+
+/// The edge loop frames an extrusion follows, ordered from start to end with V growing along the way.
+/// Derefs to a slice of points, so it can be passed wherever a `&[OrientedPoint]` is expected.
+#[derive(Debug, Clone, Default)]
+pub struct ExtrudePath(Vec<OrientedPoint>);
+
+impl ExtrudePath {
+    pub fn new(points: Vec<OrientedPoint>) -> Self {
+        Self(points)
+    }
+
+    pub fn into_inner(self) -> Vec<OrientedPoint> {
+        self.0
+    }
+
+    /// A path through recorded or authored transforms (e.g. a vehicle's trajectory, or empties placed
+    /// in a level editor), each facing along its forward (-Z) axis, with V the distance travelled.
+    pub fn from_transforms(transforms: &[Transform]) -> Self {
+        let mut distance = 0.;
+        transforms.iter().enumerate()
+            .map(|(i, transform)| {
+                if i > 0 {
+                    distance += transform.translation.distance(transforms[i - 1].translation);
+                }
+                let mut point = OrientedPoint::from_transform(transform);
+                point.v_coordinate = distance;
+                point
+            })
+            .collect()
+    }
+
+    /// The distance travelled through the points.
+    pub fn length(&self) -> f32 {
+        self.0.windows(2).map(|pair| pair[0].position.distance(pair[1].position)).sum()
+    }
+
+    /// The frame `distance` along the path, interpolated between the neighbouring points and clamped to the ends.
+    /// Panics if the path is empty.
+    pub fn sample_at_distance(&self, distance: f32) -> OrientedPoint {
+        interpolate_path(&self.0, &cumulative_distances(&self.0), distance)
+    }
+
+    /// Applies `transform` to every point, see [`bezier::transform_path`].
+    pub fn transform(&mut self, transform: &Transform) {
+        bezier::transform_path(&mut self.0, transform);
+    }
+
+    /// Runs the path the other way: the points in reverse order, each frame turned around its up
+    /// axis, and V still growing from the (new) start.
+    pub fn reverse(&mut self) {
+        let (Some(first), Some(last)) = (self.0.first(), self.0.last()) else {
+            return;
+        };
+        let v_sum = first.v_coordinate + last.v_coordinate;
+
+        self.0.reverse();
+        for point in &mut self.0 {
+            point.rotation *= Quat::from_rotation_y(std::f32::consts::PI);
+            point.v_coordinate = v_sum - point.v_coordinate;
+        }
+    }
+
+    /// Appends `other`, shifting its V coordinates to continue from this path's end. If `other`
+    /// starts where this path ends, its first point is dropped so no edge loop is doubled.
+    pub fn concat(&mut self, other: &[OrientedPoint]) {
+        let Some(first) = other.first() else {
+            return;
+        };
+        let Some(last) = self.0.last() else {
+            self.0.extend_from_slice(other);
+            return;
+        };
+
+        let gap = last.position.distance(first.position);
+        let shift = last.v_coordinate + gap - first.v_coordinate;
+        let skip = if gap <= f32::EPSILON { 1 } else { 0 };
+        self.0.extend(other.iter().skip(skip).cloned().map(|mut point| {
+            point.v_coordinate += shift;
+            point
+        }));
+    }
+}
+
+impl Deref for ExtrudePath {
+    type Target = [OrientedPoint];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for ExtrudePath {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl AsRef<[OrientedPoint]> for ExtrudePath {
+    fn as_ref(&self) -> &[OrientedPoint] {
+        &self.0
+    }
+}
+
+impl From<Vec<OrientedPoint>> for ExtrudePath {
+    fn from(points: Vec<OrientedPoint>) -> Self {
+        Self(points)
+    }
+}
+
+impl From<ExtrudePath> for Vec<OrientedPoint> {
+    fn from(path: ExtrudePath) -> Self {
+        path.0
+    }
+}
+
+impl FromIterator<OrientedPoint> for ExtrudePath {
+    fn from_iter<I: IntoIterator<Item = OrientedPoint>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for ExtrudePath {
+    type Item = OrientedPoint;
+    type IntoIter = std::vec::IntoIter<OrientedPoint>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ExtrudePath {
+    type Item = &'a OrientedPoint;
+    type IntoIter = std::slice::Iter<'a, OrientedPoint>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// A path through any parametric curve given by its `position` and `velocity` at a parameter,
+/// sampled at `subdivisions + 1` evenly spaced parameters in `domain`. Works with `bevy_math`'s
+/// `CubicCurve` and `RationalCurve` (pass their `position` and `velocity` methods) as well as
+/// hand-written curves. V is the distance travelled through the samples.
+pub fn from_parametric(domain: Range<f32>, subdivisions: u32, position: impl Fn(f32) -> Vec3, velocity: impl Fn(f32) -> Vec3) -> ExtrudePath {
+    let subdivisions = subdivisions.max(1);
+    let mut previous: Option<(Vec3, Vec3)> = None;
+    let mut distance = 0.;
+
+    (0..=subdivisions)
+        .map(|i| {
+            let t = domain.start + (domain.end - domain.start) * i as f32 / subdivisions as f32;
+            let point = position(t);
+            // Keep the previous direction where the curve stops, e.g. at coincident control points
+            let direction = velocity(t).try_normalize()
+                .or(previous.map(|(_, direction)| direction))
+                .unwrap_or(Vec3::NEG_Z);
+            if let Some((previous_point, _)) = previous {
+                distance += point.distance(previous_point);
+            }
+            previous = Some((point, direction));
+
+            OrientedPoint::new(point, frame_rotation(direction, Vec3::Y), distance)
+        })
+        .collect()
+}
+
+/// A path along a `bevy_math` cubic curve, sampled `subdivisions` times per segment.
+pub fn from_cubic_curve(curve: &CubicCurve<Vec3>, subdivisions: u32) -> ExtrudePath {
+    let segments = curve.segments().len();
+    from_parametric(0.0..segments as f32, subdivisions * segments as u32, |t| curve.position(t), |t| curve.velocity(t))
+}
+
+/// A full circle of `radius` around the origin on the XZ plane, counter-clockwise seen from above.
+/// The first and last points coincide so the extrusion closes.
+pub fn circle(radius: f32, subdivisions: u32) -> ExtrudePath {
+    arc(radius, TAU, subdivisions)
+}
+
+/// An arc of `radius` around the origin on the XZ plane, starting at `(radius, 0, 0)` and turning
+/// counter-clockwise (seen from above) through `angle` radians.
+pub fn arc(radius: f32, angle: f32, subdivisions: u32) -> ExtrudePath {
+    helix(radius, 0., angle / TAU, subdivisions)
+}
+
+/// A helix around the Y axis, rising `pitch` per full turn, for springs and spiral staircases.
+pub fn helix(radius: f32, pitch: f32, turns: f32, subdivisions: u32) -> ExtrudePath {
+    let subdivisions = subdivisions.max(1);
+    let total_angle = turns * TAU;
+    let rise_per_radian = pitch / TAU;
+    // Length of the helix per radian turned
+    let speed = (radius * radius + rise_per_radian * rise_per_radian).sqrt();
+
+    (0..=subdivisions)
+        .map(|i| {
+            let angle = total_angle * i as f32 / subdivisions as f32;
+            let (sin, cos) = angle.sin_cos();
+            let position = Vec3::new(radius * cos, rise_per_radian * angle, -radius * sin);
+            let tangent = Vec3::new(-radius * sin, rise_per_radian, -radius * cos) * total_angle.signum();
+
+            OrientedPoint::new(position, frame_rotation(tangent, Vec3::Y), speed * angle.abs())
+        })
+        .collect()
+}
+
+/// A straight path from the origin along `direction`, for beams, walls and other prisms. The cross-section's
+/// up (Y) follows world Y, or world -Z when the path itself is vertical.
+pub fn linear(direction: Vec3, length: f32, segments: u32) -> ExtrudePath {
+    let segments = segments.max(1);
+    let rotation = upright_rotation(direction.normalize_or(Vec3::NEG_Z));
+
+    (0..=segments)
+        .map(|i| {
+            let distance = length * i as f32 / segments as f32;
+            OrientedPoint::new(direction * distance, rotation, distance)
+        })
+        .collect()
+}
+
+/// A path along straight lines between `points`, with its corners joined so the edge loops of a
+/// cross-section reaching `half_width` to either side never pinch or fold over. [`LineJoin::Miter`]
+/// puts a single edge loop on the corner, stretched across the turn to keep the walls parallel, and
+/// bevels corners sharper than its limit; [`LineJoin::Bevel`] and [`LineJoin::Round`] start the turn
+/// where the inner walls meet. Joins are reduced where the neighbouring segments are too short, and
+/// joins with no room left (e.g. a zero `half_width`) are mitered.
+///
+/// The miter stretch is along the frame's X for corners turning about world Y and along its Y for
+/// corners turning over, and split between them for anything in between. Repeated points are skipped.
+pub fn polyline(points: &[Vec3], join: LineJoin, half_width: f32) -> ExtrudePath {
+    let mut points = points.to_vec();
+    points.dedup_by(|a, b| a.distance_squared(*b) <= f32::EPSILON);
+
+    // Position, direction and the stretch of the edge loop's X and Y
+    let mut samples: Vec<(Vec3, Vec3, Vec2)> = Vec::new();
+    let n = points.len();
+    if n < 2 {
+        return ExtrudePath::default();
+    }
+
+    samples.push((points[0], points[1] - points[0], Vec2::ONE));
+    for i in 1..n - 1 {
+        let incoming = points[i] - points[i - 1];
+        let outgoing = points[i + 1] - points[i];
+        let (direction_in, direction_out) = (incoming.normalize(), outgoing.normalize());
+        let angle = direction_in.angle_between(direction_out);
+        if angle < 1e-4 {
+            samples.push((points[i], direction_in, Vec2::ONE));
+            continue;
+        }
+
+        let cos_half = (angle * 0.5).cos();
+        let bisector = (direction_in + direction_out).normalize_or_zero();
+        let miter_fits = |limit: f32| cos_half > 1e-3 && 1. / cos_half <= limit;
+        // How far the join may reach into each neighbouring segment
+        let max_trim = (incoming.length() * 0.5).min(outgoing.length() * 0.5);
+        let trim = (half_width * (angle * 0.5).tan()).min(max_trim);
+        let join = match join {
+            LineJoin::Miter { limit } if miter_fits(limit) => LineJoin::Miter { limit },
+            LineJoin::Miter { .. } => LineJoin::Bevel,
+            join => join,
+        };
+        if matches!(join, LineJoin::Miter { .. }) || (trim <= 1e-5 && miter_fits(f32::INFINITY)) {
+            samples.push((points[i], bisector, miter_stretch(bisector, direction_in.cross(direction_out), cos_half)));
+            continue;
+        }
+
+        match join {
+            LineJoin::Round { segments } => {
+                let radius = trim / (angle * 0.5).tan();
+                let start = points[i] - direction_in * trim;
+                // A U-turn has no plane of its own, so it turns about world Y where it can
+                let axis = direction_in.cross(direction_out).try_normalize()
+                    .or_else(|| Vec3::Y.reject_from_normalized(direction_in).try_normalize())
+                    .unwrap_or_else(|| direction_in.any_orthonormal_vector());
+                let inward = axis.cross(direction_in);
+                let center = start + inward * radius;
+
+                let steps = (angle / PI * segments as f32).ceil().max(1.) as u32;
+                for k in 0..=steps {
+                    let rotation = Quat::from_axis_angle(axis, angle * k as f32 / steps as f32);
+                    samples.push((center + rotation * (start - center), rotation * direction_in, Vec2::ONE));
+                }
+            }
+            _ => {
+                samples.push((points[i] - direction_in * trim, direction_in, Vec2::ONE));
+                samples.push((points[i] + direction_out * trim, direction_out, Vec2::ONE));
+            }
+        }
+    }
+    samples.push((points[n - 1], points[n - 1] - points[n - 2], Vec2::ONE));
+
+    let mut distance = 0.;
+    samples.iter().enumerate()
+        .map(|(i, (position, direction, stretch))| {
+            if i > 0 {
+                distance += position.distance(samples[i - 1].0);
+            }
+            OrientedPoint::new(*position, upright_rotation(*direction), distance).with_scale(stretch.extend(1.))
+        })
+        .collect()
+}
+
+/// How much a mitered corner loop facing along `bisector` stretches its X and Y, for a turn about `axis`.
+fn miter_stretch(bisector: Vec3, axis: Vec3, cos_half: f32) -> Vec2 {
+    let rotation = upright_rotation(bisector);
+    // The stretch runs across the turn, within the edge loop's plane
+    let across = (rotation.inverse() * bisector.cross(axis)).truncate().normalize_or_zero();
+    Vec2::ONE + (1. / cos_half - 1.) * across * across
+}
+
+/// The frame facing along `direction` with its Y as close to world Y as it gets, or facing world -Z
+/// for vertical directions.
+fn upright_rotation(direction: Vec3) -> Quat {
+    let up = if direction.cross(Vec3::Y).length_squared() > 1e-6 { Vec3::Y } else { Vec3::NEG_Z };
+
+    frame_rotation(direction, up)
+}
+
+/// The path running parallel to `path` at `offset` (sideways, up) in its frames. Like
+/// [`crate::extrude::ExtrudeOptions::offset`], the offset is in world units and not stretched by the
+/// frames' scale, so a rail stays the same distance from the centerline where the path widens. Unlike
+/// it, directions and V coordinates are recomputed from the offset points, so textures keep their
+/// scale on the inside and outside of bends.
+pub fn offset_path(path: &[OrientedPoint], offset: Vec2) -> ExtrudePath {
+    let positions: Vec<Vec3> = path.iter().map(|point| point.position + point.rotation * offset.extend(0.)).collect();
+
+    let mut distance = 0.;
+    path.iter().enumerate()
+        .map(|(i, point)| {
+            if i > 0 {
+                distance += positions[i].distance(positions[i - 1]);
+            }
+
+            let previous = positions[i.saturating_sub(1)];
+            let next = positions[(i + 1).min(positions.len() - 1)];
+            let direction = if next != previous { next - previous } else { point.forward() };
+
+            OrientedPoint::new(positions[i], frame_rotation(direction, point.rotation * Vec3::Y), path[0].v_coordinate + distance)
+                .with_scale(point.scale)
+        })
+        .collect()
+}
+
+/// The start of `path`, up to `progress` (0 to 1) of its length. The last frame is interpolated to
+/// sit exactly at that distance, so the end moves smoothly as `progress` grows.
+pub fn partial_path(path: &[OrientedPoint], progress: f32) -> ExtrudePath {
+    if path.len() < 2 {
+        return path.to_vec().into();
+    }
+    let distances = cumulative_distances(path);
+    let end = distances[distances.len() - 1] * progress.clamp(0., 1.);
+
+    let mut partial: Vec<OrientedPoint> = path.iter().zip(&distances)
+        .take_while(|(_, distance)| **distance < end)
+        .map(|(point, _)| point.clone())
+        .collect();
+    partial.push(interpolate_path(path, &distances, end));
+
+    partial.into()
+}
+
+/// The distance along `path` at each of its points.
+pub(crate) fn cumulative_distances(path: &[OrientedPoint]) -> Vec<f32> {
+    let mut total = 0.;
+    path.iter().enumerate().map(|(i, point)| {
+        if i > 0 {
+            total += point.position.distance(path[i - 1].position);
+        }
+        total
+    }).collect()
+}
+
+/// The frame `distance` along `path`, given the [`cumulative_distances`] of its points.
+pub(crate) fn interpolate_path(path: &[OrientedPoint], distances: &[f32], distance: f32) -> OrientedPoint {
+    if path.len() < 2 {
+        return path[0].clone();
+    }
+    let index = distances.partition_point(|d| *d < distance).clamp(1, path.len() - 1);
+
+    let (a, b) = (&path[index - 1], &path[index]);
+    let span = distances[index] - distances[index - 1];
+    let f = if span > 0. { ((distance - distances[index - 1]) / span).clamp(0., 1.) } else { 0. };
+
+    OrientedPoint::new(a.position.lerp(b.position, f), a.rotation.slerp(b.rotation, f), a.v_coordinate + (b.v_coordinate - a.v_coordinate) * f)
+        .with_scale(a.scale.lerp(b.scale, f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_finite(path: &[OrientedPoint]) {
+        for point in path {
+            assert!(point.position.is_finite() && point.rotation.is_finite() && point.scale.is_finite() && point.v_coordinate.is_finite(), "{point:?}");
+        }
+    }
+
+    #[test]
+    fn polyline_skips_repeated_points() {
+        let points = [Vec3::ZERO, Vec3::ZERO, Vec3::new(0., 0., -4.), Vec3::new(0., 0., -4.), Vec3::new(4., 0., -4.)];
+        for join in [LineJoin::Miter { limit: 4. }, LineJoin::Bevel, LineJoin::Round { segments: 8 }] {
+            let path = polyline(&points, join, 0.5);
+            assert_finite(&path);
+            assert!(path.windows(2).all(|pair| pair[0].position != pair[1].position), "{join:?}");
+        }
+    }
+
+    #[test]
+    fn polyline_turns_back_and_climbs_without_nan() {
+        let u_turn = [Vec3::ZERO, Vec3::new(0., 0., -4.), Vec3::new(0., 0., -1.)];
+        let climb = [Vec3::ZERO, Vec3::new(0., 4., 0.), Vec3::new(0., 4., -4.)];
+        for points in [u_turn, climb] {
+            for join in [LineJoin::Miter { limit: 4. }, LineJoin::Bevel, LineJoin::Round { segments: 8 }] {
+                assert_finite(&polyline(&points, join, 0.5));
+            }
+        }
+    }
+
+    #[test]
+    fn concat_continues_v_and_drops_the_shared_point() {
+        let mut path = linear(Vec3::NEG_Z, 2., 2);
+        let mut tail = linear(Vec3::X, 3., 3);
+        tail.transform(&Transform::from_xyz(0., 0., -2.));
+        path.concat(&tail);
+
+        assert_eq!(path.len(), 6);
+        assert!((path[5].v_coordinate - 5.).abs() < 1e-5);
+        assert!((path.length() - 5.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn reverse_keeps_v_growing() {
+        let mut path = arc(2., PI, 8);
+        let end = path[8].v_coordinate;
+        path.reverse();
+
+        assert_eq!(path[0].v_coordinate, 0.);
+        assert!((path[8].v_coordinate - end).abs() < 1e-4);
+        assert!(path.windows(2).all(|pair| pair[1].v_coordinate > pair[0].v_coordinate));
+    }
+}
@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+use crate::bezier::BezierCurve;
+
+/// A position expressed relative to a curve: `s` is the distance along it, `lateral` and `height`
+/// the offsets along the right and up axes of the curve's frame at that distance.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct PathLocal {
+    pub s: f32,
+    pub lateral: f32,
+    pub height: f32,
+}
+
+impl PathLocal {
+    pub fn new(s: f32, lateral: f32, height: f32) -> Self {
+        Self {
+            s,
+            lateral,
+            height,
+        }
+    }
+
+    pub fn from_world(curve: &BezierCurve, point: Vec3) -> Self {
+        let (t, _, oriented_point) = curve.project(point);
+        let local = oriented_point.world_to_local(point);
+
+        Self::new(curve.distance_at_t(t), local.x, local.y)
+    }
+
+    pub fn to_world(&self, curve: &BezierCurve) -> Vec3 {
+        let point = curve.get_oriented_point_at_distance(self.s);
+
+        point.local_to_world(Vec3::new(self.lateral, self.height, 0.))
+    }
+
+    /// The world transform at this position, facing along the curve.
+    pub fn to_transform(&self, curve: &BezierCurve) -> Transform {
+        let point = curve.get_oriented_point_at_distance(self.s);
+
+        Transform::from_translation(point.local_to_world(Vec3::new(self.lateral, self.height, 0.)))
+            .with_rotation(point.rotation)
+    }
+}
@@ -0,0 +1,62 @@
+#[cfg(any(feature = "rapier", feature = "avian"))]
+use crate::bezier::OrientedPoint;
+#[cfg(any(feature = "rapier", feature = "avian"))]
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+
+/// How the extruded geometry is turned into a collider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColliderKind {
+    /// The exact triangles. Fine for static geometry like roads and tracks.
+    #[default]
+    TriMesh,
+    /// A set of convex hulls approximating the geometry, needed for dynamic bodies.
+    ConvexDecomposition,
+    /// A single convex hull around all vertices. Cheapest, but only tight for short, straight chunks;
+    /// falls back to a trimesh for flat geometry that has no volume.
+    ConvexHull,
+}
+
+/// A trimesh or convex decomposition collider built straight from the extrusion, without going through a [`bevy::prelude::Mesh`].
+#[cfg(feature = "rapier")]
+pub fn to_rapier_collider(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions, kind: ColliderKind) -> bevy_rapier3d::prelude::Collider {
+    use bevy_rapier3d::prelude::Collider;
+
+    let (vertices, triangles) = extrude::extrude_triangles(shape, path, options);
+    match kind {
+        ColliderKind::TriMesh => Collider::trimesh(vertices, triangles),
+        ColliderKind::ConvexDecomposition => Collider::convex_decomposition(&vertices, &triangles),
+        ColliderKind::ConvexHull => Collider::convex_hull(&vertices).unwrap_or_else(|| Collider::trimesh(vertices, triangles)),
+    }
+}
+
+/// One collider per run of `segments_per_chunk` path segments, matching the chunks of an [`crate::spline::ExtrudedSpline`].
+#[cfg(feature = "rapier")]
+pub fn to_rapier_colliders(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions, kind: ColliderKind, segments_per_chunk: usize) -> Vec<bevy_rapier3d::prelude::Collider> {
+    extrude::path_chunks(path, segments_per_chunk)
+        .into_iter()
+        .map(|chunk| to_rapier_collider(shape, chunk, options, kind))
+        .collect()
+}
+
+/// A trimesh, convex decomposition or convex hull collider for avian3d, built straight from the extrusion.
+#[cfg(feature = "avian")]
+pub fn to_avian_collider(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions, kind: ColliderKind) -> avian3d::prelude::Collider {
+    use avian3d::prelude::Collider;
+
+    let (vertices, triangles) = extrude::extrude_triangles(shape, path, options);
+    match kind {
+        ColliderKind::TriMesh => Collider::trimesh(vertices, triangles),
+        ColliderKind::ConvexDecomposition => Collider::convex_decomposition(vertices, triangles),
+        ColliderKind::ConvexHull => Collider::convex_hull(vertices.clone()).unwrap_or_else(|| Collider::trimesh(vertices, triangles)),
+    }
+}
+
+/// One avian3d collider per run of `segments_per_chunk` path segments, keeping each collider's bounds
+/// small for the broad phase.
+#[cfg(feature = "avian")]
+pub fn to_avian_colliders(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions, kind: ColliderKind, segments_per_chunk: usize) -> Vec<avian3d::prelude::Collider> {
+    extrude::path_chunks(path, segments_per_chunk)
+        .into_iter()
+        .map(|chunk| to_avian_collider(shape, chunk, options, kind))
+        .collect()
+}
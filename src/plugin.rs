@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+use crate::extrude::ExtrudeShape;
+use crate::grow::grow_extrusions;
+use crate::kit::{resolve_kit_tracks, CurveLibrary};
+use crate::lod::update_lods;
+use crate::network::PathNetwork;
+use crate::scatter::spawn_path_scatters;
+use crate::settings::ExtrudeMeshSettings;
+use crate::spline::{advance_pending_extrusions, update_extruded_splines, ExtrusionFinished};
+use crate::streaming::stream_tracks;
+
+pub struct ExtrudeMeshPlugin;
+
+impl Plugin for ExtrudeMeshPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ExtrudeShape>()
+            .init_resource::<CurveLibrary>()
+            .init_resource::<ExtrudeMeshSettings>()
+            .init_resource::<PathNetwork>()
+            .add_event::<ExtrusionFinished>()
+            .add_systems(Update, (resolve_kit_tracks, (update_extruded_splines, advance_pending_extrusions).chain(), update_lods, stream_tracks, grow_extrusions, spawn_path_scatters));
+    }
+}
@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+use crate::extrude::ExtrudeShape;
+
+/// Resamples a polyline to `count` points spaced evenly along its arc length.
+pub fn resample_outline(points: &[Vec2], count: usize, closed: bool) -> Vec<Vec2> {
+    if points.len() < 2 || count == 0 {
+        return vec![points.first().copied().unwrap_or(Vec2::ZERO); count];
+    }
+
+    let mut polyline = points.to_vec();
+    if closed {
+        polyline.push(points[0]);
+    }
+
+    let mut lengths = vec![0.; polyline.len()];
+    for i in 1..polyline.len() {
+        lengths[i] = lengths[i - 1] + polyline[i].distance(polyline[i - 1]);
+    }
+    let total = lengths[lengths.len() - 1];
+
+    // Closed outlines don't repeat the start point, open ones hit both ends
+    let divisor = if closed { count } else { (count - 1).max(1) };
+    let mut result = Vec::with_capacity(count);
+    let mut segment = 0;
+    for i in 0..count {
+        let target = total * i as f32 / divisor as f32;
+        while segment < polyline.len() - 2 && lengths[segment + 1] < target {
+            segment += 1;
+        }
+
+        let segment_length = lengths[segment + 1] - lengths[segment];
+        let f = if segment_length > 0. { (target - lengths[segment]) / segment_length } else { 0. };
+        result.push(polyline[segment].lerp(polyline[segment + 1], f.clamp(0., 1.)));
+    }
+
+    result
+}
+
+/// Mean distance between corresponding points of two outlines with equal vertex counts.
+pub fn profile_distance(a: &[Vec2], b: &[Vec2]) -> f32 {
+    if a.is_empty() {
+        return 0.;
+    }
+
+    a.iter().zip(b).map(|(p, q)| p.distance(*q)).sum::<f32>() / a.len() as f32
+}
+
+/// A vertex-to-vertex correspondence between two cross-sections, resampled to a common vertex count.
+#[derive(Debug, Clone)]
+pub struct ProfileCorrespondence {
+    pub source: Vec<Vec2>,
+    pub target: Vec<Vec2>,
+    pub closed: bool,
+    pub distance: f32,
+}
+
+impl ProfileCorrespondence {
+    pub fn new(source: &ExtrudeShape, target: &ExtrudeShape, count: usize) -> Self {
+        let (source_outline, source_closed) = source.outline();
+        let (target_outline, target_closed) = target.outline();
+
+        Self::from_outlines(&source_outline, &target_outline, count, source_closed && target_closed)
+    }
+
+    pub fn from_outlines(source: &[Vec2], target: &[Vec2], count: usize, closed: bool) -> Self {
+        let source = resample_outline(source, count, closed);
+        let mut target = resample_outline(target, count, closed);
+
+        if closed {
+            // Match the winding, then line the outlines up by their angle around the centroid
+            if signed_area(&source).signum() != signed_area(&target).signum() {
+                target.reverse();
+            }
+
+            let source_centroid = centroid(&source);
+            let target_centroid = centroid(&target);
+            let centered_source: Vec<Vec2> = source.iter().map(|p| *p - source_centroid).collect();
+            let centered_target: Vec<Vec2> = target.iter().map(|p| *p - target_centroid).collect();
+
+            let mut best_shift = 0;
+            let mut best_distance = f32::MAX;
+            for shift in 0..count {
+                let distance = (0..count)
+                    .map(|i| centered_source[i].distance_squared(centered_target[(i + shift) % count]))
+                    .sum::<f32>();
+                if distance < best_distance {
+                    best_distance = distance;
+                    best_shift = shift;
+                }
+            }
+            target.rotate_left(best_shift);
+        } else {
+            let forward = profile_distance(&source, &target);
+            target.reverse();
+            if forward <= profile_distance(&source, &target) {
+                target.reverse();
+            }
+        }
+
+        Self {
+            distance: profile_distance(&source, &target),
+            source,
+            target,
+            closed,
+        }
+    }
+
+    /// Blends the corresponding vertices, `t = 0` being the source and `t = 1` the target outline.
+    pub fn lerp(&self, t: f32) -> Vec<Vec2> {
+        self.source.iter().zip(&self.target).map(|(a, b)| a.lerp(*b, t)).collect()
+    }
+
+    pub fn blend(&self, t: f32) -> ExtrudeShape {
+        ExtrudeShape::from_outline(&self.lerp(t), self.closed)
+    }
+}
+
+fn signed_area(points: &[Vec2]) -> f32 {
+    let n = points.len();
+    (0..n).map(|i| points[i].perp_dot(points[(i + 1) % n])).sum::<f32>() * 0.5
+}
+
+fn centroid(points: &[Vec2]) -> Vec2 {
+    points.iter().copied().sum::<Vec2>() / points.len().max(1) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resample_spaces_points_evenly() {
+        let points = [Vec2::ZERO, Vec2::new(1., 0.), Vec2::new(3., 0.)];
+        assert_eq!(resample_outline(&points, 4, false), vec![Vec2::ZERO, Vec2::new(1., 0.), Vec2::new(2., 0.), Vec2::new(3., 0.)]);
+
+        let square = [Vec2::new(1., 1.), Vec2::new(-1., 1.), Vec2::new(-1., -1.), Vec2::new(1., -1.)];
+        let resampled = resample_outline(&square, 8, true);
+        assert_eq!(resampled.len(), 8);
+        assert!(resampled.windows(2).all(|pair| (pair[0].distance(pair[1]) - 1.).abs() < 1e-5), "{resampled:?}");
+    }
+
+    #[test]
+    fn closed_correspondence_lines_up_rotated_and_reversed_outlines() {
+        let square = [Vec2::new(1., 1.), Vec2::new(-1., 1.), Vec2::new(-1., -1.), Vec2::new(1., -1.)];
+        // The same square, starting at another corner and running the other way
+        let target = [Vec2::new(-1., -1.), Vec2::new(-1., 1.), Vec2::new(1., 1.), Vec2::new(1., -1.)];
+        let correspondence = ProfileCorrespondence::from_outlines(&square, &target, 8, true);
+
+        assert!(correspondence.distance < 1e-5, "{}", correspondence.distance);
+        assert_eq!(correspondence.lerp(0.5).len(), 8);
+    }
+
+    #[test]
+    fn open_correspondence_matches_direction() {
+        let source = [Vec2::new(-1., 0.), Vec2::new(1., 0.)];
+        let target = [Vec2::new(2., 0.), Vec2::new(-2., 0.)];
+        let correspondence = ProfileCorrespondence::from_outlines(&source, &target, 3, false);
+
+        assert_eq!(correspondence.target[0], Vec2::new(-2., 0.));
+        assert_eq!(correspondence.lerp(0.5), vec![Vec2::new(-1.5, 0.), Vec2::ZERO, Vec2::new(1.5, 0.)]);
+    }
+}
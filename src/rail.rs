@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+use crate::bezier::{BezierCurve, OrientedPoint};
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape, IndexFormat};
+use crate::merge;
+use crate::path::{self, ExtrudePath};
+use crate::scatter;
+
+const GRAVITY: f32 = 9.81;
+
+/// Standard gauge, the distance between the rails' centers in meters.
+pub const STANDARD_GAUGE: f32 = 1.435;
+
+/// Banks `path` into its curves for trains passing at `design_speed`, rolling each frame about the
+/// path so the inner side is lower, by up to `max_cant` radians. The cant balances the sideways force
+/// for the path's curvature at that speed, so straight sections stay level.
+pub fn apply_cant(path: &mut [OrientedPoint], design_speed: f32, max_cant: f32) {
+    let positions: Vec<Vec3> = path.iter().map(|point| point.position.with_y(0.)).collect();
+    for i in 1..path.len().saturating_sub(1) {
+        // Skipping points that coincide, e.g. the doubled corners of a polyline
+        let before = positions[..i].iter().rev().map(|p| positions[i] - *p).find(|d| d.length_squared() > f32::EPSILON);
+        let after = positions[i + 1..].iter().map(|p| *p - positions[i]).find(|d| d.length_squared() > f32::EPSILON);
+        let (Some(before), Some(after)) = (before, after) else {
+            continue;
+        };
+        let length = (before.length() + after.length()) / 2.;
+        // Positive turning left
+        let turn = before.angle_between(after).copysign(before.cross(after).y);
+        let cant = (design_speed * design_speed * turn / length / GRAVITY).atan().clamp(-max_cant, max_cant);
+
+        let point = &mut path[i];
+        point.rotation = (Quat::from_axis_angle(point.forward(), -cant) * point.rotation).normalize();
+    }
+}
+
+/// The meshes of a railway track, see [`RailBuilder::build`].
+#[derive(Clone, Debug)]
+pub struct RailMeshes {
+    /// The left and right rail.
+    pub rails: [Mesh; 2],
+    /// Every sleeper merged into one mesh.
+    pub sleepers: Mesh,
+    /// Where each sleeper stands, to instance another mesh instead.
+    pub sleeper_transforms: Vec<Transform>,
+}
+
+/// Builds a railway track from a single centerline: two rails at either side, sleepers evenly spaced
+/// under them and cant in the curves.
+#[derive(Clone, Debug)]
+pub struct RailBuilder {
+    path: ExtrudePath,
+    rail: ExtrudeShape,
+    gauge: f32,
+    sleeper: Option<Mesh>,
+    sleeper_spacing: f32,
+    design_speed: f32,
+    max_cant: f32,
+    options: ExtrudeOptions,
+}
+
+impl RailBuilder {
+    /// `rail` is the cross-section of one rail, centered on X with its foot at Y = 0.
+    pub fn new(path: impl Into<ExtrudePath>, rail: ExtrudeShape) -> Self {
+        Self {
+            path: path.into(),
+            rail,
+            gauge: STANDARD_GAUGE,
+            sleeper: None,
+            sleeper_spacing: 0.6,
+            design_speed: 0.,
+            max_cant: 0.1,
+            options: ExtrudeOptions::default(),
+        }
+    }
+
+    pub fn from_curve(curve: &BezierCurve, subdivisions: u32, rail: ExtrudeShape) -> Self {
+        Self::new(curve.generate_path(subdivisions), rail)
+    }
+
+    pub fn with_gauge(mut self, gauge: f32) -> Self {
+        self.gauge = gauge;
+        self
+    }
+
+    /// Places `mesh` every `spacing` along the track, in the frame of the centerline with its top at
+    /// Y = 0. Without one the sleepers are plain boxes reaching past both rails.
+    pub fn with_sleeper(mut self, mesh: Mesh, spacing: f32) -> Self {
+        self.sleeper = Some(mesh);
+        self.sleeper_spacing = spacing;
+        self
+    }
+
+    pub fn with_sleeper_spacing(mut self, spacing: f32) -> Self {
+        self.sleeper_spacing = spacing;
+        self
+    }
+
+    /// Banks the track in curves, see [`apply_cant`]. A `design_speed` of 0, the default, keeps it level.
+    pub fn with_cant(mut self, design_speed: f32, max_cant: f32) -> Self {
+        self.design_speed = design_speed;
+        self.max_cant = max_cant;
+        self
+    }
+
+    /// Options applied to both rails.
+    pub fn with_options(mut self, options: ExtrudeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The centerline with the cant applied.
+    pub fn path(&self) -> ExtrudePath {
+        let mut path = self.path.clone();
+        if self.design_speed != 0. {
+            apply_cant(&mut path, self.design_speed, self.max_cant);
+        }
+
+        path
+    }
+
+    pub fn build(&self) -> RailMeshes {
+        let path = self.path();
+        let rails = [-0.5, 0.5].map(|side| {
+            let rail_path = path::offset_path(&path, Vec2::new(self.gauge * side, 0.));
+            extrude::extrude_with_options(&self.rail, &rail_path, &self.options)
+        });
+
+        let sleeper = self.sleeper.clone().unwrap_or_else(|| {
+            let height = 0.15;
+            Mesh::from(Cuboid::new(self.gauge + 1.2, height, 0.25)).translated_by(Vec3::new(0., -height / 2., 0.))
+        });
+        let sleeper_transforms = scatter::scatter_along_path(&path, self.sleeper_spacing, 0., 0);
+
+        RailMeshes {
+            rails,
+            sleepers: merge::merge_transformed_meshes(sleeper_transforms.iter().map(|transform| (sleeper.clone(), *transform)), IndexFormat::Auto),
+            sleeper_transforms,
+        }
+    }
+
+    /// Spawns the track as a parent entity with a child for each rail and one for the sleepers, and
+    /// returns the parent.
+    pub fn spawn(&self, commands: &mut Commands, meshes: &mut Assets<Mesh>, rail_material: Handle<StandardMaterial>, sleeper_material: Handle<StandardMaterial>) -> Entity {
+        let RailMeshes { rails: [left, right], sleepers, .. } = self.build();
+
+        commands.spawn((SpatialBundle::default(), Name::new("Railway")))
+            .with_children(|parent| {
+                for (mesh, name) in [(left, "Left rail"), (right, "Right rail")] {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: meshes.add(mesh),
+                            material: rail_material.clone(),
+                            ..default()
+                        },
+                        Name::new(name),
+                    ));
+                }
+                parent.spawn((
+                    PbrBundle {
+                        mesh: meshes.add(sleepers),
+                        material: sleeper_material,
+                        ..default()
+                    },
+                    Name::new("Sleepers"),
+                ));
+            })
+            .id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rail() -> ExtrudeShape {
+        ExtrudeShape::from_outline(&[Vec2::new(0.04, 0.), Vec2::new(0.04, 0.15), Vec2::new(-0.04, 0.15), Vec2::new(-0.04, 0.)], true)
+    }
+
+    #[test]
+    fn cant_lowers_the_inside_of_curves_only() {
+        let mut straight = path::linear(Vec3::NEG_Z, 10., 10);
+        let level = straight.clone();
+        apply_cant(&mut straight, 30., 0.1);
+        assert!(straight.iter().zip(level.iter()).all(|(a, b)| a.rotation.angle_between(b.rotation) < 1e-5));
+
+        // Counter-clockwise seen from above, so turning left
+        let mut curve = path::arc(50., 1., 20);
+        apply_cant(&mut curve, 30., 0.1);
+        let right = curve[10].local_to_world_direction(Vec3::X);
+        assert!(right.y > 0., "{right}");
+        assert!((right.y.asin() - 0.1).abs() < 1e-4, "the cant is clamped to the maximum");
+    }
+
+    #[test]
+    fn build_lays_sleepers_evenly_under_both_rails() {
+        let meshes = RailBuilder::new(path::linear(Vec3::NEG_Z, 6., 6), rail()).with_sleeper_spacing(0.5).build();
+
+        assert_eq!(meshes.sleeper_transforms.len(), 13);
+        assert_eq!(meshes.sleeper_transforms[12].translation, Vec3::new(0., 0., -6.));
+        let rail_x = meshes.rails.each_ref().map(|rail| {
+            let positions = rail.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().as_float3().unwrap();
+            positions.iter().map(|p| p[0]).sum::<f32>() / positions.len() as f32
+        });
+        assert!((rail_x[0] + STANDARD_GAUGE / 2.).abs() < 1e-4 && (rail_x[1] - STANDARD_GAUGE / 2.).abs() < 1e-4, "{rail_x:?}");
+    }
+}
@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+use bevy::render::mesh::skinning::{SkinnedMesh, SkinnedMeshInverseBindposes};
+use bevy::render::mesh::VertexAttributeValues;
+use crate::bezier::OrientedPoint;
+use crate::deform;
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape, ATTRIBUTE_PATH_DISTANCE};
+use crate::path::interpolate_path;
+
+/// An extrusion skinned to a chain of joints spread evenly along its path, so ropes, hoses and
+/// bridges can be bent by moving the joints instead of extruding again.
+#[derive(Debug, Clone)]
+pub struct RiggedExtrusion {
+    /// The extruded mesh, with `Mesh::ATTRIBUTE_JOINT_INDEX` and `Mesh::ATTRIBUTE_JOINT_WEIGHT`.
+    pub mesh: Mesh,
+    /// The rest pose of each joint, in the mesh's space, from the start of the path to its end. Joints
+    /// are unscaled, whatever the scale of the path.
+    pub joints: Vec<Transform>,
+}
+
+/// Extrudes `shape` along `path` with `joint_count` joints (at least two), placed by the path's V
+/// coordinates. Each edge loop is weighted to the two joints on either side of it, in proportion to
+/// how close it is to each.
+pub fn extrude_rigged(shape: &ExtrudeShape, path: &[OrientedPoint], joint_count: usize, options: &ExtrudeOptions) -> RiggedExtrusion {
+    let joint_count = joint_count.max(2);
+    let v_coordinates: Vec<f32> = path.iter().map(|point| point.v_coordinate).collect();
+    let (start, end) = match (v_coordinates.first(), v_coordinates.last()) {
+        (Some(&start), Some(&end)) => (start, end),
+        _ => (0., 0.),
+    };
+    let joint_v = move |joint: usize| start + (end - start) * joint as f32 / (joint_count - 1) as f32;
+
+    let joints = if path.is_empty() {
+        Vec::new()
+    } else {
+        (0..joint_count).map(|joint| interpolate_path(path, &v_coordinates, joint_v(joint)).to_transform().with_scale(Vec3::ONE)).collect()
+    };
+
+    // The pair of joints around a loop, and how far the loop is from the first towards the second
+    let span = move |v: f32| {
+        let f = if end > start { ((v - start) / (end - start)).clamp(0., 1.) * (joint_count - 1) as f32 } else { 0. };
+        let joint = (f.floor() as usize).min(joint_count - 2);
+        (joint, f - joint as f32)
+    };
+    // The loops' V coordinates come back as the path distance attribute
+    let requested_distance = options.path_distance;
+    let options = ExtrudeOptions {
+        path_distance: true,
+        ..options.clone()
+    };
+    let mut mesh = extrude::extrude_with_options(shape, path, &options);
+    let distances = if requested_distance {
+        mesh.attribute(ATTRIBUTE_PATH_DISTANCE).cloned()
+    } else {
+        mesh.remove_attribute(ATTRIBUTE_PATH_DISTANCE)
+    };
+    if let Some(VertexAttributeValues::Float32(distances)) = distances {
+        let (indices, weights): (Vec<[u16; 4]>, Vec<[f32; 4]>) = distances.into_iter()
+            .map(|v| {
+                let (joint, f) = span(v);
+                ([joint as u16, joint as u16 + 1, 0, 0], [1. - f, f, 0., 0.])
+            })
+            .unzip();
+        deform::insert_skin_weights(&mut mesh, indices, weights).expect("the extrusion has normals and a distance per vertex");
+    }
+
+    RiggedExtrusion { mesh, joints }
+}
+
+impl RiggedExtrusion {
+    /// The inverse of each joint's rest pose, see [`deform::inverse_bindposes`].
+    pub fn inverse_bindposes(&self) -> SkinnedMeshInverseBindposes {
+        let rest_pose: Vec<GlobalTransform> = self.joints.iter().map(|joint| GlobalTransform::from(*joint)).collect();
+        deform::inverse_bindposes(&rest_pose)
+    }
+
+    /// Spawns the mesh at `transform` with the joints as a chain of child entities, each relative to
+    /// the one before, and returns the mesh entity. Animate the joint entities (see
+    /// [`SkinnedMesh::joints`]) to bend it.
+    ///
+    /// The bind poses are in the mesh's space, so the joints must stay descendants of the mesh entity:
+    /// skinning ignores the mesh entity's own transform and places the mesh by the joints alone, which
+    /// follow it only as its children.
+    pub fn spawn(self, commands: &mut Commands, meshes: &mut Assets<Mesh>, bindposes: &mut Assets<SkinnedMeshInverseBindposes>, material: Handle<StandardMaterial>, transform: Transform) -> Entity {
+        let inverse_bindposes = bindposes.add(self.inverse_bindposes());
+
+        let mesh_entity = commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(self.mesh),
+                material,
+                transform,
+                ..default()
+            },
+            Name::new("Rigged extrusion"),
+        )).id();
+
+        let mut joints = Vec::with_capacity(self.joints.len());
+        let mut parent = (mesh_entity, GlobalTransform::IDENTITY);
+        for (index, joint) in self.joints.iter().enumerate() {
+            let global = GlobalTransform::from(*joint);
+            let entity = commands.spawn((SpatialBundle::from_transform(global.reparented_to(&parent.1)), Name::new(format!("Joint {index}")))).id();
+            commands.entity(parent.0).add_child(entity);
+            joints.push(entity);
+            parent = (entity, global);
+        }
+
+        commands.entity(mesh_entity).insert(SkinnedMesh { inverse_bindposes, joints });
+        mesh_entity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loops_are_weighted_to_the_joints_around_them() {
+        let shape = ExtrudeShape::from_outline(&[Vec2::new(1., 0.), Vec2::new(-1., 0.)], false);
+        let path = crate::path::linear(Vec3::NEG_Z, 8., 8);
+        let rigged = extrude_rigged(&shape, &path, 5, &ExtrudeOptions::default());
+
+        assert_eq!(rigged.joints.len(), 5);
+        assert!(rigged.joints[4].translation.distance(Vec3::new(0., 0., -8.)) < 1e-5);
+        assert!(rigged.mesh.attribute(ATTRIBUTE_PATH_DISTANCE).is_none());
+
+        let Some(VertexAttributeValues::Uint16x4(indices)) = rigged.mesh.attribute(Mesh::ATTRIBUTE_JOINT_INDEX) else {
+            panic!("no joint indices");
+        };
+        let Some(VertexAttributeValues::Float32x4(weights)) = rigged.mesh.attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT) else {
+            panic!("no joint weights");
+        };
+        for (index, weight) in indices.iter().zip(weights) {
+            assert!(index[1] < 5);
+            assert!((weight.iter().sum::<f32>() - 1.).abs() < 1e-5);
+        }
+        // The loop halfway between the first two joints
+        assert_eq!((indices[2], weights[2]), ([0, 1, 0, 0], [0.5, 0.5, 0., 0.]));
+    }
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use crate::bezier::{BezierCurve, OrientedPoint};
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+use crate::path::ExtrudePath;
+
+/// One strip of a road: a cross-section extruded at an offset from the centerline, e.g. a lane,
+/// a lane marking, a curb or a sidewalk.
+#[derive(Clone, Debug)]
+pub struct RoadLane {
+    pub name: String,
+    pub shape: ExtrudeShape,
+    /// Sideways (X) and vertical (Y) offset from the centerline.
+    pub offset: Vec2,
+    pub material: Handle<StandardMaterial>,
+}
+
+impl RoadLane {
+    pub fn new(name: impl Into<String>, shape: ExtrudeShape, offset: Vec2, material: Handle<StandardMaterial>) -> Self {
+        Self {
+            name: name.into(),
+            shape,
+            offset,
+            material,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RoadMesh {
+    pub name: String,
+    pub mesh: Mesh,
+    pub material: Handle<StandardMaterial>,
+}
+
+/// Builds a full road, with all its lanes and markings, from a single centerline.
+#[derive(Clone, Debug, Default)]
+pub struct RoadBuilder {
+    path: ExtrudePath,
+    lanes: Vec<RoadLane>,
+    options: ExtrudeOptions,
+}
+
+impl RoadBuilder {
+    pub fn new(path: impl Into<ExtrudePath>) -> Self {
+        Self {
+            path: path.into(),
+            ..default()
+        }
+    }
+
+    pub fn from_curve(curve: &BezierCurve, subdivisions: u32) -> Self {
+        Self::new(curve.generate_path(subdivisions))
+    }
+
+    pub fn lane(mut self, lane: RoadLane) -> Self {
+        self.lanes.push(lane);
+        self
+    }
+
+    /// Options applied to every lane. Their `offset` is added to each lane's own offset.
+    pub fn with_options(mut self, options: ExtrudeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn lanes(&self) -> &[RoadLane] {
+        &self.lanes
+    }
+
+    pub fn path(&self) -> &[OrientedPoint] {
+        &self.path
+    }
+
+    /// One mesh per lane, in the order the lanes were added.
+    pub fn build(&self) -> Vec<RoadMesh> {
+        self.lanes.iter()
+            .map(|lane| {
+                let options = ExtrudeOptions {
+                    offset: self.options.offset + lane.offset,
+                    ..self.options.clone()
+                };
+
+                RoadMesh {
+                    name: lane.name.clone(),
+                    mesh: extrude::extrude_with_options(&lane.shape, &self.path, &options),
+                    material: lane.material.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Spawns the road as a parent entity with one child per lane, and returns the parent.
+    pub fn spawn(&self, commands: &mut Commands, meshes: &mut Assets<Mesh>) -> Entity {
+        let lanes = self.build();
+
+        commands.spawn((SpatialBundle::default(), Name::new("Road")))
+            .with_children(|parent| {
+                for lane in lanes {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: meshes.add(lane.mesh),
+                            material: lane.material,
+                            ..default()
+                        },
+                        Name::new(lane.name),
+                    ));
+                }
+            })
+            .id()
+    }
+}
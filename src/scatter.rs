@@ -0,0 +1,275 @@
+use std::ops::Range;
+use bevy::prelude::*;
+use crate::bezier::OrientedPoint;
+use crate::extrude::ExtrudeShape;
+use crate::noise;
+use crate::path::{cumulative_distances, interpolate_path, ExtrudePath};
+
+/// A lateral band of the cross-section, e.g. the shoulder strip between `0.8..1.0`.
+#[derive(Debug, Clone)]
+pub struct ProfileRegion {
+    pub name: String,
+    pub lateral: Range<f32>,
+}
+
+impl ProfileRegion {
+    pub fn new(name: impl Into<String>, lateral: Range<f32>) -> Self {
+        Self {
+            name: name.into(),
+            lateral,
+        }
+    }
+}
+
+/// Scatters points over the top surface of the extrusion, restricted to the given regions of the
+/// cross-section. Points are laid out on a grid of `spacing` along and across the path, each moved
+/// randomly by up to `jitter * spacing`. The same seed always produces the same points.
+pub fn scatter_in_regions(path: &[OrientedPoint], shape: &ExtrudeShape, regions: &[ProfileRegion], spacing: f32, jitter: f32, seed: u64) -> Vec<Transform> {
+    let distances = cumulative_distances(path);
+    let Some(&length) = distances.last() else {
+        return Vec::new();
+    };
+    if spacing <= 0. {
+        return Vec::new();
+    }
+
+    let mut result = Vec::new();
+    let rows = (length / spacing).floor() as u64;
+    for row in 0..=rows {
+        for (region_index, region) in regions.iter().enumerate() {
+            let width = region.lateral.end - region.lateral.start;
+            let columns = (width / spacing).floor().max(0.) as u64;
+            for column in 0..=columns {
+                let key = seed ^ (region_index as u64) << 48 ^ row << 24 ^ column;
+                let distance = (row as f32 + 0.5 + (noise::hash_unit(key) - 0.5) * jitter) * spacing;
+                let lateral = region.lateral.start + (column as f32 + 0.5 + (noise::hash_unit(key ^ 1 << 63) - 0.5) * jitter) * spacing;
+                if distance > length || !region.lateral.contains(&lateral) {
+                    continue;
+                }
+                let Some(height) = shape.surface_height(lateral) else {
+                    continue;
+                };
+
+                let frame = interpolate_path(path, &distances, distance);
+                result.push(Transform::from_translation(frame.local_to_world(Vec3::new(lateral, height, 0.))).with_rotation(frame.rotation));
+            }
+        }
+    }
+
+    result
+}
+
+/// A grid of densities over the extrusion: one row per path point, `columns` samples spread over
+/// `lateral`, the sideways extent of the widest edge loop. Each row follows its point's frame, so
+/// where the path's scale narrows the cross-section the columns past its sides are empty. 1 inside
+/// any of the regions where the surface exists, 0 elsewhere.
+#[derive(Debug, Clone)]
+pub struct DensityMask {
+    pub columns: usize,
+    pub rows: usize,
+    pub lateral: Range<f32>,
+    pub values: Vec<f32>,
+}
+
+impl DensityMask {
+    pub fn new(path: &[OrientedPoint], shape: &ExtrudeShape, regions: &[ProfileRegion], columns: usize) -> Self {
+        let (outline, _) = shape.outline();
+        let extent = outline.iter().fold(f32::MAX..f32::MIN, |range, p| range.start.min(p.x)..range.end.max(p.x));
+        let widest = path.iter().fold(0_f32, |widest, point| widest.max(point.scale.x.abs()));
+        let lateral = extent.start * widest..extent.end * widest;
+        let columns = columns.max(1);
+
+        let values = path.iter()
+            .flat_map(|point| {
+                let lateral = lateral.clone();
+                (0..columns).map(move |column| {
+                    let x = lateral.start + (lateral.end - lateral.start) * (column as f32 + 0.5) / columns as f32;
+                    if point.scale.x.abs() <= f32::EPSILON {
+                        return 0.;
+                    }
+                    // Back into the cross-section's own space
+                    let x = x / point.scale.x;
+                    let inside = regions.iter().any(|region| region.lateral.contains(&x)) && shape.surface_height(x).is_some();
+                    if inside { 1. } else { 0. }
+                })
+            })
+            .collect();
+
+        Self {
+            columns,
+            rows: path.len(),
+            lateral,
+            values,
+        }
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        self.values[row * self.columns + column]
+    }
+}
+
+/// Transforms every `spacing` along `path`, from its start to its end, aligned to the path's frames,
+/// for fence posts, streetlights and railway sleepers. Each is moved randomly along the path by up to
+/// `jitter * spacing`. The same seed always produces the same transforms.
+pub fn scatter_along_path(path: &[OrientedPoint], spacing: f32, jitter: f32, seed: u64) -> Vec<Transform> {
+    scatter_frames(path, spacing, jitter, seed)
+        .into_iter()
+        .map(|frame| Transform::from_translation(frame.position).with_rotation(frame.rotation))
+        .collect()
+}
+
+fn scatter_frames(path: &[OrientedPoint], spacing: f32, jitter: f32, seed: u64) -> Vec<OrientedPoint> {
+    let distances = cumulative_distances(path);
+    let Some(&length) = distances.last() else {
+        return Vec::new();
+    };
+    if spacing <= 0. {
+        return Vec::new();
+    }
+
+    let count = (length / spacing).floor() as u64;
+    (0..=count)
+        .map(|i| {
+            let distance = (i as f32 + (noise::hash_unit(seed ^ i) - 0.5) * jitter) * spacing;
+            interpolate_path(path, &distances, distance.clamp(0., length))
+        })
+        .collect()
+}
+
+/// Scenes spawned along a path by [`crate::plugin::ExtrudeMeshPlugin`], each as a child of this entity.
+/// Changing the component respawns them, leaving the entity's other children alone.
+#[derive(Component, Clone, Debug)]
+pub struct PathScatter {
+    pub path: ExtrudePath,
+    /// Picked at random for each instance, so a few variations break up the repetition.
+    pub scenes: Vec<Handle<Scene>>,
+    pub spacing: f32,
+    pub jitter: f32,
+    /// Where the instances stand in the path's frame, X across and Y up, scaled with the cross-section.
+    /// Each spot along the path gets one instance per offset, e.g. streetlights on both sides of a road.
+    pub offsets: Vec<Vec3>,
+    /// Turns every instance in the path's frame.
+    pub rotation: Quat,
+    pub seed: u64,
+}
+
+impl PathScatter {
+    pub fn new(path: impl Into<ExtrudePath>, scene: Handle<Scene>, spacing: f32) -> Self {
+        Self {
+            path: path.into(),
+            scenes: vec![scene],
+            spacing,
+            jitter: 0.,
+            offsets: vec![Vec3::ZERO],
+            rotation: Quat::IDENTITY,
+            seed: 0,
+        }
+    }
+
+    pub fn with_scenes(mut self, scenes: Vec<Handle<Scene>>) -> Self {
+        self.scenes = scenes;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f32, seed: u64) -> Self {
+        self.jitter = jitter;
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_offsets(mut self, offsets: Vec<Vec3>) -> Self {
+        self.offsets = offsets;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// The scene and transform of every instance, relative to the entity.
+    pub fn instances(&self) -> Vec<(Handle<Scene>, Transform)> {
+        if self.scenes.is_empty() {
+            return Vec::new();
+        }
+
+        let frames = scatter_frames(&self.path, self.spacing, self.jitter, self.seed);
+        frames.iter()
+            .enumerate()
+            .flat_map(|(i, frame)| self.offsets.iter().enumerate().map(move |(j, offset)| {
+                let key = self.seed ^ (i as u64) << 32 ^ j as u64 ^ 1 << 63;
+                let scene = ((noise::hash_unit(key) * self.scenes.len() as f32) as usize).min(self.scenes.len() - 1);
+                let transform = Transform::from_translation(frame.local_to_world(*offset)).with_rotation(frame.rotation * self.rotation);
+                (self.scenes[scene].clone(), transform)
+            }))
+            .collect()
+    }
+}
+
+/// The instances spawned for a [`PathScatter`] by [`crate::plugin::ExtrudeMeshPlugin`], despawned when it respawns
+/// them so other children of the entity are left alone.
+#[derive(Component, Debug, Default)]
+pub struct PathScatterSpawned(pub Vec<Entity>);
+
+pub(crate) fn spawn_path_scatters(mut commands: Commands, scatters: Query<(Entity, Ref<PathScatter>, Option<&PathScatterSpawned>)>) {
+    for (entity, scatter, spawned) in &scatters {
+        if spawned.is_some() && !scatter.is_changed() {
+            continue;
+        }
+
+        for &instance in spawned.iter().flat_map(|spawned| &spawned.0) {
+            if let Some(instance) = commands.get_entity(instance) {
+                instance.despawn_recursive();
+            }
+        }
+
+        let mut instances = Vec::new();
+        commands.entity(entity).with_children(|parent| {
+            for (scene, transform) in scatter.instances() {
+                instances.push(parent.spawn(SceneBundle {
+                    scene,
+                    transform,
+                    ..default()
+                }).id());
+            }
+        });
+        commands.entity(entity).insert(PathScatterSpawned(instances));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scatter_along_path_covers_both_ends() {
+        let path = crate::path::linear(Vec3::NEG_Z, 10., 4);
+        let transforms = scatter_along_path(&path, 2.5, 0., 0);
+
+        assert_eq!(transforms.iter().map(|transform| transform.translation.z).collect::<Vec<_>>(), vec![0., -2.5, -5., -7.5, -10.]);
+        assert!(scatter_along_path(&path, 0., 0., 0).is_empty());
+    }
+
+    #[test]
+    fn jitter_is_repeatable_per_seed() {
+        let path = crate::path::linear(Vec3::NEG_Z, 10., 4);
+
+        assert_eq!(scatter_along_path(&path, 1., 0.5, 7), scatter_along_path(&path, 1., 0.5, 7));
+        assert_ne!(scatter_along_path(&path, 1., 0.5, 7), scatter_along_path(&path, 1., 0.5, 8));
+    }
+
+    #[test]
+    fn regions_restrict_points_and_mask() {
+        let path = crate::path::linear(Vec3::NEG_Z, 10., 4);
+        let shape = ExtrudeShape::from_outline(&[Vec2::new(2., 0.), Vec2::new(-2., 0.)], false);
+        let shoulder = [ProfileRegion::new("shoulder", 1.0..2.0)];
+
+        let points = scatter_in_regions(&path, &shape, &shoulder, 0.5, 0.5, 3);
+        assert!(!points.is_empty());
+        assert!(points.iter().all(|transform| (1.0..2.0).contains(&transform.translation.x)));
+
+        let mask = DensityMask::new(&path, &shape, &shoulder, 4);
+        assert_eq!((mask.rows, mask.columns), (5, 4));
+        assert_eq!((0..4).map(|column| mask.get(2, column)).collect::<Vec<_>>(), vec![0., 0., 0., 1.]);
+    }
+}
@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+use crate::bezier::FrameMode;
+use crate::extrude::{ExtrudeOptions, UMode};
+
+/// Project-wide defaults, used by the convenience constructors and the plugin's systems.
+#[derive(Resource, Debug, Clone)]
+pub struct ExtrudeMeshSettings {
+    pub u_mode: UMode,
+    pub frame_mode: FrameMode,
+    /// When set, splines are sampled with [`crate::bezier::BezierCurve::generate_path_adaptive`] at this tolerance instead of their subdivision count.
+    pub adaptive_tolerance: Option<f32>,
+    /// Extrude the chunks of a spline on the compute task pool.
+    pub parallel: bool,
+    /// When set, splines are extruded progressively over several frames, generating about this many
+    /// edge loops per frame, to avoid hitches on WASM and low-end targets. Unchunked splines are
+    /// chunked by this many segments.
+    pub loops_per_frame: Option<u32>,
+}
+
+impl Default for ExtrudeMeshSettings {
+    fn default() -> Self {
+        Self {
+            u_mode: UMode::default(),
+            frame_mode: FrameMode::default(),
+            adaptive_tolerance: None,
+            parallel: true,
+            loops_per_frame: None,
+        }
+    }
+}
+
+impl From<&ExtrudeMeshSettings> for ExtrudeOptions {
+    fn from(settings: &ExtrudeMeshSettings) -> Self {
+        Self {
+            u_mode: settings.u_mode,
+            ..default()
+        }
+    }
+}
@@ -0,0 +1,54 @@
+#define_import_path bevy_extrude_mesh::path_deform
+
+// A frame of the path
+struct PathFrame {
+    position: vec4<f32>,
+    // A quaternion
+    rotation: vec4<f32>,
+    scale: vec4<f32>,
+}
+
+@group(2) @binding(100) var<uniform> frame_spacing: f32;
+// Three vectors per frame (position, rotation, scale), for frames at equal distances along the path
+@group(2) @binding(101) var<storage, read> frames: array<vec4<f32>>;
+
+fn frame_at(i: u32) -> PathFrame {
+    var frame: PathFrame;
+    frame.position = frames[3u * i];
+    frame.rotation = frames[3u * i + 1u];
+    frame.scale = frames[3u * i + 2u];
+    return frame;
+}
+
+// The frame at the vertex's distance along the straight mesh, which runs down -Z
+fn path_frame(local_position: vec3<f32>) -> PathFrame {
+    let count = arrayLength(&frames) / 3u;
+    let f = max(-local_position.z, 0.0) / frame_spacing;
+    let i = min(u32(f), count - 2u);
+    let t = clamp(f - f32(i), 0.0, 1.0);
+    let a = frame_at(i);
+    let b = frame_at(i + 1u);
+
+    var frame: PathFrame;
+    frame.position = mix(a.position, b.position, t);
+    frame.rotation = normalize(mix(a.rotation, b.rotation, t));
+    frame.scale = mix(a.scale, b.scale, t);
+    return frame;
+}
+
+fn rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
+
+fn deform_position(frame: PathFrame, local_position: vec3<f32>) -> vec3<f32> {
+    return frame.position.xyz + rotate(frame.rotation, vec3<f32>(local_position.xy, 0.0) * frame.scale.xyz);
+}
+
+// Normals scale inversely to keep them perpendicular to the stretched surface
+fn deform_normal(frame: PathFrame, normal: vec3<f32>) -> vec3<f32> {
+    return normalize(rotate(frame.rotation, normal / frame.scale.xyz));
+}
+
+fn deform_tangent(frame: PathFrame, tangent: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(normalize(rotate(frame.rotation, tangent.xyz * frame.scale.xyz)), tangent.w);
+}
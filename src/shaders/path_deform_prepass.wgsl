@@ -0,0 +1,51 @@
+#import bevy_pbr::{
+    mesh_functions,
+    prepass_io::{Vertex, VertexOutput},
+    view_transformations::position_world_to_clip,
+}
+#import bevy_extrude_mesh::path_deform::{path_frame, deform_position, deform_normal, deform_tangent}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let frame = path_frame(vertex.position);
+    let position = vec4<f32>(deform_position(frame, vertex.position), 1.0);
+    var world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+
+    out.world_position = mesh_functions::mesh_position_local_to_world(world_from_local, position);
+    out.position = position_world_to_clip(out.world_position.xyz);
+#ifdef DEPTH_CLAMP_ORTHO
+    out.clip_position_unclamped = out.position;
+    out.position.z = min(out.position.z, 1.0);
+#endif
+
+#ifdef VERTEX_UVS_A
+    out.uv = vertex.uv;
+#endif
+#ifdef VERTEX_UVS_B
+    out.uv_b = vertex.uv_b;
+#endif
+
+#ifdef NORMAL_PREPASS_OR_DEFERRED_PREPASS
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(deform_normal(frame, vertex.normal), vertex.instance_index);
+#ifdef VERTEX_TANGENTS
+    out.world_tangent = mesh_functions::mesh_tangent_local_to_world(world_from_local, deform_tangent(frame, vertex.tangent), vertex.instance_index);
+#endif
+#endif
+
+#ifdef VERTEX_COLORS
+    out.color = vertex.color;
+#endif
+
+#ifdef MOTION_VECTOR_PREPASS
+    // The path's previous frames aren't kept, so only the entity's own motion counts
+    out.previous_world_position = mesh_functions::mesh_position_local_to_world(mesh_functions::get_previous_world_from_local(vertex.instance_index), position);
+#endif
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+
+    return out;
+}
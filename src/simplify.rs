@@ -0,0 +1,277 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::ops::AddAssign;
+use glam::{DVec3, Vec3};
+use crate::geometry::ExtrudedGeometry;
+
+/// Vertices closer than this are merged before simplifying, so neighboring edge loops share edges.
+const WELD_EPSILON: f32 = 1e-5;
+/// How much more moving a vertex off a boundary edge costs than moving it off a face, so open outlines
+/// and the ends of the extrusion keep their shape.
+const BOUNDARY_WEIGHT: f64 = 100.;
+
+/// Decimates the extrusion by collapsing edges in order of their quadric error (Garland and Heckbert),
+/// until about `target_ratio` of the triangles are left or the next collapse would move the surface by
+/// more than `max_error`. Meant for distant levels of detail and collision proxies: the vertices are
+/// welded first, so UV seams are merged and normals are recomputed smooth.
+pub fn simplify(geometry: &ExtrudedGeometry, target_ratio: f32, max_error: f32) -> ExtrudedGeometry {
+    let mut welded = geometry.clone();
+    welded.weld(WELD_EPSILON);
+
+    let positions: Vec<DVec3> = welded.positions.iter().map(|p| Vec3::from_array(*p).as_dvec3()).collect();
+    let mut triangles: Vec<[u32; 3]> = welded.indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+    let target = (triangles.len() as f32 * target_ratio.clamp(0., 1.)).round() as usize;
+    let max_cost = (max_error as f64).powi(2);
+
+    let mut quadrics = vec![Quadric::default(); positions.len()];
+    let mut vertex_triangles = vec![Vec::new(); positions.len()];
+    let mut edge_faces: HashMap<[u32; 2], Vec<usize>> = HashMap::new();
+    for (t, triangle) in triangles.iter().enumerate() {
+        if let Some(normal) = face_normal(triangle, &positions) {
+            let plane = Quadric::plane(normal, positions[triangle[0] as usize], 1.);
+            for &v in triangle {
+                quadrics[v as usize] += plane;
+            }
+        }
+        for (k, &v) in triangle.iter().enumerate() {
+            vertex_triangles[v as usize].push(t);
+            edge_faces.entry(edge_key(v, triangle[(k + 1) % 3])).or_default().push(t);
+        }
+    }
+
+    // Planes through the boundary edges, perpendicular to their face, hold the borders in place
+    for (&[a, b], faces) in &edge_faces {
+        let (pa, pb) = (positions[a as usize], positions[b as usize]);
+        let Some(normal) = faces.first().filter(|_| faces.len() == 1).and_then(|&t| face_normal(&triangles[t], &positions)) else {
+            continue;
+        };
+        if let Some(normal) = (pb - pa).cross(normal).try_normalize() {
+            let plane = Quadric::plane(normal, pa, BOUNDARY_WEIGHT);
+            quadrics[a as usize] += plane;
+            quadrics[b as usize] += plane;
+        }
+    }
+
+    let mut versions = vec![0u32; positions.len()];
+    let mut removed = vec![false; positions.len()];
+    let mut alive = vec![true; triangles.len()];
+    let mut live_triangles = triangles.len();
+    let mut heap: BinaryHeap<Collapse> = edge_faces.keys()
+        .map(|&[a, b]| Collapse::cheapest(a, b, &quadrics, &positions, &versions))
+        .collect();
+
+    while live_triangles > target {
+        let Some(collapse) = heap.pop() else {
+            break;
+        };
+        let (from, to) = (collapse.from as usize, collapse.to as usize);
+        if removed[from] || removed[to] || collapse.versions != [versions[from], versions[to]] {
+            continue;
+        }
+        if collapse.cost > max_cost {
+            break;
+        }
+        if flips_faces(from, to, &triangles, &alive, &vertex_triangles, &positions) {
+            continue;
+        }
+
+        for t in std::mem::take(&mut vertex_triangles[from]) {
+            if !alive[t] {
+                continue;
+            }
+            if triangles[t].contains(&(to as u32)) {
+                alive[t] = false;
+                live_triangles -= 1;
+            } else {
+                triangles[t].iter_mut().filter(|v| **v == from as u32).for_each(|v| *v = to as u32);
+                vertex_triangles[to].push(t);
+            }
+        }
+        let merged = quadrics[from];
+        quadrics[to] += merged;
+        removed[from] = true;
+        versions[to] += 1;
+        vertex_triangles[to].retain(|&t| alive[t]);
+
+        let neighbors: HashSet<u32> = vertex_triangles[to].iter()
+            .flat_map(|&t| triangles[t])
+            .filter(|&v| v != to as u32)
+            .collect();
+        heap.extend(neighbors.into_iter().map(|v| Collapse::cheapest(to as u32, v, &quadrics, &positions, &versions)));
+    }
+
+    let mut remap = vec![u32::MAX; positions.len()];
+    let mut result = ExtrudedGeometry::default();
+    for (triangle, _) in triangles.iter().zip(&alive).filter(|(_, alive)| **alive) {
+        for &v in triangle {
+            let v = v as usize;
+            if remap[v] == u32::MAX {
+                remap[v] = result.positions.len() as u32;
+                result.positions.push(welded.positions[v]);
+                if !welded.uvs.is_empty() {
+                    result.uvs.push(welded.uvs[v]);
+                }
+            }
+            result.indices.push(remap[v]);
+        }
+    }
+    result.recompute_normals();
+
+    result
+}
+
+fn edge_key(a: u32, b: u32) -> [u32; 2] {
+    [a.min(b), a.max(b)]
+}
+
+fn face_normal(triangle: &[u32; 3], positions: &[DVec3]) -> Option<DVec3> {
+    let [a, b, c] = triangle.map(|v| positions[v as usize]);
+    (b - a).cross(c - a).try_normalize()
+}
+
+/// Whether moving `from` onto `to` would turn any of the faces around `from` (other than the ones
+/// that collapse) upside down.
+fn flips_faces(from: usize, to: usize, triangles: &[[u32; 3]], alive: &[bool], vertex_triangles: &[Vec<usize>], positions: &[DVec3]) -> bool {
+    vertex_triangles[from].iter()
+        .filter(|&&t| alive[t] && !triangles[t].contains(&(to as u32)))
+        .any(|&t| {
+            let [a, b, c] = triangles[t].map(|v| positions[v as usize]);
+            let [na, nb, nc] = triangles[t].map(|v| positions[if v as usize == from { to } else { v as usize }]);
+            (b - a).cross(c - a).dot((nb - na).cross(nc - na)) <= 0.
+        })
+}
+
+/// The sum of squared distances to a set of planes, as a symmetric 4x4 matrix's upper triangle.
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(normal: DVec3, point: DVec3, weight: f64) -> Self {
+        let [a, b, c] = normal.to_array();
+        let d = -normal.dot(point);
+        Self([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|q| q * weight))
+    }
+
+    fn error(&self, p: DVec3) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        q[0] * x * x + 2. * q[1] * x * y + 2. * q[2] * x * z + 2. * q[3] * x
+            + q[4] * y * y + 2. * q[5] * y * z + 2. * q[6] * y
+            + q[7] * z * z + 2. * q[8] * z
+            + q[9]
+    }
+}
+
+impl AddAssign for Quadric {
+    fn add_assign(&mut self, other: Self) {
+        for (q, o) in self.0.iter_mut().zip(other.0) {
+            *q += o;
+        }
+    }
+}
+
+/// Moving `from` onto `to`, valid as long as neither vertex changed since (see `versions`).
+#[derive(Debug, Clone, Copy)]
+struct Collapse {
+    cost: f64,
+    from: u32,
+    to: u32,
+    versions: [u32; 2],
+}
+
+impl Collapse {
+    /// The cheaper direction to collapse the edge between `a` and `b` in.
+    fn cheapest(a: u32, b: u32, quadrics: &[Quadric], positions: &[DVec3], versions: &[u32]) -> Self {
+        let mut quadric = quadrics[a as usize];
+        quadric += quadrics[b as usize];
+        let (from, to, cost) = {
+            let onto_b = quadric.error(positions[b as usize]);
+            let onto_a = quadric.error(positions[a as usize]);
+            if onto_b <= onto_a { (a, b, onto_b) } else { (b, a, onto_a) }
+        };
+
+        Self {
+            cost: cost.max(0.),
+            from,
+            to,
+            versions: [versions[from as usize], versions[to as usize]],
+        }
+    }
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    /// Reversed, so the heap pops the cheapest collapse first. Ties go to the lowest vertex indices,
+    /// so the result doesn't depend on hash map order.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then_with(|| (other.from, other.to).cmp(&(self.from, self.to)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid of `n` by `n` unit quads on the XZ plane, raised to `height(x)`.
+    fn grid(n: u32, height: impl Fn(f32) -> f32) -> ExtrudedGeometry {
+        let mut geometry = ExtrudedGeometry::default();
+        for z in 0..=n {
+            for x in 0..=n {
+                geometry.positions.push([x as f32, height(x as f32), z as f32]);
+                geometry.normals.push([0., 1., 0.]);
+                geometry.uvs.push([x as f32, z as f32]);
+            }
+        }
+        for z in 0..n {
+            for x in 0..n {
+                let a = z * (n + 1) + x;
+                let b = a + n + 1;
+                geometry.indices.extend([a, b, a + 1, a + 1, b, b + 1]);
+            }
+        }
+        geometry
+    }
+
+    fn bounds(geometry: &ExtrudedGeometry) -> (Vec3, Vec3) {
+        geometry.positions.iter().fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), p| {
+            (min.min(Vec3::from_array(*p)), max.max(Vec3::from_array(*p)))
+        })
+    }
+
+    #[test]
+    fn flat_grid_collapses_to_its_corners() {
+        let simplified = simplify(&grid(5, |_| 0.), 0., 1e-3);
+        assert_eq!(simplified.indices.len() / 3, 2);
+        assert_eq!(bounds(&simplified), (Vec3::ZERO, Vec3::new(5., 0., 5.)));
+    }
+
+    #[test]
+    fn ridge_is_kept_within_max_error() {
+        let simplified = simplify(&grid(4, |x| 2. - (x - 2.).abs()), 0., 1e-3);
+        assert!(simplified.indices.len() / 3 < 32 / 4);
+        assert_eq!(bounds(&simplified), (Vec3::ZERO, Vec3::new(4., 2., 4.)));
+        // Every vertex stays on one of the two slopes
+        for p in &simplified.positions {
+            assert!((p[1] - (2. - (p[0] - 2.).abs())).abs() < 1e-4, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn target_ratio_one_keeps_everything() {
+        let simplified = simplify(&grid(3, |x| x * x), 1., 1.);
+        assert_eq!(simplified.indices.len(), 3 * 18);
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use crate::bezier::OrientedPoint;
+
+const WELD_EPSILON: f32 = 1e-4;
+
+/// Intersects a triangle mesh with the plane through `frame` facing along its direction.
+/// Returns the cut as 2D outlines in the frame's local X/Y coordinates, with a flag telling
+/// whether each one is closed. Outlines of a solid mesh come out counter-clockwise (holes clockwise).
+pub fn slice_mesh(mesh: &Mesh, frame: &OrientedPoint) -> Vec<(Vec<Vec2>, bool)> {
+    let Some(positions) = mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(|p| p.as_float3()) else {
+        return Vec::new();
+    };
+    let indices: Vec<u32> = match mesh.indices() {
+        Some(Indices::U16(i)) => i.iter().map(|x| *x as u32).collect(),
+        Some(Indices::U32(i)) => i.clone(),
+        None => (0..positions.len() as u32).collect(),
+    };
+
+    let plane_normal = frame.forward();
+    let distance = |p: Vec3| (p - frame.position).dot(plane_normal);
+
+    // Cut every triangle crossing the plane into a segment
+    let mut segments = Vec::new();
+    for triangle in indices.chunks_exact(3) {
+        let corners = [0, 1, 2].map(|i| Vec3::from_array(positions[triangle[i] as usize]));
+        let distances = corners.map(distance);
+
+        let mut crossing = Vec::with_capacity(2);
+        for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+            if (distances[i] >= 0.) != (distances[j] >= 0.) {
+                let f = distances[i] / (distances[i] - distances[j]);
+                crossing.push(corners[i].lerp(corners[j], f));
+            }
+        }
+        if crossing.len() != 2 {
+            continue;
+        }
+
+        // Orient the segments consistently so they chain head to tail
+        let triangle_normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+        let (mut a, mut b) = (crossing[0], crossing[1]);
+        if (b - a).dot(plane_normal.cross(triangle_normal)) < 0. {
+            std::mem::swap(&mut a, &mut b);
+        }
+
+        let to_local = |p: Vec3| frame.world_to_local(p).truncate();
+        segments.push((to_local(a), to_local(b)));
+    }
+
+    let key = |p: Vec2| ((p.x / WELD_EPSILON).round() as i64, (p.y / WELD_EPSILON).round() as i64);
+    let mut starting_at: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, (a, _)) in segments.iter().enumerate() {
+        starting_at.entry(key(*a)).or_default().push(i);
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut outlines = Vec::new();
+    for first in 0..segments.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+
+        let start_key = key(segments[first].0);
+        let mut points = vec![segments[first].0];
+        let mut current = segments[first].1;
+        let mut closed = false;
+        loop {
+            if key(current) == start_key {
+                closed = true;
+                break;
+            }
+            points.push(current);
+
+            let next = starting_at.get(&key(current)).and_then(|candidates| candidates.iter().copied().find(|i| !used[*i]));
+            let Some(next) = next else {
+                break;
+            };
+            used[next] = true;
+            current = segments[next].1;
+        }
+
+        points.dedup_by(|a, b| a.distance(*b) < WELD_EPSILON);
+        if points.len() >= 2 {
+            outlines.push((points, closed));
+        }
+    }
+
+    // The plane's facing decides the winding, flip everything so the outer outlines end up counter-clockwise
+    let total_area: f32 = outlines.iter().filter(|(_, closed)| *closed).map(|(points, _)| signed_area(points)).sum();
+    if total_area < 0. {
+        outlines.iter_mut().for_each(|(points, _)| points.reverse());
+    }
+
+    outlines
+}
+
+fn signed_area(points: &[Vec2]) -> f32 {
+    let n = points.len();
+    (0..n).map(|i| points[i].perp_dot(points[(i + 1) % n])).sum::<f32>() * 0.5
+}
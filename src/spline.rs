@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use bevy::ecs::system::EntityCommand;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use bevy::utils::Instant;
+use crate::bezier::{self, BezierCurve, FrameMode, VMode};
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+use crate::kit::KitTrack;
+use crate::path::ExtrudePath;
+use crate::settings::ExtrudeMeshSettings;
+
+/// A curve extruded by [`crate::plugin::ExtrudeMeshPlugin`]. The mesh is split into chunks of
+/// `segments_per_chunk` path segments (a single chunk if `None`), each spawned as a child entity
+/// sharing `material`. Changing the component regenerates the chunks in place.
+#[derive(Component, Clone, Debug)]
+pub struct ExtrudedSpline {
+    pub curve: BezierCurve,
+    pub shape: Handle<ExtrudeShape>,
+    pub material: Handle<StandardMaterial>,
+    pub subdivisions: u32,
+    /// When set, the curve is sampled adaptively with this tolerance instead of `subdivisions`.
+    pub adaptive_tolerance: Option<f32>,
+    pub frame_mode: FrameMode,
+    pub segments_per_chunk: Option<u32>,
+    pub options: ExtrudeOptions,
+}
+
+impl ExtrudedSpline {
+    pub fn new(curve: BezierCurve, shape: Handle<ExtrudeShape>, material: Handle<StandardMaterial>, subdivisions: u32) -> Self {
+        Self {
+            curve,
+            shape,
+            material,
+            subdivisions,
+            adaptive_tolerance: None,
+            frame_mode: FrameMode::default(),
+            segments_per_chunk: None,
+            options: ExtrudeOptions::default(),
+        }
+    }
+
+    /// Like [`ExtrudedSpline::new`], taking the defaults from the project settings.
+    pub fn from_settings(curve: BezierCurve, shape: Handle<ExtrudeShape>, material: Handle<StandardMaterial>, subdivisions: u32, settings: &ExtrudeMeshSettings) -> Self {
+        Self {
+            adaptive_tolerance: settings.adaptive_tolerance,
+            frame_mode: settings.frame_mode,
+            options: settings.into(),
+            ..Self::new(curve, shape, material, subdivisions)
+        }
+    }
+
+    pub fn with_segments_per_chunk(mut self, segments_per_chunk: u32) -> Self {
+        self.segments_per_chunk = Some(segments_per_chunk);
+        self
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ExtrudedChunk {
+    pub index: usize,
+}
+
+#[derive(Component)]
+pub struct ExtrudedSplineGenerated;
+
+/// Sent whenever the plugin has (re)generated the mesh of a spline, for budgeting systems to react to.
+#[derive(Event, Debug, Clone)]
+pub struct ExtrusionFinished {
+    pub entity: Entity,
+    pub chunk_count: usize,
+    pub vertex_count: usize,
+    pub index_count: usize,
+    pub duration: Duration,
+}
+
+/// The path a spline was last extruded along. It is reused as long as the curve and sampling
+/// settings stay the same, so swapping the cross-section or options keeps the exact same edge loops and V coordinates.
+#[derive(Component, Clone, Debug)]
+pub struct SampledPath {
+    pub path: ExtrudePath,
+    key: SamplingKey,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct SamplingKey {
+    points: Vec<Vec3>,
+    samples: usize,
+    v_mode: VMode,
+    resolution: usize,
+    subdivisions: u32,
+    adaptive_tolerance: Option<f32>,
+    frame_mode: FrameMode,
+}
+
+impl SamplingKey {
+    fn new(spline: &ExtrudedSpline) -> Self {
+        Self {
+            points: spline.curve.points().to_vec(),
+            samples: spline.curve.samples(),
+            v_mode: spline.curve.v_mode(),
+            resolution: spline.curve.resolution(),
+            subdivisions: spline.subdivisions,
+            adaptive_tolerance: spline.adaptive_tolerance,
+            frame_mode: spline.frame_mode,
+        }
+    }
+}
+
+/// Re-extrudes a spline (or kit track) with a different cross-section, keeping its path, chunks and UV layout.
+///
+/// `commands.entity(road).add(Reprofile(paved_shape));`
+pub struct Reprofile(pub Handle<ExtrudeShape>);
+
+impl EntityCommand for Reprofile {
+    fn apply(self, id: Entity, world: &mut World) {
+        if let Some(mut spline) = world.get_mut::<ExtrudedSpline>(id) {
+            spline.shape = self.0.clone();
+        }
+        if let Some(mut track) = world.get_mut::<KitTrack>(id) {
+            track.shape = self.0;
+        }
+    }
+}
+
+fn sample_path(spline: &ExtrudedSpline) -> ExtrudePath {
+    let mut path = match spline.adaptive_tolerance {
+        Some(tolerance) => spline.curve.generate_path_adaptive(tolerance),
+        None => spline.curve.generate_path(spline.subdivisions),
+    };
+    bezier::apply_frame_mode(&mut path, spline.frame_mode);
+
+    path
+}
+
+/// A spline being extruded a few chunks per frame, see [`ExtrudeMeshSettings::loops_per_frame`].
+#[derive(Component, Clone, Debug)]
+pub struct PendingExtrusion {
+    path: ExtrudePath,
+    key: SamplingKey,
+    segments_per_chunk: usize,
+    next_chunk: usize,
+    vertex_count: usize,
+    index_count: usize,
+    duration: Duration,
+}
+
+impl PendingExtrusion {
+    /// How much of the path has been extruded so far, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        let segments = self.path.len().saturating_sub(1);
+        if segments == 0 {
+            return 1.;
+        }
+
+        (self.next_chunk * self.segments_per_chunk).min(segments) as f32 / segments as f32
+    }
+}
+
+type ChunkQuery<'w, 's> = Query<'w, 's, (&'static ExtrudedChunk, &'static Handle<Mesh>, &'static mut Handle<StandardMaterial>, &'static mut Aabb)>;
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_extruded_splines(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    shapes: Res<Assets<ExtrudeShape>>,
+    settings: Res<ExtrudeMeshSettings>,
+    mut finished: EventWriter<ExtrusionFinished>,
+    splines: Query<(Entity, Ref<ExtrudedSpline>, Option<&Children>, Option<&SampledPath>, Has<ExtrudedSplineGenerated>)>,
+    mut chunks: ChunkQuery,
+) {
+    for (entity, spline, children, sampled_path, generated) in &splines {
+        if generated && !spline.is_changed() {
+            continue;
+        }
+        let Some(shape) = shapes.get(&spline.shape) else {
+            continue;
+        };
+
+        let start = Instant::now();
+        let key = SamplingKey::new(&spline);
+        let path = match sampled_path {
+            Some(sampled) if sampled.key == key => sampled.path.clone(),
+            _ => sample_path(&spline),
+        };
+
+        // Progressive generation picks up from here over the next frames
+        if let Some(loops_per_frame) = settings.loops_per_frame {
+            let segments_per_chunk = spline.segments_per_chunk.unwrap_or(loops_per_frame).max(1) as usize;
+            commands.entity(entity).insert((ExtrudedSplineGenerated, PendingExtrusion {
+                path,
+                key,
+                segments_per_chunk,
+                next_chunk: 0,
+                vertex_count: 0,
+                index_count: 0,
+                duration: start.elapsed(),
+            }));
+            continue;
+        }
+
+        let segments_per_chunk = spline.segments_per_chunk.map_or(path.len(), |n| n as usize);
+        let mut existing = existing_chunks(children, &chunks);
+
+        let chunk_paths = extrude::path_chunks(&path, segments_per_chunk);
+        let chunk_meshes: Vec<Mesh> = if settings.parallel && chunk_paths.len() > 1 {
+            ComputeTaskPool::get_or_init(TaskPool::default).scope(|scope| {
+                for chunk_path in &chunk_paths {
+                    let options = &spline.options;
+                    scope.spawn(async move { extrude::extrude_with_options(shape, chunk_path, options) });
+                }
+            })
+        } else {
+            chunk_paths.iter().map(|chunk_path| extrude::extrude_with_options(shape, chunk_path, &spline.options)).collect()
+        };
+
+        let chunk_count = chunk_meshes.len();
+        let vertex_count = chunk_meshes.iter().map(|mesh| mesh.count_vertices()).sum();
+        let index_count = chunk_meshes.iter().map(|mesh| mesh.indices().map_or(0, |indices| indices.len())).sum();
+
+        for (index, mesh) in chunk_meshes.into_iter().enumerate() {
+            apply_chunk(&mut commands, &mut meshes, &mut chunks, &mut existing, entity, &spline.material, index, mesh);
+        }
+
+        // Chunks past the new end of the path
+        for (_, child) in existing {
+            commands.entity(child).despawn_recursive();
+        }
+
+        commands.entity(entity).remove::<PendingExtrusion>().insert((ExtrudedSplineGenerated, SampledPath { path, key }));
+        finished.send(ExtrusionFinished {
+            entity,
+            chunk_count,
+            vertex_count,
+            index_count,
+            duration: start.elapsed(),
+        });
+    }
+}
+
+/// Extrudes the next chunks of pending splines, stopping once `loops_per_frame` edge loops have been generated this frame.
+pub(crate) fn advance_pending_extrusions(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    shapes: Res<Assets<ExtrudeShape>>,
+    settings: Res<ExtrudeMeshSettings>,
+    mut finished: EventWriter<ExtrusionFinished>,
+    mut splines: Query<(Entity, &ExtrudedSpline, Option<&Children>, &mut PendingExtrusion)>,
+    mut chunks: ChunkQuery,
+) {
+    let mut budget = settings.loops_per_frame.map_or(usize::MAX, |n| n.max(1) as usize);
+
+    for (entity, spline, children, pending) in &mut splines {
+        if budget == 0 {
+            break;
+        }
+        let pending = pending.into_inner();
+        let Some(shape) = shapes.get(&spline.shape) else {
+            continue;
+        };
+
+        let start = Instant::now();
+        let mut existing = existing_chunks(children, &chunks);
+        let chunk_paths = extrude::path_chunks(&pending.path, pending.segments_per_chunk);
+
+        while budget > 0 && pending.next_chunk < chunk_paths.len() {
+            let index = pending.next_chunk;
+            let mesh = extrude::extrude_with_options(shape, chunk_paths[index], &spline.options);
+            pending.vertex_count += mesh.count_vertices();
+            pending.index_count += mesh.indices().map_or(0, |indices| indices.len());
+            budget = budget.saturating_sub(chunk_paths[index].len());
+
+            apply_chunk(&mut commands, &mut meshes, &mut chunks, &mut existing, entity, &spline.material, index, mesh);
+            pending.next_chunk += 1;
+        }
+        pending.duration += start.elapsed();
+
+        if pending.next_chunk < chunk_paths.len() {
+            continue;
+        }
+
+        // Done: drop chunks past the new end of the path
+        let chunk_count = chunk_paths.len();
+        for (index, child) in existing {
+            if index >= chunk_count {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+
+        commands.entity(entity).remove::<PendingExtrusion>().insert(SampledPath { path: pending.path.clone(), key: pending.key.clone() });
+        finished.send(ExtrusionFinished {
+            entity,
+            chunk_count,
+            vertex_count: pending.vertex_count,
+            index_count: pending.index_count,
+            duration: pending.duration,
+        });
+    }
+}
+
+fn existing_chunks(children: Option<&Children>, chunks: &ChunkQuery) -> HashMap<usize, Entity> {
+    children
+        .map(|children| children.iter().filter_map(|&child| chunks.get(child).ok().map(|(chunk, ..)| (chunk.index, child))).collect())
+        .unwrap_or_default()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_chunk(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    chunks: &mut ChunkQuery,
+    existing: &mut HashMap<usize, Entity>,
+    parent: Entity,
+    material: &Handle<StandardMaterial>,
+    index: usize,
+    mesh: Mesh,
+) {
+    let aabb = mesh.compute_aabb().unwrap_or_default();
+
+    // Reuse the chunk's entity and mesh asset if it already exists
+    if let Some((_, handle, mut chunk_material, mut chunk_aabb)) = existing.remove(&index).and_then(|child| chunks.get_mut(child).ok()) {
+        meshes.insert(handle, mesh);
+        *chunk_aabb = aabb;
+        if *chunk_material != *material {
+            *chunk_material = material.clone();
+        }
+        return;
+    }
+
+    let chunk = commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(mesh),
+            material: material.clone(),
+            ..default()
+        },
+        aabb,
+        ExtrudedChunk { index },
+        Name::new(format!("Chunk {index}")),
+    )).id();
+    commands.entity(parent).add_child(chunk);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spline(curve: BezierCurve) -> ExtrudedSpline {
+        ExtrudedSpline::new(curve, Handle::default(), Handle::default(), 8)
+    }
+
+    fn curve() -> BezierCurve {
+        BezierCurve::new(vec![Vec3::ZERO, Vec3::new(1., 0., -1.), Vec3::new(2., 0., -2.), Vec3::new(3., 0., -3.)], None)
+    }
+
+    #[test]
+    fn sampling_key_changes_with_the_curve_settings() {
+        let key = SamplingKey::new(&spline(curve()));
+        assert_eq!(key, SamplingKey::new(&spline(curve())));
+        assert_ne!(key, SamplingKey::new(&spline(curve().with_samples(20))));
+        assert_ne!(key, SamplingKey::new(&spline(curve().with_v_mode(VMode::Normalized))));
+        assert_ne!(key, SamplingKey::new(&spline(curve().with_resolution(50))));
+    }
+}
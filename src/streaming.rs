@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+use bevy::prelude::*;
+use crate::bezier::OrientedPoint;
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+use crate::spline::ExtrudedChunk;
+
+/// Produces the point following `last` on an endless path. Closures `FnMut(&OrientedPoint) -> OrientedPoint`
+/// implement it; the returned V coordinate is ignored and recomputed from the distance travelled.
+pub trait PathExtender: Send + Sync + 'static {
+    fn next(&mut self, last: &OrientedPoint) -> OrientedPoint;
+}
+
+impl<F: FnMut(&OrientedPoint) -> OrientedPoint + Send + Sync + 'static> PathExtender for F {
+    fn next(&mut self, last: &OrientedPoint) -> OrientedPoint {
+        self(last)
+    }
+}
+
+struct StreamedChunk {
+    entity: Entity,
+    mesh: Handle<Mesh>,
+    path: Vec<OrientedPoint>,
+}
+
+/// An endless track generated ahead of `follower` and recycled behind it. The track keeps at most
+/// `chunks_behind + 1 + chunks_ahead` chunks of `segments_per_chunk` segments; chunks falling too far
+/// behind are reused, entity and mesh asset included, for the new chunks ahead.
+#[derive(Component)]
+pub struct StreamedTrack {
+    pub shape: Handle<ExtrudeShape>,
+    pub material: Handle<StandardMaterial>,
+    pub options: ExtrudeOptions,
+    pub follower: Entity,
+    pub segments_per_chunk: usize,
+    pub chunks_ahead: usize,
+    pub chunks_behind: usize,
+    extender: Box<dyn PathExtender>,
+    end: OrientedPoint,
+    next_index: usize,
+    chunks: VecDeque<StreamedChunk>,
+}
+
+impl StreamedTrack {
+    pub fn new(start: OrientedPoint, extender: impl PathExtender, shape: Handle<ExtrudeShape>, material: Handle<StandardMaterial>, follower: Entity) -> Self {
+        Self {
+            shape,
+            material,
+            options: ExtrudeOptions::default(),
+            follower,
+            segments_per_chunk: 16,
+            chunks_ahead: 4,
+            chunks_behind: 1,
+            extender: Box::new(extender),
+            end: start,
+            next_index: 0,
+            chunks: VecDeque::new(),
+        }
+    }
+
+    /// The live part of the path, from the oldest kept chunk to the end generated so far.
+    pub fn path(&self) -> impl Iterator<Item = &OrientedPoint> {
+        self.chunks.iter()
+            .enumerate()
+            .flat_map(|(i, chunk)| chunk.path.iter().skip(if i == 0 { 0 } else { 1 }))
+    }
+
+    fn extend(&mut self) -> Vec<OrientedPoint> {
+        let mut path = Vec::with_capacity(self.segments_per_chunk.max(1) + 1);
+        path.push(self.end.clone());
+        for _ in 0..self.segments_per_chunk.max(1) {
+            let last = &path[path.len() - 1];
+            let mut next = self.extender.next(last);
+            next.v_coordinate = last.v_coordinate + last.position.distance(next.position);
+            path.push(next);
+        }
+        self.end = path[path.len() - 1].clone();
+
+        path
+    }
+
+    /// The index (in the kept chunks) of the chunk closest to `position`.
+    fn current_chunk(&self, position: Vec3) -> Option<usize> {
+        self.chunks.iter()
+            .map(|chunk| chunk.path.iter().map(|point| point.position.distance_squared(position)).fold(f32::MAX, f32::min))
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+}
+
+pub(crate) fn stream_tracks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    shapes: Res<Assets<ExtrudeShape>>,
+    followers: Query<&GlobalTransform>,
+    mut tracks: Query<(Entity, &mut StreamedTrack)>,
+) {
+    for (entity, mut track) in &mut tracks {
+        let Some(shape) = shapes.get(&track.shape) else {
+            continue;
+        };
+        let Ok(follower) = followers.get(track.follower) else {
+            continue;
+        };
+
+        let position = follower.translation();
+        let mut current = track.current_chunk(position).unwrap_or(0);
+        while track.chunks.len() < current + 1 + track.chunks_ahead {
+            let path = track.extend();
+            let mesh = extrude::extrude_with_options(shape, &path, &track.options);
+            let aabb = mesh.compute_aabb().unwrap_or_default();
+            let index = track.next_index;
+            track.next_index += 1;
+
+            // Recycle the oldest chunk if the follower has left it far enough behind
+            let chunk = if current > track.chunks_behind {
+                let chunk = track.chunks.pop_front().unwrap();
+                current -= 1;
+                meshes.insert(&chunk.mesh, mesh);
+                commands.entity(chunk.entity).insert((aabb, ExtrudedChunk { index }, Name::new(format!("Chunk {index}"))));
+                StreamedChunk { path, ..chunk }
+            } else {
+                let handle = meshes.add(mesh);
+                let chunk = commands.spawn((
+                    PbrBundle {
+                        mesh: handle.clone(),
+                        material: track.material.clone(),
+                        ..default()
+                    },
+                    aabb,
+                    ExtrudedChunk { index },
+                    Name::new(format!("Chunk {index}")),
+                )).id();
+                commands.entity(entity).add_child(chunk);
+                StreamedChunk { entity: chunk, mesh: handle, path }
+            };
+            track.chunks.push_back(chunk);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+use crate::bezier::OrientedPoint;
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+use crate::geometry::frame_rotation;
+use crate::path::{cumulative_distances, interpolate_path};
+use crate::terrain::HeightSampler;
+
+/// Where and how often [`support_placements`] puts pillars under a path.
+#[derive(Debug, Clone)]
+pub struct SupportSettings {
+    /// About the distance along the path between pillars, rounded so they spread evenly with the first
+    /// and last half of it from the ends.
+    pub spacing: f32,
+    /// Pillars only stand where the path is at least this high above the ground.
+    pub min_gap: f32,
+    /// Where the pillars meet the cross-section, in its own plane, e.g. under each rail of a track.
+    pub attachments: Vec<Vec2>,
+    /// How far the pillars reach below the ground, so they don't float on slopes.
+    pub footing: f32,
+}
+
+impl Default for SupportSettings {
+    fn default() -> Self {
+        Self {
+            spacing: 10.,
+            min_gap: 1.,
+            attachments: vec![Vec2::ZERO],
+            footing: 0.,
+        }
+    }
+}
+
+impl SupportSettings {
+    pub fn with_spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn with_min_gap(mut self, min_gap: f32) -> Self {
+        self.min_gap = min_gap;
+        self
+    }
+
+    pub fn with_attachments(mut self, attachments: Vec<Vec2>) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    pub fn with_footing(mut self, footing: f32) -> Self {
+        self.footing = footing;
+        self
+    }
+}
+
+/// A pillar standing from the ground up to the path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SupportPlacement {
+    pub top: Vec3,
+    pub bottom: Vec3,
+    /// The distance along the path.
+    pub distance: f32,
+    /// Turned about Y only, so -Z follows the path.
+    pub rotation: Quat,
+}
+
+impl SupportPlacement {
+    pub fn height(&self) -> f32 {
+        self.top.y - self.bottom.y
+    }
+
+    /// Places a mesh one unit tall, standing on `y = 0`, stretched from the ground to the path.
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.bottom)
+            .with_rotation(self.rotation)
+            .with_scale(Vec3::new(1., self.height(), 1.))
+    }
+}
+
+/// Pillars every [`SupportSettings::spacing`] along `path` wherever it runs higher above the ground
+/// than [`SupportSettings::min_gap`]. Places the ground doesn't cover get no pillar.
+pub fn support_placements(path: &[OrientedPoint], sampler: &impl HeightSampler, settings: &SupportSettings) -> Vec<SupportPlacement> {
+    let distances = cumulative_distances(path);
+    let Some(&length) = distances.last() else {
+        return Vec::new();
+    };
+    if settings.spacing <= 0. {
+        return Vec::new();
+    }
+
+    let count = (length / settings.spacing).round().max(1.) as u32;
+    (0..count)
+        .map(|i| (i as f32 + 0.5) * length / count as f32)
+        .flat_map(|distance| {
+            let frame = interpolate_path(path, &distances, distance);
+            let forward = frame.forward().with_y(0.);
+            let rotation = if forward.length_squared() > f32::EPSILON {
+                Transform::IDENTITY.looking_to(forward, Vec3::Y).rotation
+            } else {
+                Quat::IDENTITY
+            };
+
+            settings.attachments.iter().filter_map(move |attachment| {
+                let top = frame.local_to_world(attachment.extend(0.));
+                let ground = sampler.height(top.x, top.z)?;
+                (top.y - ground >= settings.min_gap).then(|| SupportPlacement {
+                    top,
+                    bottom: top.with_y(ground - settings.footing),
+                    distance,
+                    rotation,
+                })
+            })
+        })
+        .collect()
+}
+
+/// Extrudes `shape` up each pillar and merges them into one mesh. The cross-section's Y follows the
+/// path and V runs up from 0 at the bottom.
+pub fn support_columns(placements: &[SupportPlacement], shape: &ExtrudeShape, options: &ExtrudeOptions) -> Mesh {
+    let paths: Vec<[OrientedPoint; 2]> = placements.iter()
+        .map(|placement| {
+            let rotation = frame_rotation(Vec3::Y, placement.rotation * Vec3::NEG_Z);
+            [
+                OrientedPoint::new(placement.bottom, rotation, 0.),
+                OrientedPoint::new(placement.top, rotation, placement.height()),
+            ]
+        })
+        .collect();
+
+    extrude::extrude_batch_merged(shape, &paths, options, true)
+}
+
+/// What stands under the path.
+#[derive(Debug, Clone)]
+pub enum Pillar {
+    /// A mesh one unit tall standing on `y = 0`, see [`SupportPlacement::transform`].
+    Mesh(Handle<Mesh>),
+    /// A cross-section extruded up each pillar with the default options, see [`support_columns`].
+    Column(ExtrudeShape),
+}
+
+/// Spawns the pillars as a parent entity with one child per pillar, or a single child holding every
+/// column, and returns the parent.
+pub fn spawn_supports(commands: &mut Commands, meshes: &mut Assets<Mesh>, placements: &[SupportPlacement], pillar: &Pillar, material: Handle<StandardMaterial>) -> Entity {
+    commands.spawn((SpatialBundle::default(), Name::new("Supports")))
+        .with_children(|parent| match pillar {
+            Pillar::Mesh(mesh) => {
+                for placement in placements {
+                    parent.spawn(PbrBundle {
+                        mesh: mesh.clone(),
+                        material: material.clone(),
+                        transform: placement.transform(),
+                        ..default()
+                    });
+                }
+            }
+            Pillar::Column(shape) => {
+                parent.spawn(PbrBundle {
+                    mesh: meshes.add(support_columns(placements, shape, &default())),
+                    material,
+                    ..default()
+                });
+            }
+        })
+        .id()
+}
@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+use crate::bezier::{reorient_path, OrientedPoint};
+use crate::bvh::{self, Bvh};
+use crate::geometry::frame_rotation;
+use crate::path::cumulative_distances;
+
+/// Triangles per leaf of a [`MeshSurface`].
+const LEAF_TRIANGLES: usize = 4;
+
+/// The ground paths are draped over, see [`drape_path`].
+pub trait HeightSampler {
+    /// The height of the ground at `(x, z)`, `None` off the terrain.
+    fn height(&self, x: f32, z: f32) -> Option<f32>;
+}
+
+impl<F: Fn(f32, f32) -> f32> HeightSampler for F {
+    fn height(&self, x: f32, z: f32) -> Option<f32> {
+        Some(self(x, z))
+    }
+}
+
+/// Moves every point of `path` to `clearance` above the ground, leaving points off the terrain where
+/// they are, and re-aims the frames along the new positions so the cross-section tilts with the slope.
+/// V is re-measured along the draped points, so textures don't stretch on the slopes.
+pub fn drape_path(path: &mut [OrientedPoint], sampler: &impl HeightSampler, clearance: f32) {
+    for point in path.iter_mut() {
+        if let Some(height) = sampler.height(point.position.x, point.position.z) {
+            point.position.y = height + clearance;
+        }
+    }
+    reorient_path(path);
+    remeasure_path(path);
+}
+
+/// Sets V to the distance travelled through the moved points, starting from the first point's V.
+fn remeasure_path(path: &mut [OrientedPoint]) {
+    let Some(start) = path.first().map(|point| point.v_coordinate) else {
+        return;
+    };
+    let distances = cumulative_distances(path);
+    for (point, distance) in path.iter_mut().zip(distances) {
+        point.v_coordinate = start + distance;
+    }
+}
+
+/// A regular grid of heights, interpolated bilinearly, e.g. loaded from a heightmap image.
+#[derive(Resource, Debug, Clone)]
+pub struct Heightmap {
+    /// The x and z of the first sample.
+    pub origin: Vec2,
+    /// The distance between neighbouring samples.
+    pub spacing: f32,
+    /// The number of samples along x.
+    pub width: usize,
+    /// Row by row along x, then z.
+    pub heights: Vec<f32>,
+}
+
+impl Heightmap {
+    pub fn new(origin: Vec2, spacing: f32, width: usize, heights: Vec<f32>) -> Self {
+        Self { origin, spacing, width: width.max(1), heights }
+    }
+
+    /// Reads the image's brightness as the height from 0 to `max_height`, one sample per pixel with
+    /// rows running along z. `None` for image formats that can't be converted.
+    pub fn from_image(image: &Image, origin: Vec2, spacing: f32, max_height: f32) -> Option<Self> {
+        let width = image.width() as usize;
+        let heights = image.clone().try_into_dynamic().ok()?.to_luma32f().into_raw();
+
+        Some(Self::new(origin, spacing, width, heights.into_iter().map(|h| h * max_height).collect()))
+    }
+
+    /// The number of samples along z.
+    pub fn depth(&self) -> usize {
+        self.heights.len() / self.width
+    }
+
+    fn sample(&self, x: usize, z: usize) -> f32 {
+        self.heights[z * self.width + x]
+    }
+}
+
+impl HeightSampler for Heightmap {
+    fn height(&self, x: f32, z: f32) -> Option<f32> {
+        let cell = (Vec2::new(x, z) - self.origin) / self.spacing;
+        let (max_x, max_z) = (self.width.checked_sub(1)?, self.depth().checked_sub(1)?);
+        if cell.x < 0. || cell.y < 0. || cell.x > max_x as f32 || cell.y > max_z as f32 {
+            return None;
+        }
+
+        let (x0, z0) = ((cell.x as usize).min(max_x.saturating_sub(1)), (cell.y as usize).min(max_z.saturating_sub(1)));
+        let (x1, z1) = ((x0 + 1).min(max_x), (z0 + 1).min(max_z));
+        let (fx, fz) = (cell.x - x0 as f32, cell.y - z0 as f32);
+        let near = self.sample(x0, z0) + (self.sample(x1, z0) - self.sample(x0, z0)) * fx;
+        let far = self.sample(x0, z1) + (self.sample(x1, z1) - self.sample(x0, z1)) * fx;
+
+        Some(near + (far - near) * fz)
+    }
+}
+
+/// How [`project_path`] moves each point onto the surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SurfaceProjection {
+    /// Along this direction, e.g. `Vec3::NEG_Y` to drop straight down. The first surface met starting
+    /// `max_distance` behind the point wins, so points slightly below the ground come back up.
+    Along(Vec3),
+    /// To the nearest point of the surface, for overhangs, walls and tunnels.
+    Nearest,
+}
+
+/// The triangles of arbitrary meshes in world space with a bounding volume hierarchy, to project paths
+/// onto sculpted terrain with [`project_path`], or as a [`HeightSampler`] for terrain without a
+/// heightmap. Normals are interpolated from the meshes' own when they have them, so roads follow
+/// smooth shading rather than the facets.
+#[derive(Debug, Clone, Default)]
+pub struct MeshSurface {
+    triangles: Vec<[Vec3; 3]>,
+    normals: Vec<[Vec3; 3]>,
+    bvh: Bvh,
+}
+
+/// Where a query met a [`MeshSurface`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfacePoint {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+impl MeshSurface {
+    /// Collects the triangles of `meshes`, each placed by its transform.
+    pub fn new<'a>(meshes: impl IntoIterator<Item = (&'a Mesh, Transform)>) -> Self {
+        let (triangles, normals): (Vec<[Vec3; 3]>, Vec<[Vec3; 3]>) = meshes.into_iter()
+            .flat_map(|(mesh, transform)| world_triangles(mesh, &transform))
+            .unzip();
+        let boxes: Vec<(Vec3, Vec3)> = triangles.iter().map(|triangle| bvh::bounds(triangle)).collect();
+        let (bvh, order) = Bvh::sorted(&boxes, LEAF_TRIANGLES);
+
+        Self {
+            triangles: order.iter().map(|&i| triangles[i]).collect(),
+            normals: order.iter().map(|&i| normals[i]).collect(),
+            bvh,
+        }
+    }
+
+    /// The first hit of `ray` within `max_distance`, from either side of the surface.
+    pub fn raycast(&self, ray: Ray3d, max_distance: f32) -> Option<SurfacePoint> {
+        let direction = Vec3::from(ray.direction);
+        self.bvh.raycast(ray, max_distance, |triangle, _| {
+            let (distance, weights) = bvh::ray_triangle(ray.origin, direction, self.triangles[triangle])?;
+            Some((distance, self.point(triangle, ray.origin + direction * distance, weights, distance)))
+        })
+    }
+
+    /// The point of the surface nearest to `point`, if one is within `max_distance`.
+    pub fn closest_point(&self, point: Vec3, max_distance: f32) -> Option<SurfacePoint> {
+        self.bvh.closest_point(point, max_distance, |triangle, _| {
+            let (closest, weights) = bvh::closest_on_triangle(point, self.triangles[triangle]);
+            let distance = closest.distance(point);
+            Some((distance, self.point(triangle, closest, weights, distance)))
+        })
+    }
+
+    fn point(&self, triangle: usize, point: Vec3, weights: Vec3, distance: f32) -> SurfacePoint {
+        let [a, b, c] = self.normals[triangle];
+
+        SurfacePoint {
+            point,
+            normal: (a * weights.x + b * weights.y + c * weights.z).normalize_or_zero(),
+            distance,
+        }
+    }
+}
+
+/// The highest surface at `(x, z)`, found with a ray cast straight down from above all the triangles.
+impl HeightSampler for MeshSurface {
+    fn height(&self, x: f32, z: f32) -> Option<f32> {
+        let (min, max) = self.bvh.bounds()?;
+        let top = max.y + 1.;
+        self.raycast(Ray3d::new(Vec3::new(x, top, z), Vec3::NEG_Y), top - min.y + 1.)
+            .map(|hit| hit.point.y)
+    }
+}
+
+/// Moves every point of `path` onto `surface`, `clearance` above it along the surface normal, and turns
+/// the frames so their up follows the normal while they keep facing along the path. Points with no
+/// surface within `max_distance` keep their position and are only re-aimed along the path. V is
+/// re-measured along the projected points.
+pub fn project_path(path: &mut [OrientedPoint], surface: &MeshSurface, projection: SurfaceProjection, clearance: f32, max_distance: f32) {
+    let normals: Vec<Option<Vec3>> = path.iter_mut()
+        .map(|point| {
+            let hit = match projection {
+                SurfaceProjection::Along(direction) => {
+                    let direction = Dir3::new(direction).ok()?;
+                    surface.raycast(Ray3d::new(point.position - direction * max_distance, *direction), max_distance * 2.)
+                }
+                SurfaceProjection::Nearest => surface.closest_point(point.position, max_distance),
+            }?;
+            // Faces hit from behind still lift the path off the side it came from
+            let normal = match projection {
+                SurfaceProjection::Along(direction) if hit.normal.dot(direction) > 0. => -hit.normal,
+                _ => hit.normal,
+            };
+            point.position = hit.point + normal * clearance;
+            Some(normal)
+        })
+        .collect();
+
+    reorient_path(path);
+    for (point, normal) in path.iter_mut().zip(normals) {
+        let forward = point.rotation * Vec3::NEG_Z;
+        if let Some(normal) = normal.filter(|normal| forward.cross(*normal).length_squared() > f32::EPSILON) {
+            point.rotation = frame_rotation(forward, normal);
+        }
+    }
+    remeasure_path(path);
+}
+
+/// The mesh's triangles and their vertex normals, placed by `transform`. Meshes without normals get
+/// the face normal of each triangle.
+fn world_triangles(mesh: &Mesh, transform: &Transform) -> Vec<([Vec3; 3], [Vec3; 3])> {
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return Vec::new();
+    };
+    let matrix = transform.compute_matrix();
+    let normal_matrix = Mat3::from_mat4(matrix).inverse().transpose();
+    let positions: Vec<Vec3> = positions.iter().map(|p| matrix.transform_point3(Vec3::from_array(*p))).collect();
+    let normals: Option<Vec<Vec3>> = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals)) => Some(normals.iter().map(|n| (normal_matrix * Vec3::from_array(*n)).normalize_or_zero()).collect()),
+        _ => None,
+    };
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+
+    indices.chunks_exact(3)
+        .filter(|triangle| triangle.iter().all(|&i| i < positions.len()))
+        .map(|triangle| {
+            let corners = [triangle[0], triangle[1], triangle[2]].map(|i| positions[i]);
+            let vertex_normals = match &normals {
+                Some(normals) => [triangle[0], triangle[1], triangle[2]].map(|i| normals[i]),
+                None => [(corners[1] - corners[0]).cross(corners[2] - corners[0]).normalize_or_zero(); 3],
+            };
+            (corners, vertex_normals)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heightmap_interpolates_bilinearly() {
+        let heightmap = Heightmap::new(Vec2::new(10., 20.), 2., 2, vec![0., 1., 2., 3.]);
+        assert_eq!(heightmap.height(10., 20.), Some(0.));
+        assert_eq!(heightmap.height(11., 21.), Some(1.5));
+        assert_eq!(heightmap.height(12., 21.), Some(2.));
+        assert_eq!(heightmap.height(9., 21.), None);
+    }
+
+    #[test]
+    fn drape_follows_the_slope() {
+        let mut path = crate::path::linear(Vec3::X, 10., 10);
+        drape_path(&mut path, &|x: f32, _z: f32| x, 1.);
+        for point in path.iter() {
+            assert!((point.position.y - point.position.x - 1.).abs() < 1e-5);
+            assert!(point.forward().distance(Vec3::new(1., 1., 0.).normalize()) < 1e-5);
+        }
+        assert!((path[10].v_coordinate - 10. * std::f32::consts::SQRT_2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn project_onto_a_tilted_plane() {
+        let plane = Mesh::from(Plane3d::default().mesh().size(40., 40.));
+        let rotation = Quat::from_rotation_z(0.25);
+        let surface = MeshSurface::new([(&plane, Transform::from_xyz(0., 3., 0.).with_rotation(rotation))]);
+        let normal = rotation * Vec3::Y;
+
+        let mut path = crate::path::linear(Vec3::NEG_Z, 8., 4);
+        for point in path.iter_mut() {
+            point.position += Vec3::new(2., 10., 0.);
+        }
+        project_path(&mut path, &surface, SurfaceProjection::Along(Vec3::NEG_Y), 0.5, 20.);
+        for point in path.iter() {
+            // On the plane, lifted along its normal, with the frame's up following it
+            let above = (point.position - Vec3::new(0., 3., 0.)).dot(normal);
+            assert!((above - 0.5).abs() < 1e-4, "{above}");
+            assert!((point.rotation * Vec3::Y).distance(normal) < 1e-4);
+        }
+
+        assert_eq!(surface.height(2., 0.).map(|y| (y * 1e4).round() / 1e4), Some(((3. + 2. * 0.25f32.tan()) * 1e4).round() / 1e4));
+        assert_eq!(surface.height(50., 0.), None);
+    }
+}
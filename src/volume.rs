@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use crate::bezier::OrientedPoint;
+use crate::extrude::{self, ExtrudeOptions, ExtrudeShape};
+use crate::path::{cumulative_distances, interpolate_path};
+
+/// The solid swept by the closed outlines of a cross-section, capped at both ends of the path, for
+/// trigger volumes along a tunnel or keeping AI inside a track corridor. Points are located on the path
+/// by the nearest point of its polyline, so the queries are approximate where the path bends more
+/// tightly than the cross-section is wide. [`ExtrudeOptions::fold_handling`] clamping is ignored.
+#[derive(Debug, Clone)]
+pub struct ExtrudedVolume {
+    /// The closed outlines; a point is inside when an odd number of them hold it, so holes work.
+    outlines: Vec<Vec<Vec2>>,
+    path: Vec<OrientedPoint>,
+    distances: Vec<f32>,
+    offset: Vec2,
+}
+
+impl ExtrudedVolume {
+    /// `None` if the shape has no closed outline or the path is empty.
+    pub fn new(shape: &ExtrudeShape, path: &[OrientedPoint], options: &ExtrudeOptions) -> Option<Self> {
+        let outlines: Vec<Vec<Vec2>> = shape.outlines().into_iter()
+            .filter(|(points, closed)| *closed && points.len() > 2)
+            .map(|(points, _)| points)
+            .collect();
+        let path = extrude::extruded_path(shape, path, options).into_owned();
+        if outlines.is_empty() || path.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            outlines,
+            distances: cumulative_distances(&path),
+            path,
+            offset: options.offset,
+        })
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        self.signed_distance(point) <= 0.
+    }
+
+    /// The distance from `point` to the surface of the volume, negative inside.
+    pub fn signed_distance(&self, point: Vec3) -> f32 {
+        let (along, length) = self.project(point);
+        let frame = interpolate_path(&self.path, &self.distances, along);
+        let local = frame.rotation.inverse() * (point - frame.position);
+
+        // Across the path, against the cross-section as scaled at this point
+        let scale = frame.scale.truncate();
+        let local_2d = local.truncate();
+        let in_shape = (local_2d - self.offset) / scale;
+        let mut across = f32::INFINITY;
+        let mut inside = false;
+        for outline in &self.outlines {
+            inside ^= in_shape.is_finite() && extrude::polygon_contains(outline, in_shape);
+            for i in 0..outline.len() {
+                let (a, b) = (outline[i], outline[(i + 1) % outline.len()]);
+                across = across.min(segment_distance(local_2d, a * scale + self.offset, b * scale + self.offset));
+            }
+        }
+        let across = if inside { -across } else { across };
+
+        // Along the path, against the end caps. The frames face -Z, so points behind the start have z > 0
+        let beyond = if along <= 0. {
+            local.z
+        } else if along >= length {
+            -local.z
+        } else {
+            0.
+        };
+        let along = if beyond > 0. { beyond } else { -along.min(length - along) };
+
+        let outside = Vec2::new(across, along).max(Vec2::ZERO).length();
+        across.max(along).min(0.) + outside
+    }
+
+    /// The corners of a box around the volume.
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        self.path.iter()
+            .flat_map(|frame| self.outlines.iter().flatten().map(move |p| frame.position + frame.rotation * (*p * frame.scale.truncate() + self.offset).extend(0.)))
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), p| (min.min(p), max.max(p)))
+    }
+
+    /// Samples the signed distance on a grid of `cell_size` cells covering the volume and `padding`
+    /// around it.
+    pub fn sdf_grid(&self, cell_size: f32, padding: f32) -> SdfGrid {
+        let (min, max) = self.bounds();
+        let (min, max) = (min - Vec3::splat(padding), max + Vec3::splat(padding));
+        let size = ((max - min) / cell_size).ceil().as_uvec3() + UVec3::ONE;
+        self.sample_grid(min, cell_size, size)
+    }
+
+    /// Samples the signed distance on a grid of `size` samples `cell_size` apart from `origin`, e.g.
+    /// one chunk of a voxel world. Z slices are sampled in parallel on the `ComputeTaskPool`.
+    pub fn sample_grid(&self, origin: Vec3, cell_size: f32, size: UVec3) -> SdfGrid {
+        let slice = |z: u32| -> Vec<f32> {
+            (0..size.y)
+                .flat_map(|y| (0..size.x).map(move |x| UVec3::new(x, y, z)))
+                .map(|cell| self.signed_distance(origin + cell.as_vec3() * cell_size))
+                .collect()
+        };
+        let slices: Vec<Vec<f32>> = ComputeTaskPool::get_or_init(TaskPool::default).scope(|scope| {
+            for z in 0..size.z {
+                scope.spawn(async move { slice(z) });
+            }
+        });
+
+        SdfGrid {
+            origin,
+            cell_size,
+            size,
+            values: slices.concat(),
+        }
+    }
+
+    /// The distance along the path of its polyline's point nearest to `point`, and the path's length.
+    fn project(&self, point: Vec3) -> (f32, f32) {
+        let length = self.distances[self.distances.len() - 1];
+        let along = self.path.windows(2)
+            .zip(&self.distances)
+            .map(|(pair, start)| {
+                let (a, b) = (pair[0].position, pair[1].position);
+                let segment = b - a;
+                let f = if segment.length_squared() > 0. { ((point - a).dot(segment) / segment.length_squared()).clamp(0., 1.) } else { 0. };
+                ((a + segment * f).distance_squared(point), start + segment.length() * f)
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or(0., |(_, along)| along);
+
+        (along, length)
+    }
+}
+
+fn segment_distance(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let segment = b - a;
+    let f = if segment.length_squared() > 0. { ((point - a).dot(segment) / segment.length_squared()).clamp(0., 1.) } else { 0. };
+    (a + segment * f).distance(point)
+}
+
+/// Signed distances sampled at the points of a regular grid, see [`ExtrudedVolume::sdf_grid`], to
+/// feed marching cubes or destructible terrain.
+#[derive(Debug, Clone)]
+pub struct SdfGrid {
+    /// The position of the sample at (0, 0, 0).
+    pub origin: Vec3,
+    pub cell_size: f32,
+    /// The number of samples along each axis.
+    pub size: UVec3,
+    /// Ordered by X, then Y, then Z. Negative inside the volume.
+    pub values: Vec<f32>,
+}
+
+impl SdfGrid {
+    pub fn index(&self, cell: UVec3) -> usize {
+        (cell.x + self.size.x * (cell.y + self.size.y * cell.z)) as usize
+    }
+
+    pub fn get(&self, cell: UVec3) -> Option<f32> {
+        cell.cmplt(self.size).all().then(|| self.values[self.index(cell)])
+    }
+
+    pub fn position(&self, cell: UVec3) -> Vec3 {
+        self.origin + cell.as_vec3() * self.cell_size
+    }
+
+    /// Whether each sample is inside the volume, in the order of [`SdfGrid::values`], for voxel engines.
+    pub fn voxels(&self) -> Vec<bool> {
+        self.values.iter().map(|value| *value <= 0.).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2 by 2 square tube 10 long, running from the origin along -Z.
+    fn tube() -> ExtrudedVolume {
+        let outline = [Vec2::new(1., 1.), Vec2::new(-1., 1.), Vec2::new(-1., -1.), Vec2::new(1., -1.)];
+        let shape = ExtrudeShape::from_outline(&outline, true);
+        ExtrudedVolume::new(&shape, &crate::path::linear(Vec3::NEG_Z, 10., 10), &ExtrudeOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn signed_distance() {
+        let volume = tube();
+        assert!((volume.signed_distance(Vec3::new(0., 0., -5.)) + 1.).abs() < 1e-5);
+        assert!((volume.signed_distance(Vec3::new(0.5, 0., -5.)) + 0.5).abs() < 1e-5);
+        assert!((volume.signed_distance(Vec3::new(3., 0., -5.)) - 2.).abs() < 1e-5);
+        // Beyond the caps, and past a corner of one
+        assert!((volume.signed_distance(Vec3::new(0., 0., 2.)) - 2.).abs() < 1e-5);
+        assert!((volume.signed_distance(Vec3::new(0., 5., -13.)) - 5.).abs() < 1e-5);
+        assert!(volume.contains(Vec3::new(0.9, -0.9, -0.1)) && !volume.contains(Vec3::new(1.1, 0., -5.)));
+    }
+
+    #[test]
+    fn sdf_grid_samples_the_volume() {
+        let volume = tube();
+        let grid = volume.sdf_grid(0.5, 1.);
+        let (min, max) = volume.bounds();
+        assert!(grid.origin.cmple(min - 1.).all());
+        assert!((grid.origin + (grid.size - UVec3::ONE).as_vec3() * grid.cell_size).cmpge(max + 1.).all());
+        for cell in [UVec3::ZERO, grid.size / 2, UVec3::new(3, 4, 5)] {
+            assert_eq!(grid.get(cell), Some(volume.signed_distance(grid.position(cell))));
+        }
+        assert_eq!(grid.get(grid.size), None);
+        let inside = grid.voxels().iter().filter(|inside| **inside).count();
+        assert_eq!(inside, grid.values.iter().filter(|value| **value <= 0.).count());
+    }
+}